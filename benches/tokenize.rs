@@ -0,0 +1,36 @@
+use std::collections::BTreeSet;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use cli_bloom::{HashFunction, distinct_token_hashes};
+
+// Tokenization before the scanner: a normalized string allocated for each token, deduplicated in a set
+fn allocating_token_hashes(hash: HashFunction, text: &str) -> Vec<u64> {
+    let tokens: BTreeSet<String> = text.split_whitespace()
+        .map(|word| word.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect::<String>())
+        .filter(|token| !token.is_empty())
+        .collect();
+    tokens.iter().map(|token| hash.hash(token, 0)).collect()
+}
+
+// About 1 MB of source code like text, mostly lowercase words with some punctuation and capitals
+fn sample_text() -> String {
+    let mut text = String::new();
+    let mut position = 0;
+    while text.len() < 1024 * 1024 {
+        text.push_str(&format!("let value{} = Index::new(rate); // tokenize and hash the content of file{}\n", position % 5000, position % 700));
+        position += 1;
+    }
+    text
+}
+
+fn tokenize(c: &mut Criterion) {
+    let text = sample_text();
+    let mut group = c.benchmark_group("tokenize");
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.bench_function("allocating", |b| b.iter(|| allocating_token_hashes(HashFunction::Fnv, black_box(&text))));
+    group.bench_function("scanner", |b| b.iter(|| distinct_token_hashes(HashFunction::Fnv, black_box(&text))));
+    group.bench_function("scanner_xxhash", |b| b.iter(|| distinct_token_hashes(HashFunction::XxHash, black_box(&text))));
+    group.finish();
+}
+
+criterion_group!(benches, tokenize);
+criterion_main!(benches);
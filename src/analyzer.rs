@@ -0,0 +1,145 @@
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use serde::{Serialize, Deserialize};
+
+/// How the text of the documents and the searched keywords are turned into tokens.
+///
+/// The analyzer of an index is kept in its dump, and applied to the keywords of every search, so that a restored index matches the tokens it was built with.
+/// Tokens are always lowercased and stripped of punctuation, by the filters themselves. The default analyzer changes nothing else.
+///
+/// # Example
+///
+/// ```
+/// # use cli_bloom::{Analyzer, FsIndex};
+/// let mut fs_index = FsIndex::new(0.00001);
+/// fs_index.set_analyzer(Analyzer {
+///     stemming: true,
+///     stop_words: vec!["the".to_string()].into_iter().collect(),
+///     ..Analyzer::default()
+/// });
+/// fs_index.append("doc", "The queries");
+/// assert!(fs_index.search("query").is_some());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Analyzer {
+    /// Split words on every character which is not alphanumeric (`long-lived` gives `long` and `lived`), instead of on whitespace only (`longlived`).
+    #[serde(skip_serializing_if = "is_false")]
+    pub split_words: bool,
+    /// Remove the diacritics of latin letters, `été` gives `ete`.
+    #[serde(skip_serializing_if = "is_false")]
+    pub fold_diacritics: bool,
+    /// Tokens neither indexed nor searched, lowercase and without punctuation, e.g. `the`.
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub stop_words: BTreeSet<String>,
+    /// Reduce English plurals of more than 3 letters to their singular (`queries` gives `query`), with the S-stemmer of Harman.
+    #[serde(skip_serializing_if = "is_false")]
+    pub stemming: bool
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl Analyzer {
+    pub(crate) fn is_default(&self) -> bool {
+        *self == Analyzer::default()
+    }
+
+    /// Return the tokens of `text` separated by spaces, as inserted in the filters.
+    pub fn analyze<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.is_default() {
+            return Cow::Borrowed(text);
+        }
+        let words: Box<dyn Iterator<Item = &str>> = if self.split_words {
+            Box::new(text.split(|c: char| !c.is_alphanumeric()))
+        } else {
+            Box::new(text.split_whitespace())
+        };
+        let mut analyzed = String::with_capacity(text.len());
+        for word in words {
+            let mut token: String = word.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect();
+            if self.fold_diacritics {
+                token = fold_diacritics(&token);
+            }
+            if token.is_empty() || self.stop_words.contains(&token) {
+                continue;
+            }
+            if self.stemming {
+                token = stem(&token);
+            }
+            if !analyzed.is_empty() {
+                analyzed.push(' ');
+            }
+            analyzed.push_str(&token);
+        }
+        Cow::Owned(analyzed)
+    }
+}
+
+fn fold_diacritics(token: &str) -> String {
+    let mut folded = String::with_capacity(token.len());
+    for c in token.chars() {
+        match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => folded.push('a'),
+            'ç' => folded.push('c'),
+            'è' | 'é' | 'ê' | 'ë' => folded.push('e'),
+            'ì' | 'í' | 'î' | 'ï' => folded.push('i'),
+            'ñ' => folded.push('n'),
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => folded.push('o'),
+            'ù' | 'ú' | 'û' | 'ü' => folded.push('u'),
+            'ý' | 'ÿ' => folded.push('y'),
+            'æ' => folded.push_str("ae"),
+            'œ' => folded.push_str("oe"),
+            'ß' => folded.push_str("ss"),
+            _ => folded.push(c)
+        }
+    }
+    folded
+}
+
+// Harman, "How Effective Is Suffixing?", 1991
+fn stem(token: &str) -> String {
+    if token.chars().count() <= 3 {
+        return token.to_string();
+    }
+    if token.ends_with("ies") && !token.ends_with("eies") && !token.ends_with("aies") {
+        format!("{}y", &token[..token.len() - 3])
+    } else if token.ends_with("es") && !token.ends_with("aes") && !token.ends_with("ees") && !token.ends_with("oes") {
+        token[..token.len() - 1].to_string()
+    } else if token.ends_with('s') && !token.ends_with("us") && !token.ends_with("ss") {
+        token[..token.len() - 1].to_string()
+    } else {
+        token.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_analyzer_keeps_text() {
+        assert!(matches!(Analyzer::default().analyze("Word1, word2"), Cow::Borrowed("Word1, word2")));
+    }
+
+    #[test]
+    fn analyze_text() {
+        let analyzer = Analyzer {
+            split_words: true,
+            fold_diacritics: true,
+            stop_words: vec!["the".to_string()].into_iter().collect(),
+            stemming: true
+        };
+        assert_eq!("long lived query ete file glass bus is", analyzer.analyze("The long-lived Queries, été: files glass bus is"));
+    }
+
+    #[test]
+    fn s_stemmer() {
+        assert_eq!("query", stem("queries"));
+        assert_eq!("file", stem("files"));
+        assert_eq!("toe", stem("toes"));
+        assert_eq!("corpus", stem("corpus"));
+        assert_eq!("has", stem("has"));
+    }
+}
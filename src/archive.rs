@@ -0,0 +1,142 @@
+use std::collections::BTreeSet;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+use flate2::read::GzDecoder;
+use sha2::{Sha256, Digest};
+use crate::analyzer::Analyzer;
+use crate::errors::Error;
+use crate::tokenize::for_each_token;
+
+const EXTENSIONS: [&str; 5] = [".tar", ".tar.gz", ".tgz", ".tar.zst", ".tzst"];
+
+// Bytes read from an entry at a time
+const BUFFER_SIZE: usize = 64 * 1024;
+
+// A word longer than this is tokenized in pieces, so that a file without whitespace cannot exhaust the memory
+const MAX_WORD: usize = 1024 * 1024;
+
+// Whether the file at `path` is a tar archive, optionally compressed with gzip or zstd, from its name
+pub(crate) fn is_archive(path: &Path) -> bool {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default().to_ascii_lowercase();
+    EXTENSIONS.iter().any(|extension| name.ends_with(extension))
+}
+
+// SHA-256 digest of the content of `reader`, in hexadecimal, and its length
+pub(crate) fn stream_hash<R: Read>(mut reader: R) -> io::Result<(String, u64)> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; BUFFER_SIZE];
+    let mut size = 0;
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok((format!("{:x}", hasher.finalize()), size));
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+}
+
+// Call `f` with the path and the distinct analyzed tokens, separated by spaces, of each text file of the archive at `path` read from `reader`.
+// Entries are streamed through the decompressor, so neither the archive nor an entry is ever held in memory, only the vocabulary of the current entry.
+// Entries which are not UTF-8 text are left out.
+pub(crate) fn for_each_entry<'a, R, F>(path: &Path, reader: R, analyzer: &Analyzer, mut f: F) -> Result<(), Error>
+where R: Read + 'a, F: FnMut(&str, &str) -> Result<(), Error> {
+    let mut archive = tar::Archive::new(decompressed(path, reader)?);
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let entry_path = entry.path()?.to_string_lossy().into_owned();
+        if let Some(tokens) = distinct_tokens(entry, analyzer)? {
+            f(&entry_path, &tokens)?;
+        }
+    }
+    Ok(())
+}
+
+// The tar stream of the archive at `path` read from `reader`, decompressed according to its extension
+pub(crate) fn decompressed<'a, R: Read + 'a>(path: &Path, reader: R) -> io::Result<Box<dyn Read + 'a>> {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default().to_ascii_lowercase();
+    Ok(if name.ends_with(".gz") || name.ends_with(".tgz") {
+        Box::new(GzDecoder::new(reader))
+    } else if name.ends_with(".zst") || name.ends_with(".tzst") {
+        Box::new(zstd::Decoder::new(reader)?)
+    } else {
+        Box::new(reader)
+    })
+}
+
+// Distinct tokens of the text read from `reader`, or `None` if it is not UTF-8
+fn distinct_tokens<R: Read>(mut reader: R, analyzer: &Analyzer) -> io::Result<Option<String>> {
+    let mut tokens = BTreeSet::new();
+    let mut buffer = vec![0; BUFFER_SIZE];
+    // Bytes of an incomplete character or word at the end of the previous read
+    let mut pending = Vec::new();
+    loop {
+        let read = reader.read(&mut buffer)?;
+        pending.extend_from_slice(&buffer[..read]);
+        let valid = match std::str::from_utf8(&pending) {
+            Ok(text) => text.len(),
+            Err(error) if error.error_len().is_none() && read > 0 => error.valid_up_to(),
+            Err(_) => return Ok(None)
+        };
+        let text = std::str::from_utf8(&pending[..valid]).unwrap();
+        let end = if read == 0 || text.len() > MAX_WORD {
+            text.len()
+        } else {
+            text.rfind(char::is_whitespace).map(|position| position + text[position..].chars().next().unwrap().len_utf8()).unwrap_or(0)
+        };
+        for_each_token(&analyzer.analyze(&text[..end]), |token| {
+            if !tokens.contains(token) {
+                tokens.insert(token.to_string());
+            }
+        });
+        pending.drain(..end);
+        if read == 0 {
+            let tokens: Vec<String> = tokens.into_iter().collect();
+            return Ok(Some(tokens.join(" ")));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_across_reads() {
+        // Reads of 3 bytes split the words and the é
+        struct Trickle<'a>(&'a [u8]);
+        impl<'a> Read for Trickle<'a> {
+            fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+                let read = self.0.len().min(buffer.len()).min(3);
+                buffer[..read].copy_from_slice(&self.0[..read]);
+                self.0 = &self.0[read..];
+                Ok(read)
+            }
+        }
+        let tokens = distinct_tokens(Trickle("word1 été Word1\nword22".as_bytes()), &Analyzer::default()).unwrap();
+        assert_eq!(Some("word1 word22 été".to_string()), tokens);
+        assert_eq!(None, distinct_tokens(Trickle(b"word1 \xff"), &Analyzer::default()).unwrap());
+    }
+
+    #[test]
+    fn stream_tar_entries() {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, content) in [("a.txt", &b"word1 word2"[..]), ("b.bin", &b"\x00\xff"[..]), ("c/d.txt", &b"word3"[..])] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_cksum();
+            builder.append_data(&mut header, name, content).unwrap();
+        }
+        let archive = builder.into_inner().unwrap();
+        let mut entries = Vec::new();
+        for_each_entry(Path::new("docs.tar"), archive.as_slice(), &Analyzer::default(), |path, tokens| {
+            entries.push(format!("{}={}", path, tokens));
+            Ok(())
+        }).unwrap();
+        assert_eq!(vec!["a.txt=word1 word2", "c/d.txt=word3"], entries);
+    }
+}
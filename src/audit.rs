@@ -0,0 +1,67 @@
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use serde::Serialize;
+
+/// What happened to a file during ingestion.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    /// The file content was indexed.
+    Ingested,
+    /// The file was already indexed with the same content.
+    Unchanged,
+    /// The file was left out, e.g. because it is not a text file.
+    Skipped,
+    /// The file could not be ingested.
+    Failed
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    timestamp: u64,
+    path: &'a Path,
+    bytes: u64,
+    outcome: Outcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<&'a str>
+}
+
+// Append-only JSONL log with one record per file processed by ingestion
+pub(crate) struct AuditLog {
+    file: File
+}
+
+impl AuditLog {
+    pub(crate) fn open(path: &str) -> Self {
+        let file = OpenOptions::new().create(true).append(true).open(path).expect(format!("Impossible to open audit log {}", &path).as_str());
+        AuditLog {
+            file
+        }
+    }
+
+    // Another handle appending to the same audit log
+    pub(crate) fn try_clone(&self) -> io::Result<Self> {
+        Ok(AuditLog {
+            file: self.file.try_clone()?
+        })
+    }
+
+    pub(crate) fn record(&mut self, path: &Path, outcome: Outcome, reason: Option<&str>) {
+        let record = Record {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0),
+            path,
+            bytes: fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0),
+            outcome,
+            reason
+        };
+        let mut line = serde_json::to_string(&record).expect("Impossible to serialize audit record");
+        line.push('\n');
+        self.file.write_all(line.as_bytes()).expect("Impossible to write audit log");
+    }
+}
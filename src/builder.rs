@@ -0,0 +1,213 @@
+use std::path::Path;
+use crate::analyzer::Analyzer;
+use crate::file_system::FileSystem;
+use crate::filter::error_rate_for;
+use crate::fs_loader::FsIndex;
+use crate::membership::{Backend, HashFunction};
+use crate::retry::RetryPolicy;
+use crate::store::{IndexStore, MemoryStore};
+
+/// Builder of a [`FsIndex`] with non default options.
+///
+/// # Example
+///
+/// ```
+/// # use cli_bloom::FsIndexBuilder;
+/// let fs_index = FsIndexBuilder::new(0.00001)
+///     .fail_fast(true)
+///     .build();
+/// ```
+pub struct FsIndexBuilder<S = MemoryStore> {
+    error_rate: f32,
+    bits_per_token: Option<f64>,
+    hash_functions: Option<u32>,
+    store: S,
+    audit_log: Option<String>,
+    fail_fast: bool,
+    retry: RetryPolicy,
+    chunk_size: Option<usize>,
+    paragraphs: bool,
+    subtitle_cues: Option<usize>,
+    key_mapper: Option<Box<dyn Fn(&Path) -> String + Send + Sync>>,
+    key_prefix: Option<String>,
+    backend: Backend,
+    hash_function: HashFunction,
+    analyzer: Analyzer,
+    file_system: Option<Box<dyn FileSystem>>
+}
+
+impl FsIndexBuilder {
+    /// Constructs a builder of an in memory `FsIndex` with an acceptable `error_rate` for false positive results.
+    pub fn new(error_rate: f32) -> Self {
+        FsIndexBuilder {
+            error_rate,
+            bits_per_token: None,
+            hash_functions: None,
+            store: MemoryStore::default(),
+            audit_log: None,
+            fail_fast: false,
+            retry: RetryPolicy::none(),
+            chunk_size: None,
+            paragraphs: false,
+            subtitle_cues: None,
+            key_mapper: None,
+            key_prefix: None,
+            backend: Backend::default(),
+            hash_function: HashFunction::default(),
+            analyzer: Analyzer::default(),
+            file_system: None
+        }
+    }
+}
+
+impl<S: IndexStore> FsIndexBuilder<S> {
+    /// Keep the filters in `store`, see [`FsIndex::with_store`].
+    pub fn store<T: IndexStore>(self, store: T) -> FsIndexBuilder<T> {
+        FsIndexBuilder {
+            error_rate: self.error_rate,
+            bits_per_token: self.bits_per_token,
+            hash_functions: self.hash_functions,
+            store,
+            audit_log: self.audit_log,
+            fail_fast: self.fail_fast,
+            retry: self.retry,
+            chunk_size: self.chunk_size,
+            paragraphs: self.paragraphs,
+            subtitle_cues: self.subtitle_cues,
+            key_mapper: self.key_mapper,
+            key_prefix: self.key_prefix,
+            backend: self.backend,
+            hash_function: self.hash_function,
+            analyzer: self.analyzer,
+            file_system: self.file_system
+        }
+    }
+
+    /// Size the filters with `bits` bits per distinct token, instead of deriving them from the error rate.
+    ///
+    /// The error rate of the index becomes the one of such filters, so the choice is kept in the dump.
+    pub fn bits_per_token(mut self, bits: f64) -> Self {
+        self.bits_per_token = Some(bits);
+        self
+    }
+
+    /// Use `count` hash functions in the filters, instead of deriving them from the error rate.
+    ///
+    /// The hash functions count and the bits per token go together: the count is the smallest integer above bits per token × ln(2).
+    /// Given alone, the bits per token are the middle of the range using `count` hash functions.
+    pub fn hash_functions(mut self, count: u32) -> Self {
+        self.hash_functions = Some(count);
+        self
+    }
+
+    /// Append an audit record to the file at `path` for every file processed, see [`FsIndex::set_audit_log`].
+    pub fn audit_log(mut self, path: &str) -> Self {
+        self.audit_log = Some(path.to_string());
+        self
+    }
+
+    /// Abort ingestion on the first file which cannot be read, see [`FsIndex::set_fail_fast`].
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Read again files failing with a transient error, see [`FsIndex::set_retry_policy`].
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Split the text of files larger than `size` bytes into chunks, see [`FsIndex::set_chunk_size`].
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = Some(size);
+        self
+    }
+
+    /// Index each paragraph of the files as a document, see [`FsIndex::set_paragraphs`].
+    pub fn paragraphs(mut self, paragraphs: bool) -> Self {
+        self.paragraphs = paragraphs;
+        self
+    }
+
+    /// Index groups of `cues` consecutive cues of the subtitle files, see [`FsIndex::set_subtitle_cues`].
+    pub fn subtitle_cues(mut self, cues: usize) -> Self {
+        self.subtitle_cues = Some(cues);
+        self
+    }
+
+    /// Key the documents of each ingested file with `mapper`, see [`FsIndex::set_key_mapper`].
+    pub fn key_mapper<F: Fn(&Path) -> String + Send + Sync + 'static>(mut self, mapper: F) -> Self {
+        self.key_mapper = Some(Box::new(mapper));
+        self
+    }
+
+    /// Prefix the keys of the ingested documents with `prefix`, see [`FsIndex::set_key_prefix`].
+    pub fn key_prefix(mut self, prefix: &str) -> Self {
+        self.key_prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Build the filters with `backend`, see [`FsIndex::set_backend`].
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Hash the tokens with `hash_function`, see [`FsIndex::set_hash_function`].
+    pub fn hash_function(mut self, hash_function: HashFunction) -> Self {
+        self.hash_function = hash_function;
+        self
+    }
+
+    /// Analyze the documents and the searched keywords with `analyzer`, see [`FsIndex::set_analyzer`].
+    pub fn analyzer(mut self, analyzer: Analyzer) -> Self {
+        self.analyzer = analyzer;
+        self
+    }
+
+    /// Read the files in `file_system`, see [`FsIndex::set_file_system`].
+    pub fn file_system<F: FileSystem + 'static>(mut self, file_system: F) -> Self {
+        self.file_system = Some(Box::new(file_system));
+        self
+    }
+
+    /// Constructs the `FsIndex`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the error rate is invalid, if the bits per token and hash functions do not go together, if the backend is not available, if the keys of the store cannot be listed or if the audit log cannot be opened.
+    pub fn build(self) -> FsIndex<S> {
+        let error_rate = if self.bits_per_token.is_some() || self.hash_functions.is_some() {
+            error_rate_for(self.bits_per_token, self.hash_functions)
+        } else {
+            self.error_rate
+        };
+        let mut index = FsIndex::with_store(error_rate, self.store);
+        if let Some(path) = self.audit_log {
+            index.set_audit_log(&path);
+        }
+        index.set_fail_fast(self.fail_fast);
+        index.set_retry_policy(self.retry);
+        if let Some(size) = self.chunk_size {
+            index.set_chunk_size(size);
+        }
+        index.set_paragraphs(self.paragraphs);
+        if let Some(cues) = self.subtitle_cues {
+            index.set_subtitle_cues(cues);
+        }
+        if let Some(mapper) = self.key_mapper {
+            index.set_key_mapper(mapper);
+        }
+        if let Some(prefix) = self.key_prefix {
+            index.set_key_prefix(&prefix);
+        }
+        index.set_backend(self.backend);
+        index.set_hash_function(self.hash_function);
+        index.set_analyzer(self.analyzer);
+        if let Some(file_system) = self.file_system {
+            index.set_file_system(file_system);
+        }
+        index
+    }
+}
@@ -0,0 +1,89 @@
+use std::io;
+use std::io::{Cursor, Read};
+use flate2::read::MultiGzDecoder;
+use xz2::read::XzDecoder;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+/// Return a reader of the content of `reader`, decompressed if it is a gzip, zstd or xz stream.
+///
+/// The format is recognized from the first bytes of the content, whatever the name of its source, e.g. for the standard input.
+/// Concatenated gzip members, as written by `cat a.gz b.gz`, are all decompressed.
+///
+/// # Errors
+///
+/// Returns an error if the first bytes cannot be read or the decompressor cannot be initialized.
+///
+/// # Example
+///
+/// ```
+/// # use std::io::Read;
+/// # use cli_bloom::decompressing;
+/// let mut content = String::new();
+/// decompressing(&b"plain text"[..]).unwrap().read_to_string(&mut content).unwrap();
+/// assert_eq!("plain text", content);
+/// ```
+pub fn decompressing<'a, R: Read + 'a>(mut reader: R) -> io::Result<Box<dyn Read + 'a>> {
+    // A pipe may return fewer bytes than asked, so the magic bytes are read until there are enough
+    let mut magic = Vec::with_capacity(XZ_MAGIC.len());
+    while magic.len() < XZ_MAGIC.len() {
+        let mut byte = [0; 1];
+        match reader.read(&mut byte) {
+            Ok(0) => break,
+            Ok(_) => magic.push(byte[0]),
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error)
+        }
+    }
+    let format = if magic.starts_with(GZIP_MAGIC) {
+        Format::Gzip
+    } else if magic.starts_with(ZSTD_MAGIC) {
+        Format::Zstd
+    } else if magic.starts_with(XZ_MAGIC) {
+        Format::Xz
+    } else {
+        Format::Plain
+    };
+    let reader = Cursor::new(magic).chain(reader);
+    Ok(match format {
+        Format::Gzip => Box::new(MultiGzDecoder::new(reader)),
+        Format::Zstd => Box::new(zstd::Decoder::new(reader)?),
+        Format::Xz => Box::new(XzDecoder::new(reader)),
+        Format::Plain => Box::new(reader)
+    })
+}
+
+enum Format {
+    Plain,
+    Gzip,
+    Zstd,
+    Xz
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use super::*;
+
+    fn decompressed(content: &[u8]) -> String {
+        let mut text = String::new();
+        decompressing(content).unwrap().read_to_string(&mut text).unwrap();
+        text
+    }
+
+    #[test]
+    fn decompress_by_magic_bytes() {
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(b"word1 word2").unwrap();
+        assert_eq!("word1 word2", decompressed(&gzip.finish().unwrap()));
+        assert_eq!("word3", decompressed(&zstd::encode_all(&b"word3"[..], 0).unwrap()));
+        let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+        xz.write_all(b"word4").unwrap();
+        assert_eq!("word4", decompressed(&xz.finish().unwrap()));
+        assert_eq!("w", decompressed(b"w"));
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+
+/// Settings of the command line app, read from a TOML file.
+///
+/// Named queries are declared in the `queries` table and referred to as `@name`:
+///
+/// ```toml
+/// [queries]
+/// todo = "TODO OR FIXME"
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Config {
+    /// Query strings by name.
+    #[serde(default)]
+    pub queries: BTreeMap<String, String>
+}
+
+impl Config {
+    /// Read the config file at `path`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file cannot be read or is not a valid config.
+    pub fn load(path: &Path) -> Self {
+        let content = fs::read_to_string(path).expect(format!("Unable to read config file {}", path.display()).as_str());
+        toml::from_str(&content).expect(format!("Invalid config file {}", path.display()).as_str())
+    }
+
+    /// Return the default location of the config file, `cli-bloom/config.toml` in `$XDG_CONFIG_HOME` or `$HOME/.config`.
+    pub fn default_path() -> Option<PathBuf> {
+        let directory = match env::var_os("XDG_CONFIG_HOME") {
+            Some(directory) if !directory.is_empty() => PathBuf::from(directory),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config")
+        };
+        Some(directory.join("cli-bloom").join("config.toml"))
+    }
+
+    /// Replace a `@name` reference by the named query string.
+    ///
+    /// Return `query` itself when it is not a reference, and `None` when no query has this name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::Config;
+    /// let mut config = Config::default();
+    /// config.queries.insert("todo".to_string(), "TODO OR FIXME".to_string());
+    /// assert_eq!(Some("TODO OR FIXME"), config.expand("@todo"));
+    /// assert_eq!(Some("rust"), config.expand("rust"));
+    /// assert_eq!(None, config.expand("@fixme"));
+    /// ```
+    pub fn expand<'a>(&'a self, query: &'a str) -> Option<&'a str> {
+        match query.trim().strip_prefix('@') {
+            Some(name) => self.queries.get(name).map(|query| query.as_str()),
+            None => Some(query)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_config() {
+        let config = Config::load(Path::new("./test/data/config.toml"));
+        assert_eq!(Some("TODO OR FIXME"), config.expand("@todo"));
+        assert_eq!(1, config.queries.len());
+    }
+}
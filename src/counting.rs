@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+use serde_json::value::RawValue;
+use crate::errors::Error;
+use crate::filter::bits_per_token;
+use crate::membership::{Backend, Membership, HashFunction, double_hashing};
+use crate::tokenize::distinct_token_hashes;
+
+// A bloom filter with a counter of 8 bits instead of each bit, so that tokens can be removed.
+// A saturated counter is never decremented, which keeps false negatives out.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct CountingFilter {
+    #[serde(default, skip_serializing_if = "HashFunction::is_fnv")]
+    hash: HashFunction,
+    hash_count: u32,
+    counters: Vec<u8>
+}
+
+impl CountingFilter {
+    pub(crate) fn new(hash: HashFunction, error_rate: f32, content: &str) -> Self {
+        let keys = distinct_token_hashes(hash, content);
+        // Sized like an index-bloom filter of the same error rate
+        let size = ((keys.len() as f64 * bits_per_token(error_rate)).ceil() as usize).max(1);
+        let mut filter = CountingFilter {
+            hash,
+            hash_count: (-(error_rate as f64).log2()).ceil().max(1.0) as u32,
+            counters: vec![0; size]
+        };
+        for key in keys {
+            for position in filter.positions(key) {
+                filter.counters[position] = filter.counters[position].saturating_add(1);
+            }
+        }
+        filter
+    }
+
+    pub(crate) fn from_raw(raw: &RawValue) -> Self {
+        let mut tagged: BTreeMap<String, CountingFilter> = serde_json::from_str(raw.get()).expect("Invalid counting filter");
+        tagged.remove("counting").expect("Invalid counting filter")
+    }
+
+    // Counters of the token hashed to `key`, by double hashing
+    fn positions(&self, key: u64) -> Vec<usize> {
+        double_hashing(key, self.hash_count as usize, self.counters.len()).collect()
+    }
+
+    fn contains_key(&self, key: u64) -> bool {
+        self.positions(key).into_iter().all(|position| self.counters[position] > 0)
+    }
+}
+
+impl Membership for CountingFilter {
+    fn contains_tokens(&self, tokens: &[String]) -> Result<bool, Error> {
+        Ok(tokens.iter().all(|token| self.contains_key(self.hash.hash(token, 0))))
+    }
+
+    fn bits(&self) -> usize {
+        self.counters.len() * 8
+    }
+
+    fn hash_count(&self) -> usize {
+        self.hash_count as usize
+    }
+
+    fn fill_ratio(&self) -> f64 {
+        self.counters.iter().filter(|&&counter| counter > 0).count() as f64 / self.counters.len() as f64
+    }
+
+    fn backend(&self) -> Backend {
+        Backend::Counting
+    }
+
+    fn to_raw(&self) -> Box<RawValue> {
+        let mut tagged = BTreeMap::new();
+        tagged.insert("counting", self);
+        RawValue::from_string(serde_json::to_string(&tagged).expect("Impossible to serialize filter")).expect("Impossible to serialize filter")
+    }
+
+    fn without(&self, content: &str) -> Option<Box<dyn Membership>> {
+        let mut filter = self.clone();
+        // Tokens which are not in the filter would decrement the counters of other tokens
+        for key in distinct_token_hashes(self.hash, content).into_iter().filter(|&key| self.contains_key(key)) {
+            for position in filter.positions(key) {
+                if filter.counters[position] < u8::MAX {
+                    filter.counters[position] -= 1;
+                }
+            }
+        }
+        Some(Box::new(filter))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counting_filter_contains_tokens() {
+        let content: Vec<String> = (0..1000).map(|position| format!("word{}", position)).collect();
+        let filter = CountingFilter::new(HashFunction::Fnv, 0.001, &content.join(" "));
+        assert_eq!(10, filter.hash_count());
+        assert!(filter.contains("word1 Word999, word500").unwrap());
+        let false_positives = (1000..11000).filter(|position| filter.contains(&format!("word{}", position)).unwrap()).count();
+        assert!(false_positives < 50);
+    }
+
+    #[test]
+    fn counting_filter_without_tokens() {
+        let filter = CountingFilter::new(HashFunction::Fnv, 0.01, "word1 word2 word3");
+        let removed = filter.without("word2 word4").unwrap();
+        assert!(removed.contains("word1 word3").unwrap());
+        assert!(!removed.contains("word2").unwrap());
+        let restored = CountingFilter::from_raw(&removed.to_raw());
+        assert!(restored.contains("word3").unwrap());
+        assert!(filter.contains("word2").unwrap());
+    }
+}
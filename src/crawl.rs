@@ -0,0 +1,255 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use ureq::Agent;
+use url::Url;
+use crate::fs_loader::FsIndex;
+use crate::html::html_text;
+use crate::limiter::Limiter;
+use crate::stats::{IngestReport, Issue};
+use crate::store::IndexStore;
+
+impl<S: IndexStore> FsIndex<S> {
+    /// Ingest the pages of a web site, starting at `url` and following the links to the same origin up to `depth` links away.
+    ///
+    /// Each HTML or plain text page is a document keyed by its URL, without fragment. The text of HTML pages is extracted from their markup, scripts and styles left out.
+    /// Pages of other types and pages disallowed by robots.txt are listed as skipped in the report, pages which cannot be fetched as failures, with the URL as path.
+    /// Pages are fetched according to the politeness of the index, see [`FsIndex::set_politeness`].
+    /// The documents have no source file, so they are never pruned nor refreshed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `url` is not a valid URL or if a page cannot be inserted in the index.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use cli_bloom::FsIndex;
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// let report = fs_index.crawl("https://docs.example.com", 2);
+    /// println!("{} pages indexed", report.indexed);
+    /// ```
+    pub fn crawl(&mut self, url: &str, depth: usize) -> IngestReport {
+        let start = Url::parse(url).unwrap_or_else(|error| panic!("Invalid URL {} : {}", url, error));
+        let politeness = self.politeness();
+        let agent = ureq::AgentBuilder::new().user_agent(USER_AGENT).build();
+        let robots = if politeness.respects_robots() { Robots::fetch(&agent, &start) } else { Robots::default() };
+        let limiter = Limiter::new(politeness.interval().max(robots.crawl_delay.unwrap_or_default()));
+        let mut report = IngestReport::default();
+        let mut visited = HashSet::new();
+        visited.insert(page_key(&start));
+        let mut level = vec![start.clone()];
+        for distance in 0..=depth {
+            let (allowed, disallowed): (Vec<Url>, Vec<Url>) = level.into_iter().partition(|page| robots.allows(page.path()));
+            for page in disallowed {
+                report.skipped.push(Issue { path: PathBuf::from(page_key(&page)), reason: "disallowed by robots.txt".to_string() });
+            }
+            let fetched = fetch_all(&agent, &allowed, politeness.concurrency(), &limiter);
+            let mut next = Vec::new();
+            for (page, fetched) in allowed.into_iter().zip(fetched) {
+                let key = page_key(&page);
+                match fetched {
+                    Ok(Some(fetched)) => {
+                        self.insert_text(&key, &fetched.text);
+                        report.indexed += 1;
+                        report.bytes += fetched.bytes;
+                        if distance == depth {
+                            continue;
+                        }
+                        for link in fetched.links {
+                            if let Ok(link) = page.join(&link) {
+                                if link.origin() == start.origin() && visited.insert(page_key(&link)) {
+                                    next.push(link);
+                                }
+                            }
+                        }
+                    },
+                    Ok(None) => report.skipped.push(Issue { path: PathBuf::from(key), reason: "not an HTML or text page".to_string() }),
+                    Err(reason) => report.failed.push(Issue { path: PathBuf::from(key), reason })
+                }
+            }
+            level = next;
+        }
+        report
+    }
+}
+
+// Key of a page, its URL without fragment
+fn page_key(url: &Url) -> String {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    url.to_string()
+}
+
+struct Page {
+    text: String,
+    links: Vec<String>,
+    bytes: u64
+}
+
+// Fetch `pages` on `concurrency` threads, in the same order
+fn fetch_all(agent: &Agent, pages: &[Url], concurrency: usize, limiter: &Limiter) -> Vec<Result<Option<Page>, String>> {
+    let fetched: Vec<Mutex<Option<Result<Option<Page>, String>>>> = pages.iter().map(|_| Mutex::new(None)).collect();
+    let position = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..concurrency.min(pages.len()) {
+            scope.spawn(|| loop {
+                let page = position.fetch_add(1, Ordering::SeqCst);
+                if page >= pages.len() {
+                    break;
+                }
+                limiter.wait();
+                *fetched[page].lock().unwrap() = Some(fetch(agent, &pages[page]));
+            });
+        }
+    });
+    fetched.into_iter().map(|page| page.into_inner().unwrap().expect("Page not fetched")).collect()
+}
+
+const USER_AGENT: &str = "cli-bloom";
+
+// The rules of robots.txt for cli-bloom: path patterns allowed and disallowed, the longest matching one wins
+#[derive(Default)]
+struct Robots {
+    rules: Vec<(String, bool)>,
+    crawl_delay: Option<Duration>
+}
+
+impl Robots {
+    // The robots.txt of the origin of `url`, allowing everything if there is none
+    fn fetch(agent: &Agent, url: &Url) -> Self {
+        let robots = match url.join("/robots.txt") {
+            Ok(robots) => robots,
+            Err(_) => return Robots::default()
+        };
+        match agent.get(robots.as_str()).call().map(|response| response.into_string()) {
+            Ok(Ok(content)) => Robots::parse(&content),
+            _ => Robots::default()
+        }
+    }
+
+    // The group of cli-bloom if there is one, else the group of every robot
+    fn parse(content: &str) -> Self {
+        let mut groups: Vec<(Vec<String>, Robots)> = Vec::new();
+        let mut in_agents = false;
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field.trim().to_ascii_lowercase(), value.trim()),
+                None => continue
+            };
+            match field.as_str() {
+                "user-agent" => {
+                    if !in_agents {
+                        groups.push((Vec::new(), Robots::default()));
+                    }
+                    in_agents = true;
+                    groups.last_mut().unwrap().0.push(value.to_ascii_lowercase());
+                },
+                "allow" | "disallow" if !groups.is_empty() => {
+                    in_agents = false;
+                    // An empty disallow allows everything
+                    if !value.is_empty() {
+                        groups.last_mut().unwrap().1.rules.push((value.to_string(), field == "allow"));
+                    }
+                },
+                "crawl-delay" if !groups.is_empty() => {
+                    in_agents = false;
+                    groups.last_mut().unwrap().1.crawl_delay = value.parse().ok().map(Duration::from_secs_f64);
+                },
+                _ => ()
+            }
+        }
+        let position = groups.iter().position(|(agents, _)| agents.iter().any(|agent| agent == USER_AGENT))
+            .or_else(|| groups.iter().position(|(agents, _)| agents.iter().any(|agent| agent == "*")));
+        match position {
+            Some(position) => groups.swap_remove(position).1,
+            None => Robots::default()
+        }
+    }
+
+    fn allows(&self, path: &str) -> bool {
+        self.rules.iter()
+            .filter(|(pattern, _)| matches_pattern(pattern, path))
+            // Allow wins over a disallow of the same length
+            .max_by_key(|(pattern, allow)| (pattern.len(), *allow))
+            .map(|(_, allow)| *allow)
+            .unwrap_or(true)
+    }
+}
+
+// Whether `path` starts with `pattern`, where * matches any characters and a final $ the end of the path
+fn matches_pattern(pattern: &str, path: &str) -> bool {
+    match pattern.strip_suffix('$') {
+        Some(anchored) => matches_prefix(anchored, path).contains(&path.len()),
+        None => !matches_prefix(pattern, path).is_empty()
+    }
+}
+
+// Lengths of the prefixes of `path` matching `pattern`
+fn matches_prefix(pattern: &str, path: &str) -> Vec<usize> {
+    let mut ends = vec![0];
+    for (position, piece) in pattern.split('*').enumerate() {
+        let mut next = Vec::new();
+        for end in ends {
+            if position == 0 {
+                if path[end..].starts_with(piece) {
+                    next.push(end + piece.len());
+                }
+            } else {
+                // After a *, the piece may start anywhere
+                next.extend(path[end..].match_indices(piece).map(|(start, _)| end + start + piece.len()));
+                if piece.is_empty() {
+                    next.extend((end..=path.len()).filter(|&position| path.is_char_boundary(position)));
+                }
+            }
+        }
+        next.sort_unstable();
+        next.dedup();
+        ends = next;
+    }
+    ends
+}
+
+// The text and links of the page at `url`, or `None` if it is neither HTML nor plain text
+fn fetch(agent: &Agent, url: &Url) -> Result<Option<Page>, String> {
+    let response = agent.get(url.as_str()).call().map_err(|error| error.to_string())?;
+    let content_type = response.content_type().to_ascii_lowercase();
+    if content_type != "text/html" && content_type != "text/plain" {
+        return Ok(None);
+    }
+    let body = response.into_string().map_err(|error| error.to_string())?;
+    let bytes = body.len() as u64;
+    if content_type == "text/plain" {
+        return Ok(Some(Page { text: body, links: Vec::new(), bytes }));
+    }
+    let (text, links) = html_text(&body);
+    Ok(Some(Page { text, links, bytes }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn robots_rules() {
+        let robots = Robots::parse("User-agent: *\nDisallow: /\n\nUser-agent: other\nUser-agent: cli-bloom\nDisallow: /private\nAllow: /private/public\nDisallow: /*.pdf$\nCrawl-delay: 2\n");
+        assert!(robots.allows("/docs/page.html"));
+        assert!(!robots.allows("/private/page.html"));
+        assert!(robots.allows("/private/public/page.html"));
+        assert!(!robots.allows("/docs/manual.pdf"));
+        assert!(robots.allows("/docs/manual.pdf.html"));
+        assert_eq!(Some(Duration::from_secs(2)), robots.crawl_delay);
+        assert!(!Robots::parse("User-agent: *\nDisallow: /\n").allows("/page.html"));
+        assert!(Robots::parse("User-agent: *\nDisallow:\n").allows("/page.html"));
+    }
+
+    #[test]
+    fn page_key_without_fragment() {
+        let page = Url::parse("https://docs.example.com/guide/").unwrap();
+        assert_eq!("https://docs.example.com/docs/page.html", page_key(&page.join("/docs/page.html#top").unwrap()));
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+use serde_json::value::RawValue;
+use crate::errors::Error;
+use crate::membership::{Backend, Membership, mix, HashFunction};
+use crate::tokenize::distinct_token_hashes;
+
+const BUCKET_SIZE: usize = 4;
+
+// Relocations tried before the table is considered full
+const MAX_KICKS: usize = 500;
+
+// A cuckoo filter with fingerprints of up to 16 bits, 0 marking an empty slot.
+// See Fan et al., "Cuckoo Filter: Practically Better Than Bloom".
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct CuckooFilter {
+    #[serde(default, skip_serializing_if = "HashFunction::is_fnv")]
+    hash: HashFunction,
+    fingerprint_bits: u32,
+    tokens: usize,
+    buckets: Vec<[u16; BUCKET_SIZE]>
+}
+
+impl CuckooFilter {
+    pub(crate) fn new(hash: HashFunction, error_rate: f32, content: &str) -> Self {
+        // A token which is not in the filter matches one of the 2 × 4 fingerprints of its buckets, with a probability of 8 × 2^-bits
+        let fingerprint_bits = (-(error_rate as f64 / (2 * BUCKET_SIZE) as f64).log2()).ceil().max(4.0).min(16.0) as u32;
+        let keys = distinct_token_hashes(hash, content);
+        // Buckets are 95% full at most, and grow when a token cannot be placed
+        let mut bucket_count = ((keys.len() as f64 / (BUCKET_SIZE as f64 * 0.95)).ceil() as usize).max(1).next_power_of_two();
+        loop {
+            let mut filter = CuckooFilter {
+                hash,
+                fingerprint_bits,
+                tokens: 0,
+                buckets: vec![[0; BUCKET_SIZE]; bucket_count]
+            };
+            if keys.iter().all(|&key| filter.insert(key)) {
+                return filter;
+            }
+            bucket_count *= 2;
+        }
+    }
+
+    pub(crate) fn from_raw(raw: &RawValue) -> Self {
+        let mut tagged: BTreeMap<String, CuckooFilter> = serde_json::from_str(raw.get()).expect("Invalid cuckoo filter");
+        tagged.remove("cuckoo").expect("Invalid cuckoo filter")
+    }
+
+    fn insert(&mut self, key: u64) -> bool {
+        let (mut fingerprint, first, second) = self.locate(key);
+        for bucket in [first, second] {
+            if let Some(slot) = self.buckets[bucket].iter().position(|&stored| stored == 0) {
+                self.buckets[bucket][slot] = fingerprint;
+                self.tokens += 1;
+                return true;
+            }
+        }
+        // Evict a fingerprint to its other bucket, until one finds a free slot
+        let mut bucket = first;
+        for kick in 0..MAX_KICKS {
+            let slot = kick % BUCKET_SIZE;
+            std::mem::swap(&mut fingerprint, &mut self.buckets[bucket][slot]);
+            bucket = self.alternate(bucket, fingerprint);
+            if let Some(slot) = self.buckets[bucket].iter().position(|&stored| stored == 0) {
+                self.buckets[bucket][slot] = fingerprint;
+                self.tokens += 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn contains_token(&self, token: &str) -> bool {
+        let (fingerprint, first, second) = self.locate(self.hash.hash(token, 0));
+        self.buckets[first].contains(&fingerprint) || self.buckets[second].contains(&fingerprint)
+    }
+
+    // Fingerprint of the key and its two candidate buckets
+    fn locate(&self, key: u64) -> (u16, usize, usize) {
+        let mask = ((1u32 << self.fingerprint_bits) - 1) as u16;
+        let fingerprint = ((key >> 32) as u16 & mask).max(1);
+        let first = key as usize & (self.buckets.len() - 1);
+        (fingerprint, first, self.alternate(first, fingerprint))
+    }
+
+    // The other bucket of a fingerprint, computed from the fingerprint alone so that it can be moved
+    fn alternate(&self, bucket: usize, fingerprint: u16) -> usize {
+        bucket ^ (mix(fingerprint as u64) as usize & (self.buckets.len() - 1))
+    }
+}
+
+impl Membership for CuckooFilter {
+    fn contains_tokens(&self, tokens: &[String]) -> Result<bool, Error> {
+        Ok(tokens.iter().all(|token| self.contains_token(token)))
+    }
+
+    fn bits(&self) -> usize {
+        self.buckets.len() * BUCKET_SIZE * self.fingerprint_bits as usize
+    }
+
+    fn hash_count(&self) -> usize {
+        2
+    }
+
+    fn fill_ratio(&self) -> f64 {
+        self.tokens as f64 / (self.buckets.len() * BUCKET_SIZE) as f64
+    }
+
+    fn false_positive_rate(&self) -> f64 {
+        (2.0 * BUCKET_SIZE as f64 * self.fill_ratio() * 0.5f64.powi(self.fingerprint_bits as i32)).min(1.0)
+    }
+
+    fn backend(&self) -> Backend {
+        Backend::Cuckoo
+    }
+
+    fn to_raw(&self) -> Box<RawValue> {
+        let mut tagged = BTreeMap::new();
+        tagged.insert("cuckoo", self);
+        RawValue::from_string(serde_json::to_string(&tagged).expect("Impossible to serialize filter")).expect("Impossible to serialize filter")
+    }
+
+    fn without(&self, content: &str) -> Option<Box<dyn Membership>> {
+        let mut filter = self.clone();
+        for key in distinct_token_hashes(self.hash, content) {
+            let (fingerprint, first, second) = filter.locate(key);
+            for bucket in [first, second] {
+                if let Some(slot) = filter.buckets[bucket].iter().position(|&stored| stored == fingerprint) {
+                    filter.buckets[bucket][slot] = 0;
+                    filter.tokens -= 1;
+                    break;
+                }
+            }
+        }
+        Some(Box::new(filter))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cuckoo_filter_contains_tokens() {
+        let content: Vec<String> = (0..1000).map(|position| format!("word{}", position)).collect();
+        let filter = CuckooFilter::new(HashFunction::Fnv, 0.001, &content.join(" "));
+        assert_eq!(1000, filter.tokens);
+        assert!(filter.contains("word1 Word999, word500").unwrap());
+        let false_positives = (1000..11000).filter(|position| filter.contains(&format!("word{}", position)).unwrap()).count();
+        assert!(false_positives < 50);
+    }
+
+    #[test]
+    fn cuckoo_filter_raw_round_trip() {
+        let filter = CuckooFilter::new(HashFunction::Fnv, 0.01, "word1 word2");
+        let restored = CuckooFilter::from_raw(&filter.to_raw());
+        assert!(restored.contains("word2").unwrap());
+        assert!(!restored.contains("").unwrap());
+    }
+
+    #[test]
+    fn cuckoo_filter_without_tokens() {
+        let filter = CuckooFilter::new(HashFunction::Fnv, 0.001, "word1 word2 word3");
+        let removed = filter.without("word2 word4").unwrap();
+        assert!(removed.contains("word1 word3").unwrap());
+        assert!(!removed.contains("word2").unwrap());
+        assert_eq!(2.0 / 4.0, removed.fill_ratio());
+    }
+}
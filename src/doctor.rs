@@ -0,0 +1,155 @@
+use std::io;
+use std::path::Path;
+use serde::Serialize;
+use crate::file_system::FileSystem;
+use crate::fs_loader::FsIndex;
+use crate::store::IndexStore;
+
+/// A problem found by [`FsIndex::diagnose`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Finding {
+    pub problem: Problem,
+    /// Key of the document or path of the file concerned.
+    pub subject: String,
+    /// Description of the problem.
+    pub detail: String
+}
+
+/// Kind of a [`Finding`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Problem {
+    /// The source file of a document does not exist anymore.
+    Missing,
+    /// A file or directory cannot be read.
+    Unreadable,
+    /// A file or directory cannot be read with the permissions of the current user.
+    PermissionDenied,
+    /// A text file is not encoded in UTF-8, so it cannot be ingested.
+    Encoding,
+    /// The filter of a document is so full that its false positive rate exceeds the error rate of the index.
+    Saturated
+}
+
+impl Problem {
+    /// What to do about the problem.
+    pub fn suggestion(&self) -> &'static str {
+        match self {
+            Problem::Missing => "remove the documents of deleted files with prune or refresh",
+            Problem::Unreadable => "check that the file is not locked or on a failing disk, then ingest it again with --retries",
+            Problem::PermissionDenied => "grant read permission to the current user, or run cli-bloom as the owner of the files",
+            Problem::Encoding => "convert the file to UTF-8, e.g. with iconv -f latin1 -t utf-8",
+            Problem::Saturated => "ingest the file again with a lower --error-rate, or split it with --chunk-size"
+        }
+    }
+}
+
+impl Finding {
+    fn new(problem: Problem, subject: &str, detail: String) -> Self {
+        Finding { problem, subject: subject.to_string(), detail }
+    }
+
+    fn of_file(problem: Problem, path: &Path, detail: String) -> Self {
+        Finding::new(problem, &path.display().to_string(), detail)
+    }
+
+    fn unreadable(path: &Path, error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => Finding::of_file(Problem::PermissionDenied, path, error.to_string()),
+            _ => Finding::of_file(Problem::Unreadable, path, error.to_string())
+        }
+    }
+}
+
+impl<S: IndexStore> FsIndex<S> {
+    /// Check the index against the files under `root`, its source directory.
+    ///
+    /// Reports the documents whose source file is missing or unreadable and the saturated filters,
+    /// then the files under `root` which cannot be ingested because of their permissions or encoding.
+    /// Every file under `root` is read, files containing a NUL byte are considered binary and left out.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a filter cannot be read from the store.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # use cli_bloom::FsIndex;
+    /// let fs_index = FsIndex::restore("/foo/dump.json");
+    /// for finding in fs_index.diagnose(Path::new("/foo")) {
+    ///     println!("{} : {}, {}", finding.subject, finding.detail, finding.problem.suggestion());
+    /// }
+    /// ```
+    pub fn diagnose(&self, root: &Path) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for (key, document) in self.documents() {
+            if let Some(path) = &document.path {
+                match self.file_system().open(path) {
+                    Err(error) if error.kind() == io::ErrorKind::NotFound => findings.push(Finding::new(Problem::Missing, key, format!("{} does not exist", path.display()))),
+                    Err(error) => findings.push(Finding::unreadable(path, error)),
+                    Ok(_) => ()
+                }
+            }
+            let filter = self.filter(key).expect("Unable to read filter from index store");
+            if let Some(false_positive_rate) = filter.map(|filter| filter.false_positive_rate()) {
+                if false_positive_rate > self.error_rate() as f64 {
+                    findings.push(Finding::new(Problem::Saturated, key, format!("false positive rate {:.6} above {}", false_positive_rate, self.error_rate())));
+                }
+            }
+        }
+        check_files(self.file_system(), root, &mut findings);
+        findings
+    }
+}
+
+fn check_files(file_system: &dyn FileSystem, path: &Path, findings: &mut Vec<Finding>) {
+    let metadata = match file_system.metadata(path) {
+        Ok(metadata) => metadata,
+        Err(error) => return findings.push(Finding::unreadable(path, error))
+    };
+    if metadata.is_dir {
+        let mut entries = match file_system.read_dir(path) {
+            Ok(entries) => entries,
+            Err(error) => return findings.push(Finding::unreadable(path, error))
+        };
+        entries.sort();
+        for entry in entries {
+            check_files(file_system, &entry, findings);
+        }
+    } else if metadata.is_file {
+        match file_system.read(path) {
+            Ok(content) if content.contains(&0) => (),
+            Ok(content) => if let Err(error) = std::str::from_utf8(&content) {
+                findings.push(Finding::of_file(Problem::Encoding, path, format!("invalid UTF-8 at byte {}", error.valid_up_to())));
+            },
+            Err(error) => findings.push(Finding::unreadable(path, error))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::*;
+
+    #[test]
+    fn diagnose_missing_and_encoding() {
+        let mut root = std::env::temp_dir();
+        root.push("bloom_doctor");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join("deleted.txt"), "word1 word2").unwrap();
+        fs::write(root.join("latin1.txt"), b"caf\xe9 cr\xe8me").unwrap();
+        fs::write(root.join("binary.bin"), b"\x00\xff\xfe").unwrap();
+        let mut index = FsIndex::new(0.01);
+        index.ingest(root.to_str().unwrap());
+        fs::remove_file(root.join("deleted.txt")).unwrap();
+        let findings = index.diagnose(&root);
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(vec![Problem::Missing, Problem::Encoding], findings.iter().map(|finding| finding.problem).collect::<Vec<Problem>>());
+        assert_eq!(root.join("deleted.txt").to_str().unwrap(), findings[0].subject);
+        assert_eq!("invalid UTF-8 at byte 3", findings[1].detail);
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use serde::{Serialize, Deserialize};
+use sha2::{Sha256, Digest};
+use crate::extract::Split;
+use crate::file_system::FileSystem;
+use crate::membership;
+
+/// Metadata recorded for each document of a `FsIndex`.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Document {
+    /// SHA-256 digest of the content, in hexadecimal.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// Last modification time of the source, in seconds since the Unix epoch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u64>,
+    /// Source file of the document, if it was ingested from the file system.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    /// Other keys of the document, e.g. for the hard links to its source file.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Alias under which searches report the document, instead of its key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred: Option<String>,
+    /// Tags of the document, e.g. `project=web`, given at ingestion or read from the front matter of its file.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+    /// Number of tokens of the document, once analyzed, if it was counted at ingestion.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<usize>,
+    /// Number of distinct tokens of the document, which sizes its filter, if it was counted at ingestion.
+    ///
+    /// For a document made of appended contents, the sum of the distinct tokens of each content.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unique_tokens: Option<usize>,
+    // Splitting of the source file into documents when it was ingested, to split it again when the settings change
+    #[serde(skip_serializing_if = "Split::is_whole")]
+    pub(crate) split: Split
+}
+
+/// Freshness of an indexed document compared to its source on disk.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Staleness {
+    /// The source content is the one that was indexed.
+    Fresh,
+    /// The source content changed since it was indexed.
+    Modified,
+    /// The source does not exist anymore.
+    Missing,
+    /// The document has no recorded hash (e.g. restored from an old dump), has no source file or its source cannot be read.
+    Unknown
+}
+
+impl Document {
+    /// Return whether the document has the tag `name` with `value`.
+    pub fn has_tag(&self, name: &str, value: &str) -> bool {
+        self.tags.get(name).map_or(false, |tagged| tagged == value)
+    }
+
+    // Record the number of tokens and distinct tokens of the `analyzed` text, the whole content of the document
+    pub(crate) fn count_tokens(&mut self, analyzed: &str) {
+        let (tokens, unique_tokens) = token_counts(analyzed);
+        self.tokens = Some(tokens);
+        self.unique_tokens = Some(unique_tokens);
+    }
+
+    // Add the tokens of the `analyzed` text appended to the document, unless its tokens were never counted
+    pub(crate) fn add_tokens(&mut self, analyzed: &str) {
+        let (tokens, unique_tokens) = token_counts(analyzed);
+        self.tokens = self.tokens.map(|count| count + tokens);
+        self.unique_tokens = self.unique_tokens.map(|count| count + unique_tokens);
+    }
+
+    pub(crate) fn from_file(path: &Path, content: &[u8], modified: Option<SystemTime>) -> Self {
+        Document {
+            hash: Some(content_hash(content)),
+            modified: modified.and_then(epoch_seconds),
+            path: Some(path.to_path_buf()),
+            aliases: Vec::new(),
+            preferred: None,
+            tags: BTreeMap::new(),
+            tokens: None,
+            unique_tokens: None,
+            split: Split::default()
+        }
+    }
+
+    #[cfg(feature = "archive")]
+    pub(crate) fn from_hash(path: &Path, hash: String, modified: Option<SystemTime>) -> Self {
+        Document {
+            hash: Some(hash),
+            modified: modified.and_then(epoch_seconds),
+            path: Some(path.to_path_buf()),
+            aliases: Vec::new(),
+            preferred: None,
+            tags: BTreeMap::new(),
+            tokens: None,
+            unique_tokens: None,
+            split: Split::default()
+        }
+    }
+
+    pub(crate) fn from_content(content: &[u8]) -> Self {
+        Document {
+            hash: Some(content_hash(content)),
+            ..Document::default()
+        }
+    }
+
+    pub(crate) fn staleness(&self, file_system: &dyn FileSystem) -> Staleness {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Staleness::Unknown
+        };
+        let metadata = match file_system.metadata(path) {
+            Ok(metadata) if metadata.is_file => metadata,
+            _ => return Staleness::Missing
+        };
+        let hash = match &self.hash {
+            Some(hash) => hash,
+            None => return Staleness::Unknown
+        };
+        let modified = metadata.modified.and_then(epoch_seconds);
+        if modified.is_some() && modified == self.modified {
+            return Staleness::Fresh;
+        }
+        match file_system.read(path) {
+            Ok(content) if &content_hash(&content) == hash => Staleness::Fresh,
+            Ok(_) => Staleness::Modified,
+            Err(_) => Staleness::Unknown
+        }
+    }
+}
+
+pub(crate) fn content_hash(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+// Tags of the `name: value` lines of a front matter, the block between `---` lines at the start of `text`, e.g. of a Markdown file
+pub(crate) fn front_matter(text: &str) -> BTreeMap<String, String> {
+    let mut tags = BTreeMap::new();
+    let mut lines = text.lines();
+    if lines.next().map(str::trim_end) != Some("---") {
+        return tags;
+    }
+    for line in lines {
+        let line = line.trim_end();
+        if line == "---" || line == "..." {
+            return tags;
+        }
+        // Nested values and lists are not tags
+        if line.starts_with(char::is_whitespace) || line.starts_with('-') || line.starts_with('#') {
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim().trim_matches(|c: char| c == '"' || c == '\'');
+            if !name.trim().is_empty() && !value.is_empty() {
+                tags.insert(name.trim().to_string(), value.to_string());
+            }
+        }
+    }
+    // Without closing line, this is not a front matter
+    BTreeMap::new()
+}
+
+// Number of tokens and of distinct tokens of the `analyzed` text
+fn token_counts(analyzed: &str) -> (usize, usize) {
+    let mut tokens = 0;
+    let unique: HashSet<String> = membership::tokens(analyzed).inspect(|_| tokens += 1).collect();
+    (tokens, unique.len())
+}
+
+fn epoch_seconds(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|duration| duration.as_secs())
+}
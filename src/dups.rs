@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use serde::Serialize;
+use crate::fs_loader::FsIndex;
+use crate::store::IndexStore;
+
+/// A set of documents found by [`FsIndex::duplicates`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Duplicates {
+    /// Keys of the documents, in ascending order.
+    pub keys: Vec<String>,
+    /// `true` if the documents have the same content, `false` if their filters are nearly the same.
+    pub identical: bool,
+    /// Lowest similarity between the filters of two documents linked in the set, 1 for documents with the same content.
+    pub similarity: f64
+}
+
+impl<S: IndexStore> FsIndex<S> {
+    /// Find the sets of documents with the same content, then the sets of documents whose filters are at least `similarity` similar.
+    ///
+    /// Documents have the same content when they have the same hash, recorded at ingestion. The documents of a single file, e.g. its chunks, are not duplicates of each other.
+    /// The similarity of two bloom filters of the same size is the proportion of their bits set in both among those set in either, between 0 and 1,
+    /// so documents differing by a few tokens are near-identical. Filters of other backends or of different sizes, i.e. of documents with different numbers of distinct tokens, are never similar.
+    /// Sets with the same content come first, then the near-identical ones, each one in ascending order of its keys.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a filter cannot be read from the store.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use cli_bloom::FsIndex;
+    /// let fs_index = FsIndex::restore("/foo/dump.json");
+    /// for duplicates in fs_index.duplicates(0.9) {
+    ///     println!("{}", duplicates.keys.join(" "));
+    /// }
+    /// ```
+    pub fn duplicates(&self, similarity: f64) -> Vec<Duplicates> {
+        let mut by_hash: BTreeMap<&String, Vec<&String>> = BTreeMap::new();
+        // A single document of each content is compared with the others, by size of filter
+        let mut represented = HashSet::new();
+        let mut by_size: BTreeMap<(usize, usize), Vec<(&String, Vec<u8>)>> = BTreeMap::new();
+        for (key, document) in self.documents() {
+            if let Some(hash) = &document.hash {
+                by_hash.entry(hash).or_default().push(key);
+                if !represented.insert(hash) {
+                    continue;
+                }
+            }
+            if let Some(filter) = self.filter(key).expect("Unable to read filter from index store") {
+                if let Some(bitfield) = filter.bitfield() {
+                    by_size.entry((filter.bits(), filter.hash_count())).or_default().push((key, bitfield));
+                }
+            }
+        }
+        let mut sets = Vec::new();
+        for keys in by_hash.into_values() {
+            // A document without file is its own source
+            let sources: HashSet<_> = keys.iter().map(|key| self.document(key).and_then(|document| document.path.as_ref()).ok_or(key)).collect();
+            if sources.len() > 1 {
+                sets.push(Duplicates { keys: keys.into_iter().cloned().collect(), identical: true, similarity: 1.0 });
+            }
+        }
+        for filters in by_size.values() {
+            sets.extend(similar_sets(filters, similarity));
+        }
+        sets.sort_by(|first, second| second.identical.cmp(&first.identical).then_with(|| first.keys.cmp(&second.keys)));
+        sets
+    }
+}
+
+// Sets of the documents linked by filters at least `similarity` similar, among `filters` of the same size
+fn similar_sets(filters: &[(&String, Vec<u8>)], similarity: f64) -> Vec<Duplicates> {
+    let mut parents: Vec<usize> = (0..filters.len()).collect();
+    let mut lowest = vec![1.0; filters.len()];
+    for first in 0..filters.len() {
+        for second in first + 1..filters.len() {
+            let pair_similarity = bitfield_similarity(&filters[first].1, &filters[second].1);
+            if pair_similarity >= similarity {
+                let (first_root, second_root) = (root(&parents, first), root(&parents, second));
+                parents[second_root] = first_root;
+                lowest[first_root] = pair_similarity.min(lowest[first_root]).min(lowest[second_root]);
+            }
+        }
+    }
+    let mut sets: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    for (position, (key, _)) in filters.iter().enumerate() {
+        sets.entry(root(&parents, position)).or_default().push(key.to_string());
+    }
+    sets.into_iter()
+        .filter(|(_, keys)| keys.len() > 1)
+        .map(|(root, keys)| Duplicates { keys, identical: false, similarity: lowest[root] })
+        .collect()
+}
+
+fn root(parents: &[usize], mut position: usize) -> usize {
+    while parents[position] != position {
+        position = parents[position];
+    }
+    position
+}
+
+// Jaccard index of the bits set in two bit arrays of the same size
+fn bitfield_similarity(first: &[u8], second: &[u8]) -> f64 {
+    let (mut both, mut either) = (0, 0);
+    for (first, second) in first.iter().zip(second) {
+        both += (first & second).count_ones();
+        either += (first | second).count_ones();
+    }
+    if either == 0 {
+        1.0
+    } else {
+        both as f64 / either as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_identical_and_similar_documents() {
+        let words: Vec<String> = (0..20).map(|i| format!("word{}", i)).collect();
+        let mut changed = words.clone();
+        changed[0] = "other".to_string();
+        let mut index = FsIndex::new(0.01);
+        index.ingest_map(vec![
+            ("a".to_string(), words.join(" ")),
+            ("b".to_string(), words.join(" ")),
+            ("c".to_string(), changed.join(" ")),
+            ("d".to_string(), (0..20).map(|i| format!("token{}", i)).collect::<Vec<String>>().join(" "))
+        ]);
+        let sets = index.duplicates(0.8);
+        let keys: Vec<(Vec<String>, bool)> = sets.iter().map(|set| (set.keys.clone(), set.identical)).collect();
+        assert_eq!(vec![(vec!["a".to_string(), "b".to_string()], true), (vec!["a".to_string(), "c".to_string()], false)], keys);
+        assert!(sets[1].similarity >= 0.8 && sets[1].similarity < 1.0);
+    }
+}
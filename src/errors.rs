@@ -2,12 +2,14 @@ use std::error::Error as StdError;
 use std::io;
 use std::fmt;
 use index_bloom::Error as IndexBloomError;
+use crate::membership::Backend;
 
 #[derive(Debug)]
 pub enum Error {
     Io(io::Error),
     IndexInvalidData(io::Error),
-    IndexError(IndexBloomError)
+    IndexError(IndexBloomError),
+    UnavailableBackend(Backend)
 }
 
 impl StdError for Error {
@@ -15,7 +17,8 @@ impl StdError for Error {
         match self {
             Error::Io(error) => Some(error),
             Error::IndexInvalidData(error) => Some(error),
-            Error::IndexError(error) => Some(error)
+            Error::IndexError(error) => Some(error),
+            Error::UnavailableBackend(_) => None
         }
     }
 }
@@ -25,7 +28,8 @@ impl fmt::Display for Error {
         match self {
             Error::Io(_) => write!(f, "Error reading file"),
             Error::IndexInvalidData(_) => write!(f, "Error source must be an UTF-8 text file"),
-            Error::IndexError(_) => write!(f, "Error from index")
+            Error::IndexError(_) => write!(f, "Error from index"),
+            Error::UnavailableBackend(backend) => write!(f, "Filter backend {} needs the {} feature", backend, backend)
         }
     }
 }
@@ -0,0 +1,230 @@
+use std::path::Path;
+use serde::{Serialize, Deserialize};
+
+#[derive(Deserialize)]
+struct Notebook {
+    cells: Vec<Cell>
+}
+
+#[derive(Deserialize)]
+struct Cell {
+    cell_type: String,
+    #[serde(default)]
+    source: CellSource
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CellSource {
+    Text(String),
+    Lines(Vec<String>)
+}
+
+impl Default for CellSource {
+    fn default() -> Self {
+        CellSource::Text(String::new())
+    }
+}
+
+// How the text of the files is split into documents, kept with the documents to split them again when it changes
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq)]
+#[serde(default)]
+pub(crate) struct Split {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) chunk_size: Option<usize>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub(crate) paragraphs: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) cues: Option<usize>
+}
+
+impl Split {
+    // Whether files are indexed as a whole, the default
+    pub(crate) fn is_whole(&self) -> bool {
+        *self == Split::default()
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+// Split the content of the file at `path` into the documents to index, as (key, text) pairs
+pub(crate) fn split_documents(key: &str, path: &Path, content: &str, split: &Split) -> Vec<(String, String)> {
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("");
+    if extension.eq_ignore_ascii_case("ipynb") {
+        if let Some(cells) = notebook_cells(key, content) {
+            return cells;
+        }
+    }
+    if extension.eq_ignore_ascii_case("srt") || extension.eq_ignore_ascii_case("vtt") {
+        return subtitle_cues(key, content, split.cues);
+    }
+    if split.paragraphs {
+        return split_paragraphs(key, content);
+    }
+    match split.chunk_size {
+        Some(size) if content.len() > size => chunks(key, content, size),
+        _ => vec![(key.to_string(), content.to_string())]
+    }
+}
+
+// Dialogue of a SubRip or WebVTT file, by groups of `cues` cues if any
+fn subtitle_cues(key: &str, content: &str, cues: Option<usize>) -> Vec<(String, String)> {
+    let mut dialogues = Vec::new();
+    let mut block: Vec<&str> = Vec::new();
+    for line in content.lines().chain(std::iter::once("")) {
+        if !line.trim().is_empty() {
+            block.push(line.trim());
+            continue;
+        }
+        // Cue numbers or identifiers come before the timing line, and blocks without timing are headers, notes or styles
+        if let Some(timing) = block.iter().position(|line| line.contains("-->")) {
+            let text: Vec<String> = block[timing + 1..].iter().map(|line| strip_tags(line)).collect();
+            dialogues.push(text.join("\n"));
+        }
+        block.clear();
+    }
+    match cues {
+        Some(cues) => dialogues.chunks(cues.max(1))
+            .enumerate()
+            .map(|(position, group)| (format!("{}#cues{}", key, position), group.join("\n")))
+            .collect(),
+        None => vec![(key.to_string(), dialogues.join("\n"))]
+    }
+}
+
+// Text without the <i>, <c.color> or {\an8} formatting tags
+fn strip_tags(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut closing = None;
+    for c in line.chars() {
+        match (closing, c) {
+            (None, '<') => closing = Some('>'),
+            (None, '{') => closing = Some('}'),
+            (None, _) => text.push(c),
+            (Some(end), _) if c == end => closing = None,
+            (Some(_), _) => ()
+        }
+    }
+    text
+}
+
+// Consecutive chunks of about `size` bytes, keyed by their position; words are not cut unless longer than a chunk
+fn chunks(key: &str, content: &str, size: usize) -> Vec<(String, String)> {
+    let size = size.max(1);
+    let mut chunks = Vec::new();
+    let mut rest = content.trim_start();
+    while !rest.is_empty() {
+        let mut end = rest.len().min(size);
+        while !rest.is_char_boundary(end) {
+            end += 1;
+        }
+        if end < rest.len() {
+            if let Some(space) = rest[..end].rfind(char::is_whitespace) {
+                end = space;
+            }
+        }
+        chunks.push((format!("{}#chunk{}", key, chunks.len()), rest[..end].to_string()));
+        rest = rest[end..].trim_start();
+    }
+    chunks
+}
+
+// Paragraphs delimited by blank lines, keyed by their position
+fn split_paragraphs(key: &str, content: &str) -> Vec<(String, String)> {
+    let mut paragraphs = Vec::new();
+    let mut lines: Vec<&str> = Vec::new();
+    for line in content.lines().chain(std::iter::once("")) {
+        if !line.trim().is_empty() {
+            lines.push(line);
+        } else if !lines.is_empty() {
+            paragraphs.push((format!("{}¶{}", key, paragraphs.len()), lines.join("\n")));
+            lines.clear();
+        }
+    }
+    paragraphs
+}
+
+// Markdown and code cells of a notebook, keyed by their position; outputs are left out
+fn notebook_cells(key: &str, content: &str) -> Option<Vec<(String, String)>> {
+    let notebook: Notebook = serde_json::from_str(content).ok()?;
+    let cells = notebook.cells.into_iter()
+        .enumerate()
+        .filter(|(_, cell)| cell.cell_type == "markdown" || cell.cell_type == "code")
+        .map(|(position, cell)| {
+            let text = match cell.source {
+                CellSource::Text(text) => text,
+                CellSource::Lines(lines) => lines.concat()
+            };
+            (format!("{}#cell{}", key, position), text)
+        })
+        .filter(|(_, text)| !text.trim().is_empty())
+        .collect();
+    Some(cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_notebook_cells() {
+        let content = r##"{"cells": [
+            {"cell_type": "markdown", "source": ["# Title\n", "word1 word2"]},
+            {"cell_type": "code", "source": "print('word3')", "outputs": [{"data": {"image/png": "iVBORw0KGgo"}}]},
+            {"cell_type": "raw", "source": "word4"},
+            {"cell_type": "code", "source": []}
+        ]}"##;
+        let expected = vec![
+            ("nb.ipynb#cell0".to_string(), "# Title\nword1 word2".to_string()),
+            ("nb.ipynb#cell1".to_string(), "print('word3')".to_string())
+        ];
+        assert_eq!(expected, split_documents("nb.ipynb", Path::new("nb.ipynb"), content, &Split::default()));
+    }
+
+    #[test]
+    fn invalid_notebook_is_plain_text() {
+        let expected = vec![("nb.ipynb".to_string(), "word1".to_string())];
+        assert_eq!(expected, split_documents("nb.ipynb", Path::new("nb.ipynb"), "word1", &Split::default()));
+    }
+
+    #[test]
+    fn split_large_content_into_chunks() {
+        let expected = vec![
+            ("log.txt#chunk0".to_string(), "word1 word2".to_string()),
+            ("log.txt#chunk1".to_string(), "word3 é".to_string()),
+            ("log.txt#chunk2".to_string(), "word45678901".to_string())
+        ];
+        assert_eq!(expected, split_documents("log.txt", Path::new("log.txt"), "word1 word2 word3 é word45678901", &Split { chunk_size: Some(12), ..Split::default() }));
+    }
+
+    #[test]
+    fn small_content_is_not_chunked() {
+        let expected = vec![("log.txt".to_string(), "word1 word2".to_string())];
+        assert_eq!(expected, split_documents("log.txt", Path::new("log.txt"), "word1 word2", &Split { chunk_size: Some(12), ..Split::default() }));
+    }
+
+    #[test]
+    fn split_paragraphs_on_blank_lines() {
+        let content = "word1\nword2\n\n  \n\nword3\n";
+        let expected = vec![
+            ("book.txt¶0".to_string(), "word1\nword2".to_string()),
+            ("book.txt¶1".to_string(), "word3".to_string())
+        ];
+        assert_eq!(expected, split_documents("book.txt", Path::new("book.txt"), content, &Split { paragraphs: true, ..Split::default() }));
+    }
+
+    #[test]
+    fn split_subtitle_dialogue() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\n<i>word1</i> word2\n\n2\n00:00:03,000 --> 00:00:04,000\n{\\an8}word3\nword4\n\n3\n00:00:05,000 --> 00:00:06,000\nword5\n";
+        let expected = vec![("movie.srt".to_string(), "word1 word2\nword3\nword4\nword5".to_string())];
+        assert_eq!(expected, split_documents("movie.srt", Path::new("movie.srt"), srt, &Split::default()));
+        let vtt = "WEBVTT\n\nNOTE a comment\n\nintro\n00:01.000 --> 00:02.000 align:start\n<c.yellow>word1</c>\n\n00:03.000 --> 00:04.000\nword2\n\n00:05.000 --> 00:06.000\nword3\n";
+        let expected = vec![
+            ("movie.vtt#cues0".to_string(), "word1\nword2".to_string()),
+            ("movie.vtt#cues1".to_string(), "word3".to_string())
+        ];
+        assert_eq!(expected, split_documents("movie.vtt", Path::new("movie.vtt"), vtt, &Split { cues: Some(2), ..Split::default() }));
+    }
+}
@@ -0,0 +1,162 @@
+use std::io;
+use std::sync::Arc;
+use crate::html::html_text;
+#[cfg(feature = "ocr")]
+use crate::ocr;
+
+/// Turns the content of a kind of document into the text to index, e.g. the text of a PDF or of an HTML page.
+///
+/// Extractors are registered on a `FsIndex` with [`FsIndex::register_extractor`](crate::FsIndex::register_extractor), the content of a file goes to the last registered extractor accepting it.
+/// The type of a content is detected from its first bytes, so a misnamed file still goes to the right extractor. The extension is the fallback for the content whose type cannot be detected, e.g. most text formats.
+/// Content accepted by no extractor is indexed as UTF-8 text, unless it was detected as a binary format, e.g. an archive or an executable, which is not text. [`HtmlExtractor`] is always registered, and `OcrExtractor` with the `ocr` feature.
+///
+/// # Example
+///
+/// ```
+/// # use std::io;
+/// # use cli_bloom::{ContentExtractor, FsIndex, MemoryFileSystem};
+/// // Comma separated values, indexed without the separators
+/// struct CsvExtractor;
+///
+/// impl ContentExtractor for CsvExtractor {
+///     fn accepts(&self, mime: Option<&str>, extension: &str) -> bool {
+///         mime == Some("text/csv") || extension == "csv"
+///     }
+///
+///     fn extract(&self, bytes: &[u8]) -> io::Result<String> {
+///         let text = std::str::from_utf8(bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+///         Ok(text.replace(',', " "))
+///     }
+/// }
+///
+/// let files = MemoryFileSystem::default();
+/// files.insert("/foo/bar.csv", "word1,word2");
+/// let mut fs_index = FsIndex::new(0.00001);
+/// fs_index.set_file_system(files);
+/// fs_index.register_extractor(CsvExtractor);
+/// fs_index.ingest("/foo/bar.csv");
+/// assert!(fs_index.search("word2").is_some());
+/// ```
+pub trait ContentExtractor: Send + Sync {
+    /// Return whether the extractor reads content of the MIME type `mime`, when it is known or detected, or from files with the lowercase `extension`, without dot.
+    fn accepts(&self, mime: Option<&str>, extension: &str) -> bool;
+
+    /// Return the text of `bytes`, or an error of kind `InvalidData` if it is not a document of this kind.
+    fn extract(&self, bytes: &[u8]) -> io::Result<String>;
+}
+
+/// Extracts the text of HTML pages, without their markup, comments, scripts and styles.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HtmlExtractor;
+
+impl ContentExtractor for HtmlExtractor {
+    fn accepts(&self, mime: Option<&str>, extension: &str) -> bool {
+        match mime {
+            Some(mime) => mime == "text/html" || mime == "application/xhtml+xml",
+            None => extension == "html" || extension == "htm" || extension == "xhtml"
+        }
+    }
+
+    fn extract(&self, bytes: &[u8]) -> io::Result<String> {
+        Ok(html_text(&utf8(bytes.to_vec())?).0)
+    }
+}
+
+/// Recognizes the text of images and scanned PDFs with `tesseract`, PDFs with a text layer being read with `pdftotext`.
+#[cfg(feature = "ocr")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OcrExtractor;
+
+#[cfg(feature = "ocr")]
+impl ContentExtractor for OcrExtractor {
+    fn accepts(&self, mime: Option<&str>, extension: &str) -> bool {
+        match mime {
+            Some(mime) => mime == "application/pdf" || mime.starts_with("image/"),
+            None => ocr::is_scan_extension(extension)
+        }
+    }
+
+    fn extract(&self, bytes: &[u8]) -> io::Result<String> {
+        let text = ocr::recognize_bytes(bytes)?;
+        if text.trim().is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "no text recognized"));
+        }
+        Ok(text)
+    }
+}
+
+// The extractors of an index, the last registered first
+#[derive(Clone)]
+pub(crate) struct Extractors(Vec<Arc<dyn ContentExtractor>>);
+
+impl Default for Extractors {
+    fn default() -> Self {
+        let mut extractors = Extractors(Vec::new());
+        extractors.register(Arc::new(HtmlExtractor));
+        #[cfg(feature = "ocr")]
+        extractors.register(Arc::new(OcrExtractor));
+        extractors
+    }
+}
+
+impl Extractors {
+    pub(crate) fn register(&mut self, extractor: Arc<dyn ContentExtractor>) {
+        self.0.insert(0, extractor);
+    }
+
+    // Text of `bytes`, by the first extractor accepting them, as UTF-8 text otherwise.
+    // Without a `mime` type, it is detected from the magic bytes, which are more reliable than the extension.
+    pub(crate) fn extract(&self, mime: Option<&str>, extension: &str, bytes: Vec<u8>) -> io::Result<String> {
+        let extension = extension.to_ascii_lowercase();
+        let mime = mime.or_else(|| infer::get(&bytes).map(|kind| kind.mime_type()));
+        match self.0.iter().find(|extractor| extractor.accepts(mime, &extension)) {
+            Some(extractor) => extractor.extract(&bytes),
+            None => match mime {
+                Some(mime) if !is_text(mime) => Err(io::Error::new(io::ErrorKind::InvalidData, format!("binary content of type {}", mime))),
+                _ => utf8(bytes)
+            }
+        }
+    }
+}
+
+fn is_text(mime: &str) -> bool {
+    mime.starts_with("text/") || mime.ends_with("+xml") || mime == "application/json"
+}
+
+fn utf8(bytes: Vec<u8>) -> io::Result<String> {
+    String::from_utf8(bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Upper;
+
+    impl ContentExtractor for Upper {
+        fn accepts(&self, _mime: Option<&str>, extension: &str) -> bool {
+            extension == "html" || extension == "up"
+        }
+
+        fn extract(&self, bytes: &[u8]) -> io::Result<String> {
+            Ok(String::from_utf8_lossy(bytes).to_uppercase())
+        }
+    }
+
+    #[test]
+    fn registered_extractors_take_precedence() {
+        let mut extractors = Extractors::default();
+        assert_eq!(" word1 ", extractors.extract(None, "HTML", b"<p>word1</p>".to_vec()).unwrap());
+        assert_eq!("<p>word1</p>", extractors.extract(None, "txt", b"<p>word1</p>".to_vec()).unwrap());
+        assert_eq!(io::ErrorKind::InvalidData, extractors.extract(None, "txt", b"\xff".to_vec()).unwrap_err().kind());
+        // Detected from the content, whatever the extension
+        assert_eq!(" word1 ", extractors.extract(None, "txt", b"<!DOCTYPE html><p>word1</p>".to_vec()).unwrap().replace("  ", " "));
+        let png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR".to_vec();
+        #[cfg(not(feature = "ocr"))]
+        assert_eq!("binary content of type image/png", extractors.extract(None, "txt", png).unwrap_err().to_string());
+        #[cfg(feature = "ocr")]
+        let _ = png;
+        extractors.register(Arc::new(Upper));
+        assert_eq!("<P>WORD1</P>", extractors.extract(None, "html", b"<p>word1</p>".to_vec()).unwrap());
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+/// Metadata of a file or directory, as returned by a [`FileSystem`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FileMetadata {
+    pub is_file: bool,
+    pub is_dir: bool,
+    /// Size of the file, in bytes.
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+    /// Identifies the file whatever the path it is reached with (device and inode numbers on Unix), so that hard links are ingested once.
+    pub id: Option<(u64, u64)>
+}
+
+/// The files a `FsIndex` ingests from, checks for staleness and diagnoses.
+///
+/// A `FsIndex` reads the real file system by default, see [`FsIndex::set_file_system`](crate::FsIndex::set_file_system).
+/// Implement this trait to ingest from a virtual file system, e.g. a chroot or an overlay.
+///
+/// # Example
+///
+/// ```
+/// # use cli_bloom::{FsIndex, MemoryFileSystem};
+/// let files = MemoryFileSystem::default();
+/// files.insert("/foo/bar.txt", "word1 word2");
+/// let mut fs_index = FsIndex::new(0.00001);
+/// fs_index.set_file_system(files);
+/// fs_index.ingest("/foo");
+/// assert!(fs_index.search("word2").is_some());
+/// ```
+pub trait FileSystem: Send + Sync {
+    /// Return the paths of the entries of the directory at `path`.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// Return the metadata of the file or directory at `path`, following symbolic links.
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata>;
+
+    /// Open the file at `path` for reading.
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>>;
+
+    /// Return the content of the file at `path`.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let mut content = Vec::new();
+        self.open(path)?.read_to_end(&mut content)?;
+        Ok(content)
+    }
+}
+
+impl<T: FileSystem + ?Sized> FileSystem for Box<T> {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        (**self).read_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        (**self).metadata(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        (**self).open(path)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        (**self).read(path)
+    }
+}
+
+/// The `FileSystem` of the operating system, used by default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RealFileSystem;
+
+impl FileSystem for RealFileSystem {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::read_dir(path)?.map(|entry| entry.map(|entry| entry.path())).collect()
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        let metadata = fs::metadata(path)?;
+        Ok(FileMetadata {
+            is_file: metadata.is_file(),
+            is_dir: metadata.is_dir(),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            id: file_id(&metadata)
+        })
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(File::open(path)?))
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// A `FileSystem` of files held in memory, whose directories are the ancestors of the files.
+///
+/// Clones share the same files, so files can be changed after the file system is given to a `FsIndex`.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryFileSystem {
+    files: Arc<RwLock<BTreeMap<PathBuf, (Vec<u8>, SystemTime)>>>
+}
+
+impl MemoryFileSystem {
+    /// Create or replace the file at `path`, modified now.
+    pub fn insert<P: AsRef<Path>, C: Into<Vec<u8>>>(&self, path: P, content: C) {
+        self.files.write().unwrap().insert(path.as_ref().to_path_buf(), (content.into(), SystemTime::now()));
+    }
+
+    /// Change the modification time of the file at `path`, return `false` if there is none.
+    pub fn set_modified<P: AsRef<Path>>(&self, path: P, modified: SystemTime) -> bool {
+        match self.files.write().unwrap().get_mut(path.as_ref()) {
+            Some(file) => {
+                file.1 = modified;
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Remove the file at `path`, return `false` if there is none.
+    pub fn remove<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.files.write().unwrap().remove(path.as_ref()).is_some()
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        if !self.metadata(path)?.is_dir {
+            return Err(io::Error::new(io::ErrorKind::Other, format!("{} is not a directory", path.display())));
+        }
+        let mut entries: Vec<PathBuf> = self.files.read().unwrap().keys()
+            .filter_map(|file| file.strip_prefix(path).ok())
+            .filter_map(|relative| relative.components().next())
+            .map(|child| path.join(child))
+            .collect();
+        entries.dedup();
+        Ok(entries)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        let files = self.files.read().unwrap();
+        if let Some((content, modified)) = files.get(path) {
+            return Ok(FileMetadata { is_file: true, len: content.len() as u64, modified: Some(*modified), ..FileMetadata::default() });
+        }
+        if files.keys().any(|file| file != path && file.starts_with(path)) {
+            return Ok(FileMetadata { is_dir: true, ..FileMetadata::default() });
+        }
+        Err(io::Error::new(io::ErrorKind::NotFound, format!("{} not found", path.display())))
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(Cursor::new(self.read(path)?)))
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match self.files.read().unwrap().get(path) {
+            Some((content, _)) => Ok(content.clone()),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, format!("{} not found", path.display())))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_file_system_directories() {
+        let files = MemoryFileSystem::default();
+        files.insert("/foo/bar.txt", "word1");
+        files.insert("/foo/baz/qux.txt", "word2");
+        assert!(files.metadata(Path::new("/foo")).unwrap().is_dir);
+        assert_eq!(5, files.metadata(Path::new("/foo/bar.txt")).unwrap().len);
+        assert_eq!(vec![PathBuf::from("/foo/bar.txt"), PathBuf::from("/foo/baz")], files.read_dir(Path::new("/foo")).unwrap());
+        assert!(files.remove("/foo/bar.txt"));
+        assert_eq!(io::ErrorKind::NotFound, files.read(Path::new("/foo/bar.txt")).unwrap_err().kind());
+    }
+}
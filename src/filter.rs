@@ -0,0 +1,565 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use index_bloom::Index;
+use serde::{Serialize, Deserialize};
+use serde_json::value::RawValue;
+use crate::errors::Error;
+use crate::membership::{Backend, HashFunction, Membership, double_hashing, tokens};
+use crate::tokenize::distinct_token_hashes;
+#[cfg(feature = "xor")]
+use crate::xor::XorFilter;
+#[cfg(feature = "cuckoo")]
+use crate::cuckoo::CuckooFilter;
+#[cfg(feature = "counting")]
+use crate::counting::CountingFilter;
+
+// Each filter is a single document index, stored under an anonymous key
+const FILTER_KEY: &str = "";
+
+// Error rate of each appended layer compared to the previous one
+const LAYER_TIGHTENING: f32 = 0.5;
+
+/// The filter of a single document, a bloom filter unless another [`Backend`] is chosen.
+///
+/// Filters are created by a `FsIndex` during ingestion and handed to its [`IndexStore`](crate::IndexStore).
+/// A filter never changes once created, so clones share the same bits.
+#[derive(Clone)]
+pub struct Filter {
+    membership: Arc<dyn Membership>,
+    // Read by every size estimate of the stores, computed once
+    bits: usize
+}
+
+#[derive(Serialize)]
+struct SingleFilter<'a> {
+    error_rate: f32,
+    bloom_filters: BTreeMap<&'a str, &'a RawValue>
+}
+
+// Serialization of a filter alone, with the same layout as an index-bloom index holding a single filter
+pub(crate) fn single_filter_json(error_rate: f32, raw: &RawValue) -> String {
+    let mut bloom_filters = BTreeMap::new();
+    bloom_filters.insert(FILTER_KEY, raw);
+    let single = SingleFilter {
+        error_rate,
+        bloom_filters
+    };
+    serde_json::to_string(&single).expect("Impossible to serialize filter")
+}
+
+#[derive(Deserialize)]
+struct SerializedFilter {
+    #[serde(default)]
+    error_rate: f32,
+    bloom_filters: BTreeMap<String, Box<RawValue>>
+}
+
+// Layout of a bloom filter in a dump, without hash function for the filters built by index-bloom
+#[derive(Serialize, Deserialize)]
+struct BloomLayout {
+    key_size: usize,
+    bitfield: Vec<u8>,
+    bitfield_size: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hash: Option<HashFunction>
+}
+
+// Error rates below this one take over 57 bits per token, a sign of a typo rather than of a need
+pub(crate) const MIN_ERROR_RATE: f32 = 1e-12;
+
+// Check that `error_rate` gives a usable filter, explaining the memory cost of a too small one
+pub(crate) fn check_error_rate(error_rate: f32) {
+    if !(error_rate > 0.0 && error_rate < 1.0) {
+        panic!("Invalid error rate {} : it must be a probability strictly between 0 and 1, e.g. 0.00001", error_rate);
+    }
+    if error_rate < MIN_ERROR_RATE {
+        panic!("Error rate {} is too small : each token would take about {:.0} bits in every filter, use at least {}", error_rate, bits_per_token(error_rate), MIN_ERROR_RATE);
+    }
+}
+
+// Optimal number of bits per token of a bloom filter with the false positive probability `error_rate`
+pub(crate) fn bits_per_token(error_rate: f32) -> f64 {
+    -(error_rate as f64).ln() / (2f64.ln() * 2f64.ln())
+}
+
+// Error rate of filters with `bits_per_token` bits per token or `hash_functions` hash functions.
+// index-bloom derives both from the error rate, with ceil(-log2(error rate)) hash functions, so they cannot be chosen independently.
+pub(crate) fn error_rate_for(bits_per_token: Option<f64>, hash_functions: Option<u32>) -> f32 {
+    let ln2 = 2f64.ln();
+    let bits_per_token = match (bits_per_token, hash_functions) {
+        (Some(bits_per_token), Some(hash_functions)) => {
+            // The hash functions count is the smallest integer above bits per token * ln(2)
+            if !((hash_functions as f64 - 1.0) / ln2 < bits_per_token && bits_per_token <= hash_functions as f64 / ln2) {
+                panic!("{} bits per token cannot use {} hash functions, which need between {:.2} and {:.2} bits per token", bits_per_token, hash_functions, (hash_functions as f64 - 1.0) / ln2, hash_functions as f64 / ln2);
+            }
+            bits_per_token
+        },
+        (Some(bits_per_token), None) => bits_per_token,
+        // In the middle of the range using this count, away from rounding issues
+        (None, Some(hash_functions)) => (hash_functions as f64 - 0.5) / ln2,
+        (None, None) => panic!("Bits per token or hash functions are needed")
+    };
+    let error_rate = (-bits_per_token * ln2 * ln2).exp() as f32;
+    check_error_rate(error_rate);
+    error_rate
+}
+
+impl Filter {
+    #[cfg(test)]
+    pub(crate) fn new(error_rate: f32, content: &str) -> Result<Self, Error> {
+        Filter::with_backend(Backend::Bloom, HashFunction::Fnv, error_rate, content)
+    }
+
+    pub(crate) fn with_backend(backend: Backend, hash: HashFunction, error_rate: f32, content: &str) -> Result<Self, Error> {
+        let membership: Arc<dyn Membership> = match backend {
+            Backend::Bloom => Arc::new(BloomFilter::new(hash, error_rate, content)),
+            #[cfg(feature = "xor")]
+            Backend::Xor => Arc::new(XorFilter::new(hash, error_rate, content)),
+            #[cfg(feature = "cuckoo")]
+            Backend::Cuckoo => Arc::new(CuckooFilter::new(hash, error_rate, content)),
+            #[cfg(feature = "counting")]
+            Backend::Counting => Arc::new(CountingFilter::new(hash, error_rate, content)),
+            #[allow(unreachable_patterns)]
+            backend => return Err(Error::UnavailableBackend(backend))
+        };
+        Ok(Filter::of(membership))
+    }
+
+    fn of(membership: Arc<dyn Membership>) -> Self {
+        Filter {
+            bits: membership.bits(),
+            membership
+        }
+    }
+
+    pub(crate) fn contains(&self, keywords: &str) -> Result<bool, Error> {
+        self.membership.contains(keywords)
+    }
+
+    // Like `contains`, with keywords already split in normalized tokens, e.g. once for all the filters evaluated by a search
+    pub(crate) fn contains_tokens(&self, tokens: &[String]) -> Result<bool, Error> {
+        if tokens.is_empty() {
+            return Ok(false);
+        }
+        self.membership.contains_tokens(tokens)
+    }
+
+    /// Return the backend of the filter.
+    pub fn backend(&self) -> Backend {
+        self.membership.backend()
+    }
+
+    /// Serialize the filter in JSON.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the filter cannot be serialized.
+    pub fn to_json(&self) -> String {
+        self.membership.to_json()
+    }
+
+    /// Restore a filter serialized with [`Filter::to_json`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `serialized` is not a valid filter representation.
+    pub fn from_json(serialized: &str) -> Self {
+        let single: SerializedFilter = serde_json::from_str(serialized).expect("Invalid filter");
+        let raw = single.bloom_filters.into_iter().map(|(_, raw)| raw).next().expect("Empty filter");
+        Filter::from_raw(single.error_rate, &raw)
+    }
+
+    /// Return the number of bits of the filter.
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    /// Return the number of hash functions of the filter.
+    pub fn hash_count(&self) -> usize {
+        self.membership.hash_count()
+    }
+
+    /// Return the proportion of bits set in the filter, between 0 and 1.
+    ///
+    /// For filters storing fingerprints, the proportion of occupied slots.
+    pub fn fill_ratio(&self) -> f64 {
+        self.membership.fill_ratio()
+    }
+
+    /// Return the estimated probability that the filter matches a token it does not contain.
+    ///
+    /// For bloom filters, it is computed from the fill ratio and the number of hash functions, and exceeds the error rate of the filter when the document has more distinct tokens than the filter was sized for.
+    pub fn false_positive_rate(&self) -> f64 {
+        self.membership.false_positive_rate()
+    }
+
+    /// Return a copy of the filter without the tokens of `content`, or `None` if its backend cannot remove tokens.
+    ///
+    /// Only the counting and cuckoo backends can, e.g. to update the filter of a document whose content partly changed without reading it all again.
+    /// Tokens which are not in the filter are ignored. A token matching as a false positive is removed anyway, which may remove a token sharing its bits too.
+    pub fn without_tokens(&self, content: &str) -> Option<Filter> {
+        self.membership.without(content).map(|membership| Filter::of(Arc::from(membership)))
+    }
+
+    // A copy of the filter with the tokens of `content` stacked in a new layer, sized for them.
+    // Layers have decreasing error rates, which keeps the error rate of the stack below error_rate / (1 - LAYER_TIGHTENING).
+    pub(crate) fn append(&self, hash: HashFunction, error_rate: f32, content: &str) -> Result<Filter, Error> {
+        if tokens(content).next().is_none() {
+            return Ok(self.clone());
+        }
+        let mut layers = match self.membership.layers() {
+            Some(layers) => layers.to_vec(),
+            None => vec![self.clone()]
+        };
+        let layer_error_rate = (error_rate * LAYER_TIGHTENING.powi(layers.len() as i32)).max(MIN_ERROR_RATE);
+        layers.push(Filter::with_backend(self.backend(), hash, layer_error_rate, content)?);
+        Ok(Filter::of(Arc::new(StackedFilter { layers })))
+    }
+
+    // True for the filter of a document which was appended to, whose tokens are not all in its source
+    pub(crate) fn is_stacked(&self) -> bool {
+        self.membership.layers().is_some()
+    }
+
+    // Bits of a bloom filter, whose tokens set the same bits in filters of the same size
+    pub(crate) fn bitfield(&self) -> Option<Vec<u8>> {
+        self.membership.bitfield()
+    }
+
+    // Representation of the filter inside the "bloom_filters" map of a dump
+    pub(crate) fn to_raw(&self) -> Box<RawValue> {
+        self.membership.to_raw()
+    }
+
+    // Bloom filters are the bare layout of index-bloom, the other backends are tagged with their name
+    pub(crate) fn from_raw(error_rate: f32, raw: &RawValue) -> Self {
+        let membership: Arc<dyn Membership> = match raw_backend(raw) {
+            None => bloom_from_raw(error_rate, raw),
+            #[cfg(feature = "xor")]
+            Some("xor") => Arc::new(XorFilter::from_raw(raw)),
+            #[cfg(feature = "cuckoo")]
+            Some("cuckoo") => Arc::new(CuckooFilter::from_raw(raw)),
+            #[cfg(feature = "counting")]
+            Some("counting") => Arc::new(CountingFilter::from_raw(raw)),
+            Some("stacked") => Arc::new(StackedFilter::from_raw(error_rate, raw)),
+            Some(name) => panic!("Filter of the {} backend needs the {} feature", name, name)
+        };
+        Filter::of(membership)
+    }
+}
+
+// Name of the backend tagging a raw filter, e.g. {"xor":{...}}, none for a bloom filter
+fn raw_backend(raw: &RawValue) -> Option<&str> {
+    let tagged = raw.get().strip_prefix("{\"")?;
+    let name = &tagged[..tagged.find('"')?];
+    if name == "key_size" || name == "bitfield" || name == "bitfield_size" {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+// Bloom filters written with a hash function are built by cli-bloom, the others by index-bloom in older dumps
+fn bloom_from_raw(error_rate: f32, raw: &RawValue) -> Arc<dyn Membership> {
+    let layout: BloomLayout = serde_json::from_str(raw.get()).expect("Invalid bloom filter");
+    match layout.hash {
+        Some(hash) => Arc::new(BloomFilter {
+            key_size: layout.key_size,
+            bitfield: layout.bitfield,
+            bitfield_size: layout.bitfield_size,
+            hash
+        }),
+        None => Arc::new(IndexBloomFilter {
+            index: Index::restore(&single_filter_json(error_rate, raw)),
+            layout
+        })
+    }
+}
+
+// Proportion of the `size` bits of `bitfield` which are set
+fn bitfield_fill_ratio(bitfield: &[u8], size: usize) -> f64 {
+    if size == 0 {
+        return 0.0;
+    }
+    let set: u32 = bitfield.iter().map(|byte| byte.count_ones()).sum();
+    set as f64 / size as f64
+}
+
+// A bloom filter with the layout of index-bloom, each token setting `key_size` bits chosen by double hashing of its hash
+#[derive(Serialize)]
+struct BloomFilter {
+    key_size: usize,
+    bitfield: Vec<u8>,
+    bitfield_size: usize,
+    // Always written, it tells the filter from those of index-bloom
+    hash: HashFunction
+}
+
+impl BloomFilter {
+    fn new(hash: HashFunction, error_rate: f32, content: &str) -> Self {
+        let keys = distinct_token_hashes(hash, content);
+        // Sized like an index-bloom filter of the same error rate
+        let bitfield_size = ((keys.len() as f64 * bits_per_token(error_rate)).ceil() as usize).max(1);
+        let mut filter = BloomFilter {
+            key_size: (-(error_rate as f64).log2()).ceil().max(1.0) as usize,
+            bitfield: vec![0; (bitfield_size + 7) / 8],
+            bitfield_size,
+            hash
+        };
+        for key in keys {
+            for position in double_hashing(key, filter.key_size, bitfield_size) {
+                filter.bitfield[position / 8] |= 1 << (position % 8);
+            }
+        }
+        filter
+    }
+
+    fn contains_key(&self, key: u64) -> bool {
+        double_hashing(key, self.key_size, self.bitfield_size).all(|position| self.bitfield[position / 8] & (1 << (position % 8)) != 0)
+    }
+}
+
+impl Membership for BloomFilter {
+    fn contains_tokens(&self, tokens: &[String]) -> Result<bool, Error> {
+        Ok(tokens.iter().all(|token| self.contains_key(self.hash.hash(token, 0))))
+    }
+
+    fn bits(&self) -> usize {
+        self.bitfield_size
+    }
+
+    fn hash_count(&self) -> usize {
+        self.key_size
+    }
+
+    fn fill_ratio(&self) -> f64 {
+        bitfield_fill_ratio(&self.bitfield, self.bitfield_size)
+    }
+
+    fn backend(&self) -> Backend {
+        Backend::Bloom
+    }
+
+    fn bitfield(&self) -> Option<Vec<u8>> {
+        Some(self.bitfield.clone())
+    }
+
+    fn to_raw(&self) -> Box<RawValue> {
+        serde_json::value::to_raw_value(self).expect("Impossible to serialize filter")
+    }
+}
+
+// A bloom filter built by index-bloom, which alone knows how it hashed the tokens, restored from an older dump
+struct IndexBloomFilter {
+    layout: BloomLayout,
+    index: Index
+}
+
+impl Membership for IndexBloomFilter {
+    fn contains_tokens(&self, tokens: &[String]) -> Result<bool, Error> {
+        Ok(self.index.search(&tokens.join(" "))?.is_some())
+    }
+
+    fn bits(&self) -> usize {
+        self.layout.bitfield_size
+    }
+
+    fn hash_count(&self) -> usize {
+        self.layout.key_size
+    }
+
+    fn fill_ratio(&self) -> f64 {
+        bitfield_fill_ratio(&self.layout.bitfield, self.layout.bitfield_size)
+    }
+
+    fn backend(&self) -> Backend {
+        Backend::Bloom
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string(&self.index).expect("Impossible to serialize filter")
+    }
+
+    fn bitfield(&self) -> Option<Vec<u8>> {
+        Some(self.layout.bitfield.clone())
+    }
+
+    fn to_raw(&self) -> Box<RawValue> {
+        serde_json::value::to_raw_value(&self.layout).expect("Impossible to serialize filter")
+    }
+}
+
+// Filters of the successive parts of a document, a token being in the document when it is in any of them
+struct StackedFilter {
+    layers: Vec<Filter>
+}
+
+impl StackedFilter {
+    fn from_raw(error_rate: f32, raw: &RawValue) -> Self {
+        let mut tagged: BTreeMap<String, Vec<Box<RawValue>>> = serde_json::from_str(raw.get()).expect("Invalid stacked filter");
+        let layers = tagged.remove("stacked").expect("Invalid stacked filter").iter()
+            .map(|raw| Filter::from_raw(error_rate, raw))
+            .collect();
+        StackedFilter {
+            layers
+        }
+    }
+}
+
+impl Membership for StackedFilter {
+    fn contains_tokens(&self, tokens: &[String]) -> Result<bool, Error> {
+        // The tokens of the keywords may be spread over several layers
+        for token in tokens {
+            let mut found = false;
+            for layer in &self.layers {
+                if layer.contains_tokens(std::slice::from_ref(token))? {
+                    found = true;
+                    break;
+                }
+            }
+            if !found {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn bits(&self) -> usize {
+        self.layers.iter().map(Filter::bits).sum()
+    }
+
+    // Lookups made for each token
+    fn hash_count(&self) -> usize {
+        self.layers.iter().map(Filter::hash_count).sum()
+    }
+
+    fn fill_ratio(&self) -> f64 {
+        let bits = self.bits();
+        if bits == 0 {
+            return 0.0;
+        }
+        self.layers.iter().map(|layer| layer.fill_ratio() * layer.bits() as f64).sum::<f64>() / bits as f64
+    }
+
+    // A token matches when it matches in any layer
+    fn false_positive_rate(&self) -> f64 {
+        1.0 - self.layers.iter().map(|layer| 1.0 - layer.false_positive_rate()).product::<f64>()
+    }
+
+    fn backend(&self) -> Backend {
+        self.layers[0].backend()
+    }
+
+    fn to_raw(&self) -> Box<RawValue> {
+        let mut tagged = BTreeMap::new();
+        tagged.insert("stacked", self.layers.iter().map(Filter::to_raw).collect::<Vec<Box<RawValue>>>());
+        RawValue::from_string(serde_json::to_string(&tagged).expect("Impossible to serialize filter")).expect("Impossible to serialize filter")
+    }
+
+    fn layers(&self) -> Option<&[Filter]> {
+        Some(&self.layers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_contains_keywords() {
+        let filter = Filter::new(0.01, "word1 word2").unwrap();
+        assert!(filter.contains("word1").unwrap());
+        assert!(filter.contains("Word2, word1").unwrap());
+        assert!(!filter.contains("word3").unwrap());
+    }
+
+    #[test]
+    fn raw_representation() {
+        let filter = Filter::new(0.1, "word1 word2\nword3\n\nword4\n").unwrap();
+        let raw = filter.to_raw();
+        assert_eq!("{\"key_size\":4,\"bitfield\":[189,105,3],\"bitfield_size\":20,\"hash\":\"fnv\"}", raw.get());
+        assert!(Filter::from_raw(0.1, &raw).contains("word4").unwrap());
+        assert_eq!(20, filter.bits());
+        assert_eq!(4, filter.hash_count());
+        assert_eq!(0.6, filter.fill_ratio());
+        assert_eq!(0.6f64.powi(4), filter.false_positive_rate());
+    }
+
+    #[test]
+    fn index_bloom_filter() {
+        let raw = RawValue::from_string("{\"key_size\":4,\"bitfield\":[248,242,8],\"bitfield_size\":20}".to_string()).unwrap();
+        let filter = Filter::from_raw(0.1, &raw);
+        assert!(filter.contains("word1 Word4").unwrap());
+        assert_eq!(0.55, filter.fill_ratio());
+        assert_eq!(raw.get(), filter.to_raw().get());
+        assert!(Filter::from_json(&filter.to_json()).contains("word3").unwrap());
+    }
+
+    #[test]
+    fn bloom_filter_hash_function() {
+        let filter = Filter::with_backend(Backend::Bloom, HashFunction::XxHash, 0.01, "word1 word2").unwrap();
+        let restored = Filter::from_json(&filter.to_json());
+        assert!(restored.contains("word2").unwrap());
+        assert_eq!(filter.bitfield(), restored.bitfield());
+        assert_ne!(filter.bitfield(), Filter::new(0.01, "word1 word2").unwrap().bitfield());
+    }
+
+    #[test]
+    fn appended_layers() {
+        let filter = Filter::new(0.01, "word1 word2").unwrap();
+        let appended = filter.append(HashFunction::Fnv, 0.01, "word3").unwrap().append(HashFunction::Fnv, 0.01, "word4\n").unwrap();
+        assert!(appended.contains("word1 word3 Word4").unwrap());
+        assert!(!appended.contains("word1 word5").unwrap());
+        assert_eq!(3, appended.membership.layers().unwrap().len());
+        assert!(appended.false_positive_rate() > filter.false_positive_rate());
+        let restored = Filter::from_raw(0.01, &appended.to_raw());
+        assert!(restored.contains("word2 word4").unwrap());
+        assert_eq!(appended.bits(), restored.bits());
+        assert_eq!(filter.bits(), filter.append(HashFunction::Fnv, 0.01, " ").unwrap().bits());
+    }
+
+    #[test]
+    fn bloom_filter_cannot_remove_tokens() {
+        assert!(Filter::new(0.01, "word1 word2").unwrap().without_tokens("word1").is_none());
+    }
+
+    #[test]
+    #[should_panic(expected="Invalid error rate NaN")]
+    fn error_rate_is_nan() {
+        check_error_rate(f32::NAN);
+    }
+
+    #[test]
+    #[should_panic(expected="Invalid error rate 1")]
+    fn error_rate_is_one() {
+        check_error_rate(1.0);
+    }
+
+    #[test]
+    #[should_panic(expected="Error rate 0.0000000000001 is too small : each token would take about 62 bits")]
+    fn error_rate_is_too_small() {
+        check_error_rate(1e-13);
+    }
+
+    #[test]
+    fn error_rate_of_bloom_parameters() {
+        let error_rate = error_rate_for(Some(10.0), None);
+        assert!((bits_per_token(error_rate) - 10.0).abs() < 0.001);
+        let error_rate = error_rate_for(None, Some(4));
+        assert_eq!(4, Filter::new(error_rate, "word1 word2 word3").unwrap().hash_count());
+        assert_eq!(error_rate_for(Some(10.0), None), error_rate_for(Some(10.0), Some(7)));
+    }
+
+    #[test]
+    #[should_panic(expected="10 bits per token cannot use 3 hash functions")]
+    fn inconsistent_bloom_parameters() {
+        error_rate_for(Some(10.0), Some(3));
+    }
+
+    #[test]
+    #[cfg(not(feature = "cuckoo"))]
+    fn unavailable_backend() {
+        let error = Filter::with_backend(Backend::Cuckoo, HashFunction::Fnv, 0.01, "word1 word2").err().unwrap();
+        assert_eq!("Filter backend cuckoo needs the cuckoo feature", error.to_string());
+    }
+}
@@ -1,15 +1,359 @@
-use std::fs;
-use std::fs::File;
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Bound;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::io;
 use std::io::Read;
 use std::io::Write;
-use index_bloom::Index;
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Serialize, Serializer, Deserialize};
+use serde::ser;
+use serde::ser::SerializeMap;
+use serde_json::value::RawValue;
+use crate::analyzer::Analyzer;
+use crate::audit::{AuditLog, Outcome};
+use crate::retry::RetryPolicy;
+use crate::politeness::Politeness;
+use crate::progress::ProgressObserver;
+use crate::lock::{read_locked, write_locked};
+use crate::document::{Document, Staleness, content_hash, front_matter};
 use crate::errors::Error;
+#[cfg(feature = "archive")]
+use crate::archive;
+#[cfg(feature = "compressed")]
+use crate::compression::decompressing;
+#[cfg(feature = "object-store")]
+use crate::remote::{self, Remote};
+use crate::extract::{Split, split_documents};
+use crate::extractor::{ContentExtractor, Extractors};
+use crate::file_system::{FileMetadata, FileSystem, RealFileSystem};
+use crate::filter::{Filter, bits_per_token, check_error_rate};
+use crate::glob::Glob;
+use crate::membership::{self, Backend, HashFunction};
+use crate::pretty::to_string_pretty;
+use crate::sections::{SECTIONS_MAGIC, read_sections, write_sections};
+use crate::query::{Query, Sort};
+use crate::snapshot::Snapshot;
+use crate::stats::{Stats, Refresh, Changes, Compaction, IngestReport, Issue, Hits};
+use crate::store::{IndexStore, MemoryStore, ENTRY_OVERHEAD};
 
 /// A full-text search index with file system operations.
-pub struct FsIndex {
-    index: Index
+///
+/// Each document has its own filter, kept by an [`IndexStore`] (in memory by default).
+pub struct FsIndex<S = MemoryStore> {
+    error_rate: f32,
+    store: S,
+    documents: BTreeMap<String, Document>,
+    // Keys of the documents ingested from each file
+    keys_by_path: HashMap<PathBuf, BTreeSet<String>>,
+    // A file indexed with each content hash, to find the duplicates
+    paths_by_hash: HashMap<String, PathBuf>,
+    // Key of the document of each alias
+    aliases: HashMap<String, String>,
+    audit: Option<AuditLog>,
+    fail_fast: bool,
+    dedup: bool,
+    cancel: Option<Arc<AtomicBool>>,
+    progress: Option<Arc<dyn ProgressObserver>>,
+    ingest_hook: Option<IngestHook>,
+    retry: RetryPolicy,
+    politeness: Politeness,
+    split: Split,
+    extractors: Extractors,
+    key_mapper: Option<KeyMapper>,
+    key_prefix: String,
+    // Tags of the documents ingested from now on
+    tags: BTreeMap<String, String>,
+    backend: Backend,
+    hash_function: HashFunction,
+    analyzer: Analyzer,
+    file_system: Arc<dyn FileSystem>
+}
+
+// Key of the documents of a file, instead of its path
+type KeyMapper = Arc<dyn Fn(&Path) -> String + Send + Sync>;
+
+// Called with the key, the number of tokens and the outcome of each document ingested
+type IngestHook = Arc<dyn Fn(&str, usize, Outcome) + Send + Sync>;
+
+/// Copy of the index and its store.
+///
+/// Filters are immutable once built, so they are shared by the copies instead of being duplicated.
+/// The copy appends to the same audit log.
+///
+/// # Panics
+///
+/// Panics if the audit log cannot be shared.
+impl<S: IndexStore + Clone> Clone for FsIndex<S> {
+    fn clone(&self) -> Self {
+        FsIndex {
+            error_rate: self.error_rate,
+            store: self.store.clone(),
+            documents: self.documents.clone(),
+            keys_by_path: self.keys_by_path.clone(),
+            paths_by_hash: self.paths_by_hash.clone(),
+            aliases: self.aliases.clone(),
+            audit: self.audit.as_ref().map(|audit| audit.try_clone().expect("Impossible to share audit log")),
+            fail_fast: self.fail_fast,
+            dedup: self.dedup,
+            cancel: self.cancel.clone(),
+            progress: self.progress.clone(),
+            ingest_hook: self.ingest_hook.clone(),
+            retry: self.retry,
+            politeness: self.politeness,
+            split: self.split,
+            extractors: self.extractors.clone(),
+            key_mapper: self.key_mapper.clone(),
+            key_prefix: self.key_prefix.clone(),
+            tags: self.tags.clone(),
+            backend: self.backend,
+            hash_function: self.hash_function,
+            analyzer: self.analyzer.clone(),
+            file_system: self.file_system.clone()
+        }
+    }
+}
+
+/// A one line summary of the index: number of documents and error rate.
+///
+/// The summary does not visit the filters, see [`FsIndex::memory_usage`] for their size.
+impl<S: IndexStore> fmt::Display for FsIndex<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} documents, error rate {}", self.documents.len(), self.error_rate)
+    }
+}
+
+impl<S: IndexStore> fmt::Debug for FsIndex<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FsIndex")
+            .field("documents", &self.documents.len())
+            .field("error_rate", &self.error_rate)
+            .finish()
+    }
+}
+
+// Version of the dump format written, increased when the format changes in a way older versions of cli-bloom cannot read
+const DUMP_VERSION: u32 = 3;
+
+// Documents per section of a dump in sections, fixed so that the sections do not depend on the number of threads
+const SECTION_DOCUMENTS: usize = 1024;
+
+// A bloom filter with more times the bits needed by its tokens is rebuilt by a compaction
+const OVERSIZED_RATIO: f64 = 2.0;
+
+// Dumps written before the format was versioned
+pub(crate) fn legacy_version() -> u32 {
+    1
+}
+
+#[derive(Serialize)]
+struct Dump<'a> {
+    version: u32,
+    error_rate: f32,
+    #[serde(skip_serializing_if = "Backend::is_bloom")]
+    backend: Backend,
+    #[serde(skip_serializing_if = "HashFunction::is_fnv")]
+    hash_function: HashFunction,
+    #[serde(skip_serializing_if = "Analyzer::is_default")]
+    analyzer: &'a Analyzer,
+    bloom_filters: Filters<'a>,
+    documents: &'a BTreeMap<String, Document>
+}
+
+struct Filters<'a> {
+    store: &'a dyn IndexStore,
+    documents: &'a BTreeMap<String, Document>
+}
+
+impl<'a> Serialize for Filters<'a> {
+    fn serialize<T: Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        for key in self.documents.keys() {
+            if let Some(filter) = self.store.get(key).map_err(<T::Error as ser::Error>::custom)? {
+                map.serialize_entry(key, &filter.to_raw())?;
+            }
+        }
+        map.end()
+    }
+}
+
+#[derive(Deserialize)]
+struct DumpFile {
+    #[serde(default = "legacy_version")]
+    version: u32,
+    error_rate: f32,
+    #[serde(default)]
+    backend: Backend,
+    #[serde(default)]
+    hash_function: HashFunction,
+    #[serde(default)]
+    analyzer: Analyzer,
+    bloom_filters: BTreeMap<String, Box<RawValue>>,
+    documents: Option<BTreeMap<String, Document>>
+}
+
+#[derive(Serialize)]
+struct SectionsHeader<'a> {
+    version: u32,
+    error_rate: f32,
+    #[serde(skip_serializing_if = "Backend::is_bloom")]
+    backend: Backend,
+    #[serde(skip_serializing_if = "HashFunction::is_fnv")]
+    hash_function: HashFunction,
+    #[serde(skip_serializing_if = "Analyzer::is_default")]
+    analyzer: &'a Analyzer,
+    documents: &'a BTreeMap<String, Document>
+}
+
+#[derive(Deserialize)]
+struct SectionsHeaderFile {
+    #[serde(default = "legacy_version")]
+    version: u32,
+    error_rate: f32,
+    #[serde(default)]
+    backend: Backend,
+    #[serde(default)]
+    hash_function: HashFunction,
+    #[serde(default)]
+    analyzer: Analyzer,
+    documents: BTreeMap<String, Document>
+}
+
+// Just the version of a dump, other fields are skipped
+#[derive(Deserialize)]
+struct DumpVersion {
+    #[serde(default = "legacy_version")]
+    version: u32
+}
+
+impl DumpFile {
+    // Bring a dump of an older format version to the current one, in memory
+    fn upgrade(mut self) -> Result<Self, serde_json::Error> {
+        check_version(self.version)?;
+        if self.version < 2 {
+            // Dumps written before documents metadata existed only contain ingested files
+            if self.documents.is_none() {
+                self.documents = Some(self.bloom_filters.keys()
+                    .map(|key| (key.clone(), Document { path: Some(PathBuf::from(key)), ..Document::default() }))
+                    .collect());
+            }
+        }
+        // Bloom filters written before version 3 were built by index-bloom, they are restored as such
+        self.version = DUMP_VERSION;
+        Ok(self)
+    }
+}
+
+fn check_version(version: u32) -> Result<(), serde_json::Error> {
+    if version > DUMP_VERSION {
+        return Err(serde::de::Error::custom(format!("dump format version {} is newer than the supported version {}, upgrade cli-bloom", version, DUMP_VERSION)));
+    }
+    Ok(())
+}
+
+// `name` with its leading `from` replaced by `to`, if `from` is a whole path prefix of `name`
+fn mapped_prefix(name: &str, from: &str, to: &str) -> Option<String> {
+    let rest = name.strip_prefix(from)?;
+    let boundary = rest.is_empty() || from.ends_with(std::path::is_separator) || rest.starts_with(std::path::is_separator);
+    if boundary && !from.is_empty() {
+        Some(format!("{}{}", to, rest))
+    } else {
+        None
+    }
+}
+
+/// Error returned by [`FsIndex::map_prefix`] when moved documents would take the names of other documents.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrefixCollision {
+    keys: Vec<String>
+}
+
+impl PrefixCollision {
+    /// Return the keys and aliases given to more than one document by the mapping, sorted.
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+}
+
+impl std::error::Error for PrefixCollision {}
+
+impl fmt::Display for PrefixCollision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Mapped documents would share the names {}", self.keys.join(", "))
+    }
+}
+
+// Content of a dump, upgraded to the current format whatever its format and version
+struct Loaded {
+    error_rate: f32,
+    backend: Backend,
+    hash_function: HashFunction,
+    analyzer: Analyzer,
+    filters: Vec<(String, Filter)>,
+    documents: BTreeMap<String, Document>
+}
+
+impl Loaded {
+    fn from_json<F: Fn(&str) -> bool>(serialized: &str, selected: &F) -> Result<Self, serde_json::Error> {
+        let dump: DumpFile = serde_json::from_str(serialized)?;
+        let dump = dump.upgrade()?;
+        let filters = dump.bloom_filters.into_iter()
+            .filter(|(key, _)| selected(key.as_str()))
+            .map(|(key, raw)| (key, Filter::from_raw(dump.error_rate, &raw)))
+            .collect();
+        Ok(Loaded {
+            error_rate: dump.error_rate,
+            backend: dump.backend,
+            hash_function: dump.hash_function,
+            analyzer: dump.analyzer,
+            filters,
+            documents: dump.documents.unwrap_or_default()
+        })
+    }
+
+    fn from_sections<F: Fn(&str) -> bool + Sync>(serialized: &str, selected: &F) -> Result<Self, serde_json::Error> {
+        let sections = read_sections(serialized)?;
+        let (header, sections) = match sections.split_first() {
+            Some((header, sections)) => (header, sections),
+            None => return Err(serde::de::Error::custom("missing dump header"))
+        };
+        let header: SectionsHeaderFile = serde_json::from_str(header)?;
+        // Sections were introduced with documents metadata, so older versions need no upgrade
+        check_version(header.version)?;
+        let error_rate = header.error_rate;
+        let parsed: Vec<Result<Vec<(String, Filter)>, serde_json::Error>> = thread::scope(|scope| {
+            let handles: Vec<_> = sections.iter()
+                .map(|section| scope.spawn(move || -> Result<Vec<(String, Filter)>, serde_json::Error> {
+                    let raws: BTreeMap<String, Box<RawValue>> = serde_json::from_str(section)?;
+                    Ok(raws.into_iter()
+                        .filter(|(key, _)| selected(key.as_str()))
+                        .map(|(key, raw)| (key, Filter::from_raw(error_rate, &raw)))
+                        .collect())
+                }))
+                .collect();
+            handles.into_iter().map(|handle| handle.join().expect("Restore thread panicked")).collect()
+        });
+        let mut filters = Vec::new();
+        for section in parsed {
+            filters.extend(section?);
+        }
+        Ok(Loaded {
+            error_rate,
+            backend: header.backend,
+            hash_function: header.hash_function,
+            analyzer: header.analyzer,
+            filters,
+            documents: header.documents
+        })
+    }
 }
 
 impl FsIndex {
@@ -17,6 +361,10 @@ impl FsIndex {
     ///
     /// The `error_rate` is the probability of false positive when searching for keywords
     ///
+    /// # Panics
+    ///
+    /// Panics if `error_rate` is not strictly between 0 and 1, or is so small (below 1e-12) that every token would take dozens of bits in the filters.
+    ///
     /// # Example
     ///
     /// ```
@@ -24,16 +372,261 @@ impl FsIndex {
     /// let mut fs_index = FsIndex::new(0.00001);
     /// ```
     pub fn new(error_rate: f32) -> Self {
+        FsIndex::with_store(error_rate, MemoryStore::default())
+    }
+
+    /// Restore a `FsIndex` from a previous dump.
+    ///
+    /// A dump is a `FsIndex` serialized in JSON format.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the content is not a valid `FsIndex` representation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let fs_index = FsIndex::restore("/foo/dump.json");
+    /// # }
+    /// ```
+    pub fn restore(path :&str) -> Self {
+        FsIndex::restore_with_store(path, MemoryStore::default())
+    }
+
+    /// Return the format version of the dump at `path`, without building its filters.
+    ///
+    /// Dumps written before the format was versioned are version 1. [`FsIndex::restore`] reads every version up to the current one, upgrading older ones in memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file cannot be read or is not a dump.
+    pub fn dump_version(path: &str) -> u32 {
+        let serialized = read_locked(Path::new(path)).expect(format!("Unable to read dump file {}", &path).as_str());
+        let header = if serialized.starts_with(SECTIONS_MAGIC) {
+            read_sections(&serialized).and_then(|sections| serde_json::from_str::<DumpVersion>(sections.first().copied().unwrap_or_default()))
+        } else {
+            serde_json::from_str::<DumpVersion>(&serialized)
+        };
+        header.expect(format!("Invalid dump file {}", &path).as_str()).version
+    }
+
+    /// Rewrite the dump at `path` in the current format at `dest`, which may be `path` itself, and return its previous format version.
+    ///
+    /// A dump in sections stays in sections.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the dump cannot be restored or written.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn migrate_dump()  {
+    /// let previous = FsIndex::migrate("/foo/dump.json", "/foo/dump.json");
+    /// # }
+    /// ```
+    pub fn migrate(path: &str, dest: &str) -> u32 {
+        let version = FsIndex::dump_version(path);
+        let sections = read_locked(Path::new(path)).map(|serialized| serialized.starts_with(SECTIONS_MAGIC)).unwrap_or(false);
+        let index = FsIndex::restore(path);
+        if sections {
+            index.dump_sections(dest);
+        } else {
+            index.dump(dest);
+        }
+        version
+    }
+
+    /// Restore only the documents of a dump whose key starts with `prefix`.
+    ///
+    /// Other documents are skipped while reading the dump, so their filters are never built.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the content is not a valid `FsIndex` representation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let fs_index = FsIndex::restore_filtered("/foo/dump.json", "/foo/src/");
+    /// # }
+    /// ```
+    pub fn restore_filtered(path: &str, prefix: &str) -> Self {
+        FsIndex::load(path, MemoryStore::default(), |key| key.starts_with(prefix))
+    }
+}
+
+impl<S: IndexStore> FsIndex<S> {
+    /// Constructs a `FsIndex` keeping its filters in `store`.
+    ///
+    /// Filters already in `store` are part of the index, without metadata.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `error_rate` is invalid (see [`FsIndex::new`]) or if the keys of `store` cannot be listed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::{FsIndex, MemoryStore};
+    /// let mut fs_index = FsIndex::with_store(0.00001, MemoryStore::default());
+    /// ```
+    pub fn with_store(error_rate: f32, store: S) -> Self {
+        check_error_rate(error_rate);
+        let documents = store.keys().expect("Unable to list index store keys").into_iter()
+            .map(|key| (key, Document::default()))
+            .collect();
         FsIndex {
-            index: Index::new(error_rate)
+            error_rate,
+            store,
+            documents,
+            keys_by_path: HashMap::new(),
+            paths_by_hash: HashMap::new(),
+            aliases: HashMap::new(),
+            audit: None,
+            fail_fast: false,
+            dedup: false,
+            cancel: None,
+            progress: None,
+            ingest_hook: None,
+            retry: RetryPolicy::none(),
+            politeness: Politeness::default(),
+            split: Split::default(),
+            extractors: Extractors::default(),
+            key_mapper: None,
+            key_prefix: String::new(),
+            tags: BTreeMap::new(),
+            backend: Backend::default(),
+            hash_function: HashFunction::default(),
+            analyzer: Analyzer::default(),
+            file_system: Arc::new(RealFileSystem)
+        }
+    }
+
+    /// Restore a `FsIndex` from a previous dump, inserting its filters in `store`.
+    ///
+    /// See [`FsIndex::restore`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the content is not a valid `FsIndex` representation or if a filter cannot be inserted in `store`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::{FsIndex, MemoryStore};
+    /// # fn search_index()  {
+    /// let fs_index = FsIndex::restore_with_store("/foo/dump.json", MemoryStore::default());
+    /// # }
+    /// ```
+    pub fn restore_with_store(path: &str, store: S) -> Self {
+        FsIndex::load(path, store, |_| true)
+    }
+
+    /// Restore only the documents of a dump whose key starts with `prefix`, inserting their filters in `store`.
+    ///
+    /// See [`FsIndex::restore_filtered`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the content is not a valid `FsIndex` representation or if a filter cannot be inserted in `store`.
+    pub fn restore_filtered_with_store(path: &str, prefix: &str, store: S) -> Self {
+        FsIndex::load(path, store, |key| key.starts_with(prefix))
+    }
+
+    fn load<F: Fn(&str) -> bool + Sync>(path: &str, store: S, selected: F) -> Self {
+        #[cfg(feature = "object-store")]
+        if remote::is_remote(path) {
+            let serialized = Remote::open(path).and_then(|remote| remote.read_string())
+                .unwrap_or_else(|error| panic!("Unable to read dump file {} : {}", path, error));
+            return FsIndex::from_serialized(&serialized, path, store, selected);
+        }
+        if Path::new(path).is_file() {
+            let serialized = read_locked(Path::new(path)).expect(format!("Unable to read dump file {}", &path).as_str());
+            FsIndex::from_serialized(&serialized, path, store, selected)
+        } else {
+            panic!(format!("File not found {}", &path));
         }
     }
 
+    fn from_serialized<F: Fn(&str) -> bool + Sync>(serialized: &str, path: &str, store: S, selected: F) -> Self {
+        let loaded = if serialized.starts_with(SECTIONS_MAGIC) {
+            Loaded::from_sections(serialized, &selected)
+        } else {
+            Loaded::from_json(serialized, &selected)
+        };
+        let dump = loaded.expect(format!("Invalid dump file {}", &path).as_str());
+        let mut fs_index = FsIndex {
+            error_rate: dump.error_rate,
+            store,
+            documents: BTreeMap::new(),
+            keys_by_path: HashMap::new(),
+            paths_by_hash: HashMap::new(),
+            aliases: HashMap::new(),
+            audit: None,
+            fail_fast: false,
+            dedup: false,
+            cancel: None,
+            progress: None,
+            ingest_hook: None,
+            retry: RetryPolicy::none(),
+            politeness: Politeness::default(),
+            split: Split::default(),
+            extractors: Extractors::default(),
+            key_mapper: None,
+            key_prefix: String::new(),
+            tags: BTreeMap::new(),
+            backend: dump.backend,
+            hash_function: dump.hash_function,
+            analyzer: dump.analyzer,
+            file_system: Arc::new(RealFileSystem)
+        };
+        let mut documents = dump.documents;
+        for (key, filter) in dump.filters {
+            fs_index.store.insert(&key, filter).expect("Unable to insert filter in index store");
+            let document = documents.remove(&key).unwrap_or_default();
+            fs_index.insert_document(key, document);
+        }
+        fs_index
+    }
+
     /// Ingest a file or a directory content.
     ///
     /// Insert the content designated by the `source` parameter.
     /// If `source` is a file, ingest its content. If `source` is a directory, ingests all these files at the first level.
     /// The document key is the file path.
+    /// Jupyter notebooks (`.ipynb`) are ingested as one document per markdown or code cell, keyed `path#cellN` where `N` is the cell position.
+    /// A file already indexed with the same content hash is not ingested again, whatever its modification time.
+    /// Return a report of the processed files.
+    /// Files of a directory which cannot be read are listed in the report as failures and the ingestion goes on with the other files, unless [`FsIndex::set_fail_fast`] is set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `source` parameter is not a regular file, directory or if the `source` content cannot be read.
+    /// In fail fast mode, also panics on the first file of the directory which cannot be read.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// let report = fs_index.ingest("/foo/bar");
+    /// println!("{} files indexed, {} skipped", report.indexed, report.skipped.len());
+    /// # }
+    /// ```
+    pub fn ingest(&mut self, source: &str) -> IngestReport {
+        self.ingest_source(source, false)
+    }
+
+    /// Ingest a file or a whole directory tree.
+    ///
+    /// Same as [`FsIndex::ingest`], except that the files of all sub-directories are also ingested.
     ///
     /// # Panics
     ///
@@ -45,23 +638,116 @@ impl FsIndex {
     /// # use cli_bloom::FsIndex;
     /// # fn search_index()  {
     /// let mut fs_index = FsIndex::new(0.00001);
-    /// fs_index.ingest("/foo/bar");
+    /// fs_index.ingest_recursive("/foo/bar");
     /// # }
     /// ```
-    pub fn ingest(&mut self, source: &str) {
-        let src_path = PathBuf::from(source);
-        if src_path.is_file() {
-            match self.index_file(src_path) {
-                Ok(_) => return,
-                Err(error) => panic!("{}", error)
+    pub fn ingest_recursive(&mut self, source: &str) -> IngestReport {
+        self.ingest_source(source, true)
+    }
+
+    /// Ingest several sources in a single run, each one a file or a directory.
+    ///
+    /// Each source is ingested like with [`FsIndex::ingest`], and a single report covers them all.
+    /// Hard links are detected across the directories of all the sources, so a file reached from several of them is ingested once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a source is not a regular file or directory, or if its content cannot be read (see [`FsIndex::ingest`]).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// let report = fs_index.ingest_many(&["/foo/dir1", "/foo/dir2", "/foo/file.txt"]);
+    /// # }
+    /// ```
+    pub fn ingest_many<I, T>(&mut self, sources: I) -> IngestReport where I: IntoIterator<Item = T>, T: AsRef<str> {
+        let mut report = IngestReport::default();
+        let mut visited = HashMap::new();
+        for source in sources {
+            if self.cancelled(&mut report) {
+                break;
+            }
+            self.ingest_into(source.as_ref(), false, &mut report, &mut visited);
+        }
+        self.observe(|observer| observer.on_finish(&report));
+        report
+    }
+
+    /// Ingest a list of files.
+    ///
+    /// Paths which are not regular files are skipped, and files which cannot be read are listed in the report as failures like in a directory (see [`FsIndex::ingest`]).
+    ///
+    /// # Panics
+    ///
+    /// In fail fast mode, panics on the first file which cannot be read.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// let report = fs_index.ingest_files(vec!["/foo/bar.txt", "/foo/baz.log"]);
+    /// # }
+    /// ```
+    pub fn ingest_files<I, P>(&mut self, paths: I) -> IngestReport where I: IntoIterator<Item = P>, P: AsRef<Path> {
+        let mut report = IngestReport::default();
+        for path in paths {
+            if self.cancelled(&mut report) {
+                break;
+            }
+            let path = path.as_ref();
+            let result = match self.file_system.metadata(path) {
+                Ok(metadata) if metadata.is_file => match self.ingest_file(path, true, &mut report) {
+                    // Failures are already in the report
+                    Err(error) if self.fail_fast => Err(error),
+                    _ => Ok(())
+                },
+                Ok(_) => {
+                    self.observe(|observer| observer.on_skip(path, "not a regular file"));
+                    report.skipped.push(Issue { path: path.to_path_buf(), reason: "not a regular file".to_string() });
+                    Ok(())
+                },
+                Err(error) => self.fail(&mut report, path, error.into())
+            };
+            if let Err(error) = result {
+                panic!("{}", error);
             }
-        } else if src_path.is_dir() {
-            match self.index_directory(src_path) {
-                Ok(_) => return,
-                Err(error) => panic!("{}", error)
+        }
+        self.observe(|observer| observer.on_finish(&report));
+        report
+    }
+
+    fn ingest_source(&mut self, source: &str, recursive: bool) -> IngestReport {
+        let mut report = IngestReport::default();
+        self.ingest_into(source, recursive, &mut report, &mut HashMap::new());
+        self.observe(|observer| observer.on_finish(&report));
+        report
+    }
+
+    // Hard links are tracked in `visited` across the sources of the same run
+    fn ingest_into(&mut self, source: &str, recursive: bool, report: &mut IngestReport, visited: &mut HashMap<(u64, u64), PathBuf>) {
+        #[cfg(feature = "object-store")]
+        if remote::is_remote(source) {
+            if let Err(error) = self.ingest_remote(source, report) {
+                panic!("Unable to list {} : {}", source, error);
             }
+            return;
+        }
+        let src_path = PathBuf::from(source);
+        let metadata = self.file_system.metadata(&src_path).unwrap_or_default();
+        let ingested = if metadata.is_file {
+            self.ingest_file(&src_path, false, report)
+        } else if metadata.is_dir {
+            self.index_directory(src_path, recursive, report, visited)
         } else {
             panic!("source type must be file or directory");
+        };
+        if let Err(error) = ingested {
+            panic!("{}", error);
         }
     }
 
@@ -93,229 +779,3086 @@ impl FsIndex {
     /// # }
     /// ```
     pub fn search(&self, keywords: &str) -> Option<Vec<&String>> {
-        match self.index.search(keywords) {
+        match self.matching(keywords, self.documents.iter()) {
             Ok(result) => return result,
             Err(error) => panic!("Error while searching for {} : {}", keywords, error)
         }
     }
 
-    /// Restore a `FsIndex` from a previous dump.
+    /// Search keywords in the documents whose key starts with `prefix`, e.g. the files of a directory subtree.
     ///
-    /// A dump is a `FsIndex` serialized in JSON format.
+    /// Keys are sorted, so only the documents under `prefix` are visited, however large the rest of the index is.
+    /// Return `None` if nothing match, as [`FsIndex::search`].
     ///
     /// # Panics
     ///
-    /// Panics if the content is not a valid `FsIndex` representation.
+    /// Panics if the `keywords` cannot be processed.
     ///
     /// # Example
     ///
     /// ```
     /// # use cli_bloom::FsIndex;
-    /// # fn search_index()  {
-    /// let fs_index = FsIndex::restore("/foo/dump.json");
+    /// # fn search_index() {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest_recursive("/foo");
+    /// let hits = fs_index.search_under("TODO", "/foo/src/");
     /// # }
     /// ```
-    pub fn restore(path :&str) -> Self {
-        if Path::new(path).is_file() {
-            let serialized = fs::read_to_string(path).expect(format!("Unable to read dump file {}", &path).as_str());
-            let deserialized = Index::restore(&serialized);
-            FsIndex {
-                index: deserialized
-            }
-        } else {
-            panic!(format!("File not found {}", &path));
+    pub fn search_under(&self, keywords: &str, prefix: &str) -> Option<Vec<&String>> {
+        let documents = self.documents.range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(|(key, _)| key.starts_with(prefix));
+        match self.matching(keywords, documents) {
+            Ok(result) => result,
+            Err(error) => panic!("Error while searching for {} : {}", keywords, error)
         }
     }
 
-    /// Dump a `FsIndex` in a file.
+    /// Search documents matching a structured `query`.
     ///
-    /// Create a Json representation of the current `FsIndex` and write it at the location designated by `path`.
+    /// Return `None` if nothing match. The result may contain false positives, as [`FsIndex::search`].
     ///
     /// # Panics
     ///
-    /// Panics if it is not possible to create the file at `path` or if it is impossible to serialize the `FsIndex`.
+    /// Panics if an error occurs during the search.
     ///
     /// # Example
     ///
     /// ```
-    /// # use cli_bloom::FsIndex;
+    /// # use cli_bloom::{FsIndex, Query};
     /// # fn search_index()  {
     /// let mut fs_index = FsIndex::new(0.00001);
-    /// fs_index.ingest("/foo/bar.txt");
-    /// fs_index.dump("/foo/dump.json");
+    /// fs_index.ingest("/foo/bar");
+    /// let hits = fs_index.query(&Query::term("TODO").or(Query::term("FIXME")));
     /// # }
     /// ```
-    pub fn dump(&self, path: &str) {
-        let dest = Path::new(&path);
-        let mut output_file = File::create(dest).expect(format!("Impossible to create dump file {}", &path).as_str());
-        let serialized = serde_json::to_string(&self.index).expect("Impossible to serialize file");
-        write!(output_file, "{}\n", serialized).expect("Impossible to write dump file");
-    }
-
-    fn index_directory(&mut self, path: PathBuf) -> Result<(), Error> {
-        for entry in fs::read_dir(path)? {
-            let entry = entry?;
-            let path = entry.path();
-            let metadata = fs::metadata(&path)?;
-            if metadata.is_file() {
-                match self.index_file(path) {
-                    Ok(_) => continue,
-                    Err(error) => match error {
-                        Error::IndexInvalidData(_) => continue,
-                        _ => return Err(error)
-                    }
-                }
-            }
-        }
-        Ok(())
+    pub fn query(&self, query: &Query) -> Option<Vec<&String>> {
+        self.query_selected(query, |_| true)
     }
 
-    fn index_file(&mut self, path: PathBuf) -> Result<(), Error> {
-        let mut content = String::new();
-        let mut file = File::open(&path)?;
-        file.read_to_string(&mut content)?;
-        self.index.ingest(path.to_str().unwrap().to_string(), &content)?;
-        Ok(())
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Search documents matching a structured `query` among those whose key matches `glob`.
+    ///
+    /// Keys are matched before the filters are evaluated, so the other documents cost almost nothing.
+    /// Return `None` if nothing match, as [`FsIndex::query`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if an error occurs during the search.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::{FsIndex, Glob, Query};
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest_recursive("/foo");
+    /// let hits = fs_index.query_matching(&Query::term("TODO"), &Glob::new("src/**/*.rs"));
+    /// # }
+    /// ```
+    pub fn query_matching(&self, query: &Query, glob: &Glob) -> Option<Vec<&String>> {
+        self.query_selected(query, |key| glob.matches(key))
+    }
+
+    /// Search documents matching a structured `query` among those whose key is `selected`, lazily.
+    ///
+    /// Return an iterator over the matching keys in ascending order, as [`FsIndex::search_iter`]: documents are evaluated as the iterator advances,
+    /// and `selected` is called on each key before its filter is evaluated.
+    ///
+    /// # Panics
+    ///
+    /// The iterator panics if an error occurs while evaluating a document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::{FsIndex, Query};
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest_recursive("/foo");
+    /// let query = Query::term("TODO");
+    /// let first_results: Vec<&String> = fs_index.query_where(&query, |key| key.ends_with(".rs")).take(10).collect();
+    /// # }
+    /// ```
+    pub fn query_where<'a, F: Fn(&str) -> bool + 'a>(&'a self, query: &'a Query, selected: F) -> impl Iterator<Item = &'a String> + 'a {
+        self.documents.iter()
+            .filter(move |(key, _)| selected(key) && match self.matches_query(key, query) {
+                Ok(matches) => matches,
+                Err(error) => panic!("Error while searching for {} : {}", query, error)
+            })
+            .map(|(key, document)| document.preferred.as_ref().unwrap_or(key))
+    }
+
+    /// Search documents matching a structured `query` for at most `timeout`.
+    ///
+    /// The search stops when `timeout` is elapsed, returning the hits found so far flagged as truncated, instead of blocking on a pathological query or a huge index.
+    /// Documents are evaluated in ascending order of their keys, so the hits of a truncated search are the first ones of [`FsIndex::query`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if an error occurs during the search.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use cli_bloom::{FsIndex, Query};
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest_recursive("/foo");
+    /// let hits = fs_index.query_within(&Query::term("TODO"), Duration::from_millis(200));
+    /// if hits.truncated {
+    ///     println!("Only the first {} hits were found", hits.keys.len());
+    /// }
+    /// # }
+    /// ```
+    pub fn query_within(&self, query: &Query, timeout: Duration) -> Hits<'_> {
+        let deadline = Instant::now() + timeout;
+        let mut hits = Hits::default();
+        for (key, document) in &self.documents {
+            if Instant::now() >= deadline {
+                hits.truncated = true;
+                break;
+            }
+            match self.matches_query(key, query) {
+                Ok(true) => hits.keys.push(document.preferred.as_ref().unwrap_or(key)),
+                Ok(false) => (),
+                Err(error) => panic!("Error while searching for {} : {}", query, error)
+            }
+        }
+        hits
+    }
+
+    /// Sort the `hits` of `query` in the `sort` order, ties in ascending order of their keys.
+    ///
+    /// Hits are keys or aliases of documents, as returned by [`FsIndex::query`]. The modification times are the ones recorded at ingestion, the files are not read.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an error occurs while scoring a document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::{FsIndex, Query, Sort};
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest_recursive("/foo");
+    /// let query = Query::parse("TODO OR FIXME").unwrap();
+    /// let mut hits = fs_index.query(&query).unwrap_or_default();
+    /// fs_index.sort_hits(&mut hits, Sort::Score, &query);
+    /// # }
+    /// ```
+    pub fn sort_hits(&self, hits: &mut [&String], sort: Sort, query: &Query) {
+        hits.sort();
+        match sort {
+            Sort::Path => hits.sort_by_cached_key(|key| {
+                let path = self.document(key).and_then(|document| document.path.clone());
+                (path.is_none(), path)
+            }),
+            Sort::Modified => hits.sort_by_cached_key(|key| Reverse(self.document(key).and_then(|document| document.modified))),
+            Sort::Score => hits.sort_by_cached_key(|key| Reverse(self.score(key, query)))
+        }
+    }
+
+    fn query_selected<F: Fn(&str) -> bool>(&self, query: &Query, selected: F) -> Option<Vec<&String>> {
+        let mut hits = Vec::new();
+        for (key, document) in self.documents.iter().filter(|(key, _)| selected(key)) {
+            match self.matches_query(key, query) {
+                Ok(true) => hits.push(document.preferred.as_ref().unwrap_or(key)),
+                Ok(false) => (),
+                Err(error) => panic!("Error while searching for {} : {}", query, error)
+            }
+        }
+        if hits.is_empty() {
+            None
+        } else {
+            Some(hits)
+        }
+    }
+
+    /// Search keywords in every files, returning owned keys.
+    ///
+    /// Same as [`FsIndex::search`], but the results do not borrow the index: they can be kept while the index is updated, or sent to another thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an error occurs during the search.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest("/foo/bar");
+    /// let results = fs_index.search_owned("word1 word2");
+    /// fs_index.ingest("/foo/baz");
+    /// # }
+    /// ```
+    pub fn search_owned(&self, keywords: &str) -> Option<Vec<String>> {
+        self.search(keywords).map(|keys| keys.into_iter().cloned().collect())
+    }
+
+    /// Search keywords in every files, returning the paths of the matching files.
+    ///
+    /// Each path appears once, even when several documents of the file match (e.g. cells of a notebook). Documents without a source file are left out.
+    /// Paths are returned as ingested, relative paths are not resolved.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an error occurs during the search.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest("/foo/bar");
+    /// for path in fs_index.search_paths("word1 word2") {
+    ///     let content = std::fs::read_to_string(path);
+    /// }
+    /// # }
+    /// ```
+    pub fn search_paths(&self, keywords: &str) -> Vec<PathBuf> {
+        let mut seen = BTreeSet::new();
+        self.search_iter(keywords)
+            .filter_map(|key| self.document(key).and_then(|document| document.path.as_ref()))
+            .filter(|path| seen.insert(*path))
+            .cloned()
+            .collect()
+    }
+
+    /// Search keywords in every files, lazily.
+    ///
+    /// Return an iterator over the same keys as [`FsIndex::search`], in ascending order. Documents are evaluated as the iterator advances,
+    /// so stopping after the first matches saves the evaluation of the remaining documents.
+    ///
+    /// # Panics
+    ///
+    /// The iterator panics if an error occurs while evaluating a document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest("/foo/bar");
+    /// let first_results: Vec<&String> = fs_index.search_iter("word1 word2").take(10).collect();
+    /// # }
+    /// ```
+    pub fn search_iter<'a>(&'a self, keywords: &'a str) -> impl Iterator<Item = &'a String> + 'a {
+        let tokens = self.keyword_tokens(keywords);
+        self.documents.iter()
+            .filter(move |(key, _)| match self.matches(key, &tokens) {
+                Ok(matches) => matches,
+                Err(error) => panic!("Error while searching for {} : {}", keywords, error)
+            })
+            .map(|(key, document)| document.preferred.as_ref().unwrap_or(key))
+    }
+
+    /// Search keywords in every files, stopping at the first `limit` hits.
+    ///
+    /// Return the first keys of [`FsIndex::search`], the remaining documents are not evaluated (see [`FsIndex::search_iter`]).
+    /// Return `None` if nothing match.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an error occurs during the search.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest_recursive("/foo");
+    /// let examples = fs_index.search_limited("word1 word2", 5);
+    /// # }
+    /// ```
+    pub fn search_limited(&self, keywords: &str, limit: usize) -> Option<Vec<&String>> {
+        let hits: Vec<&String> = self.search_iter(keywords).take(limit).collect();
+        if hits.is_empty() {
+            None
+        } else {
+            Some(hits)
+        }
+    }
+
+    /// Search keywords in every files, returning the page of at most `limit` hits after the first `offset` ones.
+    ///
+    /// Hits are in ascending order of their keys, so the pages do not overlap as long as the index is not updated between them.
+    /// Only the documents up to the end of the page are evaluated. Return an empty page past the last hit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an error occurs during the search.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest_recursive("/foo");
+    /// let second_page = fs_index.search_page("word1 word2", 20, 20);
+    /// # }
+    /// ```
+    pub fn search_page(&self, keywords: &str, offset: usize, limit: usize) -> Vec<&String> {
+        self.search_iter(keywords).skip(offset).take(limit).collect()
+    }
+
+    /// Dump a `FsIndex` in a file.
+    ///
+    /// Create a Json representation of the current `FsIndex` and write it at the location designated by `path`.
+    /// Documents are written in ascending order of their keys, so an index with the same content gives a byte-identical dump, whatever the order of ingestion.
+    ///
+    /// # Panics
+    ///
+    /// Panics if it is not possible to create the file at `path` or if it is impossible to serialize the `FsIndex`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest("/foo/bar.txt");
+    /// fs_index.dump("/foo/dump.json");
+    /// # }
+    /// ```
+    pub fn dump(&self, path: &str) {
+        let serialized = serde_json::to_string(&self.dump_content()).expect("Impossible to serialize file");
+        Self::write_dump(path, serialized);
+    }
+
+    /// Dump a `FsIndex` in a file, indented with one line per document and per filter.
+    ///
+    /// The content is the same as [`FsIndex::dump`], and is restored the same way. A dump kept in version control then gives diffs showing the changed documents.
+    ///
+    /// # Panics
+    ///
+    /// Panics if it is not possible to create the file at `path` or if it is impossible to serialize the `FsIndex`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest("/foo/bar.txt");
+    /// fs_index.dump_pretty("/foo/dump.json");
+    /// # }
+    /// ```
+    pub fn dump_pretty(&self, path: &str) {
+        let serialized = to_string_pretty(&self.dump_content()).expect("Impossible to serialize file");
+        Self::write_dump(path, serialized);
+    }
+
+    fn dump_content(&self) -> Dump<'_> {
+        Dump {
+            version: DUMP_VERSION,
+            error_rate: self.error_rate,
+            backend: self.backend,
+            hash_function: self.hash_function,
+            analyzer: &self.analyzer,
+            bloom_filters: Filters {
+                store: &self.store,
+                documents: &self.documents
+            },
+            documents: &self.documents
+        }
+    }
+
+    fn write_dump(path: &str, serialized: String) {
+        #[cfg(feature = "object-store")]
+        if remote::is_remote(path) {
+            Remote::open(path).and_then(|remote| remote.write(format!("{}\n", serialized).into_bytes()))
+                .unwrap_or_else(|error| panic!("Impossible to write dump file {} : {}", path, error));
+            return;
+        }
+        write_locked(Path::new(path), |output_file| write!(output_file, "{}\n", serialized))
+            .unwrap_or_else(|error| panic!("Impossible to write dump file {} : {}", path, error));
+    }
+
+    /// Return the error rate of the index, the target probability of false positive of every filter.
+    pub fn error_rate(&self) -> f32 {
+        self.error_rate
+    }
+
+    /// Return the estimated probability of false positive of the document `key` (or alias), or `None` if it is not in the index.
+    ///
+    /// See [`Filter::false_positive_rate`]. A document whose estimate exceeds [`FsIndex::error_rate`] has a saturated filter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the filter cannot be read from the index store.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let fs_index = FsIndex::restore("/foo/dump.json");
+    /// for key in fs_index.search("content").unwrap_or_default() {
+    ///     if fs_index.false_positive_rate(key).unwrap_or(0.0) > fs_index.error_rate() as f64 {
+    ///         println!("{} may be a false positive", key);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn false_positive_rate(&self, key: &str) -> Option<f64> {
+        let key = self.resolve(key)?;
+        match self.store.get(key) {
+            Ok(filter) => filter.map(|filter| filter.false_positive_rate()),
+            Err(error) => panic!("Unable to read filter of {} : {}", key, error)
+        }
+    }
+
+    /// Return an estimate of the memory used by the index, in bytes.
+    ///
+    /// Counts the document metadata and the filters held in memory by the store, see [`IndexStore::memory_usage`].
+    pub fn memory_usage(&self) -> usize {
+        let documents: usize = self.documents.iter()
+            .map(|(key, document)| {
+                let path = document.path.as_ref().map(|path| path.as_os_str().len()).unwrap_or(0);
+                let hash = document.hash.as_ref().map(|hash| hash.len()).unwrap_or(0);
+                key.len() + path + hash + ENTRY_OVERHEAD
+            })
+            .sum();
+        documents + self.store.memory_usage()
+    }
+
+    /// Return an immutable view of the index in its current state, for searches only.
+    ///
+    /// A snapshot holds the keys of the documents and their filters, without metadata, so it is cheaper than a [`Clone`] of the index and is available with any store.
+    /// It is not affected by later changes to the index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a filter cannot be read from the store.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest("/foo/bar");
+    /// let snapshot = fs_index.snapshot();
+    /// fs_index.ingest("/foo/baz");
+    /// let results = snapshot.search("word1 word2");
+    /// # }
+    /// ```
+    pub fn snapshot(&self) -> Snapshot {
+        let filters = self.documents.iter()
+            .filter_map(|(key, document)| match self.store.get(key) {
+                Ok(filter) => filter.map(|filter| (document.preferred.as_ref().unwrap_or(key).clone(), filter.into_owned())),
+                Err(error) => panic!("Unable to read filter of {} : {}", key, error)
+            })
+            .collect();
+        Snapshot::new(filters, self.analyzer.clone())
+    }
+
+    /// Append a JSONL audit record to the file at `path` for every file processed by the next ingestions.
+    ///
+    /// Each record holds the timestamp in seconds since the Unix epoch, the file path, its size in bytes,
+    /// the outcome (`ingested`, `unchanged`, `skipped` or `failed`) and the reason of a skip or failure.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the audit log cannot be opened, or later written.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.set_audit_log("/foo/audit.jsonl");
+    /// fs_index.ingest("/foo/bar");
+    /// # }
+    /// ```
+    pub fn set_audit_log(&mut self, path: &str) {
+        self.audit = Some(AuditLog::open(path));
+    }
+
+    /// Abort the ingestion of a directory on the first file which cannot be read.
+    ///
+    /// By default these files are listed in the [`IngestReport`] and the ingestion goes on.
+    /// Files which are not UTF-8 text are skipped in both cases.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.set_fail_fast(true);
+    /// fs_index.ingest("/foo/bar");
+    /// # }
+    /// ```
+    pub fn set_fail_fast(&mut self, fail_fast: bool) {
+        self.fail_fast = fail_fast;
+    }
+
+    /// Stop the ingestions when `cancel` is set, e.g. by another thread.
+    ///
+    /// The file being ingested is completed, so the index stays consistent, and the ingestion returns a report of the files processed so far, flagged as cancelled.
+    /// The flag is checked before each file by [`FsIndex::ingest`], [`FsIndex::ingest_recursive`], [`FsIndex::ingest_many`], [`FsIndex::ingest_files`] and [`FsIndex::ingest_from`].
+    /// Clear the flag to ingest again.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use std::sync::atomic::{AtomicBool, Ordering};
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let cancel = Arc::new(AtomicBool::new(false));
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.set_cancel_flag(cancel.clone());
+    /// // From another thread, e.g. when the user presses a Cancel button
+    /// cancel.store(true, Ordering::Relaxed);
+    /// let report = fs_index.ingest_recursive("/foo");
+    /// assert!(report.cancelled);
+    /// # }
+    /// ```
+    pub fn set_cancel_flag(&mut self, cancel: Arc<AtomicBool>) {
+        self.cancel = Some(cancel);
+    }
+
+    /// Notify `observer` of the progress of the ingestions, file by file.
+    ///
+    /// The observer follows [`FsIndex::ingest`], [`FsIndex::ingest_recursive`], [`FsIndex::ingest_many`], [`FsIndex::ingest_files`] and [`FsIndex::ingest_from`],
+    /// and is shared by the copies of the index. See [`ProgressObserver`].
+    pub fn set_progress_observer<O: ProgressObserver + 'static>(&mut self, observer: O) {
+        self.progress = Some(Arc::new(observer));
+    }
+
+    /// Call `hook` after each document is processed by an ingestion, with its key, its number of tokens and the outcome.
+    ///
+    /// Use it to keep side tables, e.g. a database of metadata, in lockstep with the index.
+    /// A file split into several documents calls `hook` for each of them. Files left unchanged, skipped or failed have no new tokens, their number of tokens is 0,
+    /// and skipped or failed files are given the key they would have. Documents ingested from readers, tokens or maps call it too, and appended documents with the number of tokens appended.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::{FsIndex, Outcome};
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.set_ingest_hook(|key, tokens, outcome| {
+    ///     if outcome == Outcome::Ingested {
+    ///         println!("{} : {} tokens", key, tokens);
+    ///     }
+    /// });
+    /// fs_index.ingest("/foo/bar");
+    /// # }
+    /// ```
+    pub fn set_ingest_hook<F: Fn(&str, usize, Outcome) + Send + Sync + 'static>(&mut self, hook: F) {
+        self.ingest_hook = Some(Arc::new(hook));
+    }
+
+    /// Index the content of identical files once.
+    ///
+    /// A file with the same content as a file already indexed is not indexed again: its key becomes an alias of the documents of the first file, as for hard links, and it is listed as skipped in the report.
+    /// This saves the memory of vendored or copied trees. Searches return the first file only.
+    /// A copy stays an alias when it is ingested again, remove its alias with [`FsIndex::remove_alias`] to index a copy which diverged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::{FsIndex, MemoryFileSystem};
+    /// let files = MemoryFileSystem::default();
+    /// files.insert("/foo/a/lib.txt", "word1 word2");
+    /// files.insert("/foo/b/lib.txt", "word1 word2");
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.set_file_system(files);
+    /// fs_index.set_dedup(true);
+    /// fs_index.ingest_recursive("/foo");
+    /// assert_eq!(1, fs_index.keys().count());
+    /// assert_eq!(Some("/foo/a/lib.txt"), fs_index.resolve("/foo/b/lib.txt"));
+    /// ```
+    pub fn set_dedup(&mut self, dedup: bool) {
+        self.dedup = dedup;
+    }
+
+    /// Read again files failing with a transient error according to `policy`.
+    ///
+    /// By default, a file is not read again.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry = policy;
+    }
+
+    /// Fetch remote sources according to `politeness`, see [`Politeness`].
+    ///
+    /// By default, 4 requests at a time and 5 per second, respecting robots.txt.
+    pub fn set_politeness(&mut self, politeness: Politeness) {
+        self.politeness = politeness;
+    }
+
+    /// Return the policy of the remote sources, see [`FsIndex::set_politeness`].
+    pub fn politeness(&self) -> Politeness {
+        self.politeness
+    }
+
+    /// Split the text of files larger than `size` bytes into chunks of about `size` bytes, each one a document.
+    ///
+    /// The chunks are keyed `path#chunkN`, so that a search points at a region of a large file.
+    /// By default, a file is a single document whatever its size.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn index_logs()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.set_chunk_size(64 * 1024 * 1024);
+    /// fs_index.ingest("/var/log/huge.log");
+    /// # }
+    /// ```
+    pub fn set_chunk_size(&mut self, size: usize) {
+        self.split.chunk_size = Some(size);
+    }
+
+    /// Index each paragraph of the files, delimited by blank lines, as a document keyed `path¶n`.
+    ///
+    /// Searching prose then returns the paragraphs containing the keywords rather than whole files.
+    /// Notebooks are still split into cells, and the chunk size is ignored in this mode.
+    pub fn set_paragraphs(&mut self, paragraphs: bool) {
+        self.split.paragraphs = paragraphs;
+    }
+
+    /// Index each group of `cues` consecutive cues of the subtitle files as a document keyed `path#cuesN`.
+    ///
+    /// Subtitles (`.srt` and `.vtt`) are always indexed without their cue numbers, timings and formatting tags.
+    /// By default, all the dialogue of a subtitle file is a single document.
+    pub fn set_subtitle_cues(&mut self, cues: usize) {
+        self.split.cues = Some(cues);
+    }
+
+    /// Key the documents of each ingested file with `mapper`, instead of the file path.
+    ///
+    /// Keys can then be project relative paths, URLs or identifiers. Documents split from a file keep their suffix, e.g. `#cell1`.
+    /// The path of the file is still recorded in the document metadata, so that the index can be refreshed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.set_key_mapper(|path| format!("https://example.com/{}", path.strip_prefix("/foo").unwrap().display()));
+    /// fs_index.ingest("/foo/bar");
+    /// # }
+    /// ```
+    pub fn set_key_mapper<F: Fn(&Path) -> String + Send + Sync + 'static>(&mut self, mapper: F) {
+        self.key_mapper = Some(Arc::new(mapper));
+    }
+
+    /// Prefix the keys of the documents ingested from now on with `prefix`, e.g. `projA:`.
+    ///
+    /// Indexes of several sources merged in one dump stay distinguishable, and can be restored or searched by prefix (see [`FsIndex::restore_filtered`]).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::restore("/foo/dump.json");
+    /// fs_index.set_key_prefix("projA:");
+    /// fs_index.ingest("/foo/projA");
+    /// fs_index.dump("/foo/dump.json");
+    /// # }
+    /// ```
+    pub fn set_key_prefix(&mut self, prefix: &str) {
+        self.key_prefix = prefix.to_string();
+    }
+
+    /// Tag the documents ingested from now on with `name` and `value`, e.g. `project=web`.
+    ///
+    /// Tags are kept in the dump and select the documents searched, see [`Document::has_tag`].
+    /// The tags of the front matter of a file (`name: value` lines between `---` lines at its start, e.g. in Markdown) are added to its documents, replacing these ones.
+    /// A document ingested again keeps its previous tags, unless they are given another value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.set_tag("project", "web");
+    /// fs_index.ingest_recursive("/foo/web");
+    /// let query = cli_bloom::Query::term("TODO");
+    /// let hits: Vec<&String> = fs_index.query_where(&query, |key| fs_index.document(key).unwrap().has_tag("project", "web")).collect();
+    /// # }
+    /// ```
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        self.tags.insert(name.to_string(), value.to_string());
+    }
+
+    /// Build the filters of the documents ingested from now on with `backend`, instead of bloom filters.
+    ///
+    /// Documents already in the index keep their filter until their file changes. The backend is kept in the dump.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `backend` is not available, see [`Backend::is_available`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::{Backend, FsIndex};
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.set_backend(Backend::Bloom);
+    /// fs_index.ingest("/foo/bar");
+    /// # }
+    /// ```
+    pub fn set_backend(&mut self, backend: Backend) {
+        if !backend.is_available() {
+            panic!("{}", Error::UnavailableBackend(backend));
+        }
+        self.backend = backend;
+    }
+
+    /// Analyze the text of the documents ingested from now on, and the keywords of every search, with `analyzer`.
+    ///
+    /// Set it before any ingestion: documents already in the index keep the tokens of the previous analyzer, which searches no longer look for.
+    /// The analyzer is kept in the dump, so a restored index searches with the analyzer it was built with.
+    pub fn set_analyzer(&mut self, analyzer: Analyzer) {
+        self.analyzer = analyzer;
+    }
+
+    /// Return the analyzer of the documents and searches, see [`FsIndex::set_analyzer`].
+    pub fn analyzer(&self) -> &Analyzer {
+        &self.analyzer
+    }
+
+    /// Return the backend of the filters of the documents ingested from now on.
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Hash the tokens of the documents ingested from now on with `hash_function`, e.g. a keyed SipHash when the documents come from untrusted sources.
+    ///
+    /// Every backend uses it. The hash function is kept in the dump, with the key of SipHash, and each filter records its own.
+    pub fn set_hash_function(&mut self, hash_function: HashFunction) {
+        self.hash_function = hash_function;
+    }
+
+    /// Read the ingested files, check their staleness and diagnose them in `file_system` instead of the real file system.
+    ///
+    /// The file system is not kept in the dump, a restored index reads the real file system.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::{FsIndex, MemoryFileSystem};
+    /// let files = MemoryFileSystem::default();
+    /// files.insert("/foo/bar.txt", "word1");
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.set_file_system(files.clone());
+    /// fs_index.ingest("/foo/bar.txt");
+    /// files.remove("/foo/bar.txt");
+    /// assert_eq!(vec!["/foo/bar.txt"], fs_index.prune());
+    /// ```
+    pub fn set_file_system<F: FileSystem + 'static>(&mut self, file_system: F) {
+        self.file_system = Arc::new(file_system);
+    }
+
+    /// Extract the text of the files accepted by `extractor` with it, see [`ContentExtractor`].
+    ///
+    /// The last registered extractor accepting a file takes precedence, so a built-in extractor can be replaced.
+    pub fn register_extractor<E: ContentExtractor + 'static>(&mut self, extractor: E) {
+        self.extractors.register(Arc::new(extractor));
+    }
+
+    pub(crate) fn extractors(&self) -> &Extractors {
+        &self.extractors
+    }
+
+    /// Ingest content already split into tokens.
+    ///
+    /// Insert a document named `key` made of `tokens`, for callers with their own analysis pipeline.
+    /// Tokens are separated by whitespace before insertion and still go through the index normalization (lowercase, punctuation removal), which keeps them consistent with searched keywords.
+    /// The document has no source file, so it is never pruned nor refreshed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tokens cannot be inserted in the index.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest_tokens("doc-1", vec!["stemmed", "token"].into_iter());
+    /// ```
+    pub fn ingest_tokens<'a>(&mut self, key: &str, tokens: impl Iterator<Item = &'a str>) {
+        let content = tokens.collect::<Vec<&str>>().join(" ");
+        let analyzed = self.analyzer.analyze(&content);
+        match Filter::with_backend(self.backend, self.hash_function, self.error_rate, &analyzed) {
+            Ok(filter) => self.store.insert(key, filter).expect("Unable to insert filter in index store"),
+            Err(error) => panic!("{}", error)
+        }
+        let mut document = self.tagged(Document::from_content(content.as_bytes()));
+        document.count_tokens(&analyzed);
+        let tokens = document.tokens.unwrap_or_default();
+        self.insert_document(key.to_string(), document);
+        self.notify_hook(key, tokens, Outcome::Ingested);
+    }
+
+    /// Ingest the text read from `reader` as the document `key`, replacing any document with the same key.
+    ///
+    /// With the `compressed` feature (enabled by default), gzip, zstd and xz content is decompressed, recognized from its first bytes, e.g. for a compressed standard input.
+    /// The document has no source file, so it is never pruned nor refreshed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the content cannot be read, is not UTF-8 text or cannot be inserted in the index.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest_reader("stdin", std::io::stdin().lock());
+    /// # }
+    /// ```
+    pub fn ingest_reader<R: Read>(&mut self, key: &str, reader: R) {
+        #[cfg(feature = "compressed")]
+        let reader = decompressing(reader).unwrap_or_else(|error| panic!("Unable to read content of {} : {}", key, error));
+        let mut reader = reader;
+        let mut content = String::new();
+        reader.read_to_string(&mut content).unwrap_or_else(|error| panic!("Unable to read content of {} : {}", key, error));
+        self.insert_text(key, &content);
+    }
+
+    // Insert the document `key` without source file, replacing any document with the same key
+    pub(crate) fn insert_text(&mut self, key: &str, text: &str) {
+        let analyzed = self.analyzer.analyze(text);
+        match Filter::with_backend(self.backend, self.hash_function, self.error_rate, &analyzed) {
+            Ok(filter) => self.store.insert(key, filter).expect("Unable to insert filter in index store"),
+            Err(error) => panic!("{}", error)
+        }
+        let mut document = self.tagged(Document::from_content(text.as_bytes()));
+        document.count_tokens(&analyzed);
+        let tokens = document.tokens.unwrap_or_default();
+        self.insert_document(key.to_string(), document);
+        self.notify_hook(key, tokens, Outcome::Ingested);
+    }
+
+    /// Ingest many documents given as `(key, content)` pairs, replacing the documents with the same keys.
+    ///
+    /// The filters are analyzed and built on several threads, then inserted in the store at once, which is much faster than ingesting the documents one by one for programmatic bulk loads.
+    /// The documents have no source file, so they are never pruned nor refreshed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a content cannot be inserted in the index.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest_map((0..1000).map(|id| (format!("doc-{}", id), format!("content of document {}", id))));
+    /// assert_eq!(vec!["doc-42"], fs_index.search("42").unwrap());
+    /// ```
+    pub fn ingest_map<I: IntoIterator<Item = (String, String)>>(&mut self, documents: I) {
+        let documents: Vec<(String, String)> = documents.into_iter().collect();
+        let threads = thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1);
+        let chunk_size = ((documents.len() + threads - 1) / threads).max(1);
+        let (backend, hash_function, error_rate, analyzer) = (self.backend, self.hash_function, self.error_rate, &self.analyzer);
+        let built: Vec<Result<Vec<(Filter, Document)>, String>> = thread::scope(|scope| {
+            let handles: Vec<_> = documents.chunks(chunk_size)
+                .map(|chunk| scope.spawn(move || chunk.iter()
+                    .map(|(_, content)| {
+                        let analyzed = analyzer.analyze(content);
+                        let mut document = Document::from_content(content.as_bytes());
+                        document.count_tokens(&analyzed);
+                        Filter::with_backend(backend, hash_function, error_rate, &analyzed).map(|filter| (filter, document)).map_err(|error| error.to_string())
+                    })
+                    .collect()))
+                .collect();
+            handles.into_iter().map(|handle| handle.join().expect("Ingest thread panicked")).collect()
+        });
+        let mut filters = Vec::with_capacity(documents.len());
+        for chunk in built {
+            filters.extend(chunk.unwrap_or_else(|error| panic!("{}", error)));
+        }
+        for ((key, _), (filter, document)) in documents.into_iter().zip(filters) {
+            self.store.insert(&key, filter).expect("Unable to insert filter in index store");
+            let document = self.tagged(document);
+            let tokens = document.tokens.unwrap_or_default();
+            self.insert_document(key.clone(), document);
+            self.notify_hook(&key, tokens, Outcome::Ingested);
+        }
+    }
+
+    /// Append `content` to the document `key` (or alias), which is created if it is not in the index.
+    ///
+    /// The filter of the document is not built again: the tokens of `content` go in a new filter stacked on the previous ones and sized for them.
+    /// Each new filter has half the error rate of the previous one, so the probability of false positive of the document stays below twice the error rate however much it grows, e.g. for live logs.
+    /// The metadata of an existing document is unchanged but its token counts, so a document ingested from a file is still refreshed from it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the content cannot be inserted in the index.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.append("app.log", "service started");
+    /// fs_index.append("app.log", "request failed");
+    /// assert!(fs_index.search("started failed").is_some());
+    /// ```
+    pub fn append(&mut self, key: &str, content: &str) {
+        let key = self.resolve(key).unwrap_or(key).to_string();
+        let analyzed = self.analyzer.analyze(content);
+        let filter = match self.store.get(&key).expect("Unable to read filter from index store") {
+            Some(filter) => filter.append(self.hash_function, self.error_rate, &analyzed),
+            None => Filter::with_backend(self.backend, self.hash_function, self.error_rate, &analyzed)
+        };
+        match filter {
+            Ok(filter) => self.store.insert(&key, filter).expect("Unable to insert filter in index store"),
+            Err(error) => panic!("{}", error)
+        }
+        match self.documents.get_mut(&key) {
+            Some(document) => document.add_tokens(&analyzed),
+            None => {
+                let mut document = self.tagged(Document::from_content(content.as_bytes()));
+                document.count_tokens(&analyzed);
+                self.insert_document(key.clone(), document);
+            }
+        }
+        self.notify_hook(&key, membership::tokens(&analyzed).count(), Outcome::Ingested);
+    }
+
+    /// Dump a `FsIndex` in a file, serializing the filters on several threads.
+    ///
+    /// The dump is made of length-prefixed sections of a fixed number of documents, which are also deserialized in parallel by [`FsIndex::restore`].
+    /// Prefer this format for indexes with many documents, where serialization is the bottleneck.
+    /// As with [`FsIndex::dump`], dumping the same index twice gives identical files, whatever the number of threads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if it is not possible to create the file at `path` or if it is impossible to serialize the `FsIndex`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest("/foo/bar");
+    /// fs_index.dump_sections("/foo/dump.sections");
+    /// # }
+    /// ```
+    pub fn dump_sections(&self, path: &str) where S: Sync {
+        let header = SectionsHeader {
+            version: DUMP_VERSION,
+            error_rate: self.error_rate,
+            backend: self.backend,
+            hash_function: self.hash_function,
+            analyzer: &self.analyzer,
+            documents: &self.documents
+        };
+        let mut sections = vec![serde_json::to_string(&header).expect("Impossible to serialize file")];
+        let keys: Vec<&String> = self.documents.keys().collect();
+        let chunks: Vec<&[&String]> = keys.chunks(SECTION_DOCUMENTS).collect();
+        let threads = thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1);
+        let chunks_per_thread = ((chunks.len() + threads - 1) / threads).max(1);
+        thread::scope(|scope| {
+            let handles: Vec<_> = chunks.chunks(chunks_per_thread)
+                .map(|chunks| scope.spawn(move || chunks.iter().map(|chunk| self.serialize_section(chunk)).collect::<Vec<String>>()))
+                .collect();
+            for handle in handles {
+                sections.extend(handle.join().expect("Dump thread panicked"));
+            }
+        });
+        #[cfg(feature = "object-store")]
+        if remote::is_remote(path) {
+            let mut content = Vec::new();
+            write_sections(&mut content, &sections).expect("Impossible to serialize file");
+            Remote::open(path).and_then(|remote| remote.write(content))
+                .unwrap_or_else(|error| panic!("Impossible to write dump file {} : {}", path, error));
+            return;
+        }
+        write_locked(Path::new(&path), |output_file| write_sections(output_file, &sections))
+            .unwrap_or_else(|error| panic!("Impossible to write dump file {} : {}", path, error));
+    }
+
+    /// Return the keys of all documents.
+    ///
+    /// Keys are returned in ascending lexicographic order (byte-wise comparison of the UTF-8 strings), whatever the order of ingestion.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let fs_index = FsIndex::restore("/foo/dump.json");
+    /// for key in fs_index.keys().filter(|key| key.ends_with(".md")) {
+    ///     println!("{}", key);
+    /// }
+    /// # }
+    /// ```
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.documents.keys().map(|key| key.as_str())
+    }
+
+    /// Write the key of every document, one per line.
+    ///
+    /// Keys are written in ascending lexicographic order, so two exports of the same index are identical.
+    ///
+    /// # Errors
+    ///
+    /// Return an error if writing to `writer` fails.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let fs_index = FsIndex::restore("/foo/dump.json");
+    /// fs_index.export_keys(std::io::stdout()).unwrap();
+    /// # }
+    /// ```
+    pub fn export_keys<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for key in self.keys() {
+            writeln!(writer, "{}", key)?;
+        }
+        writer.flush()
+    }
+
+    /// Remove documents whose source file no longer exists.
+    ///
+    /// Return the keys of the removed documents.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::restore("/foo/dump.json");
+    /// for key in fs_index.prune() {
+    ///     println!("Removed {}", key);
+    /// }
+    /// fs_index.dump("/foo/dump.json");
+    /// # }
+    /// ```
+    pub fn prune(&mut self) -> Vec<String> {
+        let missing: Vec<String> = self.documents.iter()
+            .filter(|(_, document)| document.staleness(self.file_system.as_ref()) == Staleness::Missing)
+            .map(|(key, _)| key.clone())
+            .collect();
+        if !missing.is_empty() {
+            self.remove_filters(&missing);
+        }
+        missing
+    }
+
+    /// Bring the index up to date with the file system.
+    ///
+    /// Remove documents whose source file no longer exists (see [`FsIndex::prune`]) and ingest again only the documents whose source changed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a changed source cannot be read.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::restore("/foo/dump.json");
+    /// let refresh = fs_index.refresh();
+    /// println!("{} removed, {} updated", refresh.pruned.len(), refresh.updated.len());
+    /// fs_index.dump("/foo/dump.json");
+    /// # }
+    /// ```
+    pub fn refresh(&mut self) -> Refresh {
+        let pruned = self.prune();
+        let updated: Vec<String> = self.documents.iter()
+            .filter(|(_, document)| document.staleness(self.file_system.as_ref()) == Staleness::Modified)
+            .map(|(key, _)| key.clone())
+            .collect();
+        let paths: BTreeSet<PathBuf> = updated.iter()
+            .filter_map(|key| self.documents[key].path.clone())
+            .collect();
+        for path in paths {
+            if let Err(error) = self.ingest_file(&path, false, &mut IngestReport::default()) {
+                panic!("{}", error);
+            }
+        }
+        Refresh {
+            pruned,
+            updated
+        }
+    }
+
+    /// Rebuild the filters sized for many more tokens than their document has, or for fewer, to the size of the error rate of the index.
+    ///
+    /// A bloom filter is oversized when it has more than twice the bits needed by the distinct tokens of its document, counted at ingestion or else estimated from its fill ratio,
+    /// e.g. after a migration from a lower error rate. A filter is saturated when its false positive rate exceeds the error rate of the index (see [`FsIndex::false_positive_rate`]).
+    /// Filters are rebuilt from the source files, read again: the filters of documents without source file, appended to, whose file changed or which are one of several documents of their file are skipped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a rebuilt filter cannot be inserted in the index store.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::restore("/foo/dump.json");
+    /// let compaction = fs_index.compact();
+    /// println!("{} filters rebuilt, {} bits reclaimed", compaction.rebuilt.len(), compaction.bits_before.saturating_sub(compaction.bits_after));
+    /// fs_index.dump("/foo/dump.json");
+    /// # }
+    /// ```
+    pub fn compact(&mut self) -> Compaction {
+        let mut compaction = Compaction::default();
+        let keys: Vec<String> = self.documents.keys().cloned().collect();
+        for key in keys {
+            let filter = match self.store.get(&key) {
+                Ok(Some(filter)) if self.needs_compaction(&key, &filter) => filter.into_owned(),
+                _ => continue
+            };
+            match self.rebuild(&key, &filter) {
+                Some(rebuilt) => {
+                    compaction.bits_before += filter.bits();
+                    compaction.bits_after += rebuilt.bits();
+                    compaction.rebuilt.push(key);
+                },
+                None => compaction.skipped.push(key)
+            }
+        }
+        compaction
+    }
+
+    fn needs_compaction(&self, key: &str, filter: &Filter) -> bool {
+        if filter.false_positive_rate() > self.error_rate as f64 {
+            return true;
+        }
+        if filter.backend() != Backend::Bloom || filter.is_stacked() {
+            return false;
+        }
+        let unique_tokens = match self.documents[key].unique_tokens {
+            Some(unique_tokens) => unique_tokens as f64,
+            // Estimate of the number of tokens of a bloom filter from its bits set
+            None => -(filter.bits() as f64 / filter.hash_count().max(1) as f64) * (1.0 - filter.fill_ratio()).ln()
+        };
+        filter.bits() as f64 > OVERSIZED_RATIO * (unique_tokens * bits_per_token(self.error_rate)).max(1.0)
+    }
+
+    // Build again the filter of the document `key` from its source file, unchanged since ingestion
+    fn rebuild(&mut self, key: &str, filter: &Filter) -> Option<Filter> {
+        let document = &self.documents[key];
+        let path = document.path.clone()?;
+        if filter.is_stacked() || self.keys_by_path.get(&path).map_or(true, |keys| keys.len() != 1) {
+            return None;
+        }
+        #[cfg(feature = "archive")]
+        {
+            if archive::is_archive(&path) {
+                return None;
+            }
+        }
+        let bytes = self.file_system.read(&path).ok()?;
+        if document.hash.as_ref() != Some(&content_hash(&bytes)) {
+            return None;
+        }
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+        let content = self.extractors.extract(None, extension, bytes).ok()?;
+        let mut parts = split_documents(key, &path, &content, &Split::default());
+        if parts.len() != 1 {
+            return None;
+        }
+        let (_, text) = parts.remove(0);
+        let analyzed = self.analyzer.analyze(&text);
+        let rebuilt = Filter::with_backend(filter.backend(), self.hash_function, self.error_rate, &analyzed).ok()?;
+        self.store.insert(key, rebuilt.clone()).expect("Unable to insert filter in index store");
+        if let Some(document) = self.documents.get_mut(key) {
+            document.count_tokens(&analyzed);
+        }
+        Some(rebuilt)
+    }
+
+    /// Move the documents of the file at `from` to the file at `to`, without reading it again.
+    ///
+    /// Use it when a file is renamed or moved: the filters are kept and the documents are keyed again, e.g. `from#cell1` becomes `to#cell1`.
+    /// Documents of `from` whose key does not start with its current key, e.g. ingested with another key prefix, are left unchanged.
+    /// Return the new keys, empty if nothing was ingested from `from`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a filter cannot be moved in the index store.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::restore("/foo/dump.json");
+    /// fs_index.rename("/foo/bar.txt", "/foo/baz.txt");
+    /// fs_index.dump("/foo/dump.json");
+    /// # }
+    /// ```
+    pub fn rename(&mut self, from: &str, to: &str) -> Vec<String> {
+        let keys: Vec<String> = self.keys_by_path.get(Path::new(from)).map(|keys| keys.iter().cloned().collect()).unwrap_or_default();
+        let (from_key, to_key) = match (self.key_of(Path::new(from)), self.key_of(Path::new(to))) {
+            (Some(from_key), Some(to_key)) => (from_key, to_key),
+            _ => return Vec::new()
+        };
+        let mut renamed = Vec::new();
+        for key in keys {
+            let new_key = match key.strip_prefix(from_key.as_str()) {
+                Some(suffix) => format!("{}{}", to_key, suffix),
+                None => continue
+            };
+            let filter = self.store.get(&key).expect("Unable to read filter from index store").map(|filter| filter.into_owned());
+            if let Some(filter) = filter {
+                self.store.insert(&new_key, filter).expect("Unable to insert filter in index store");
+            }
+            self.store.remove(&key).expect("Unable to remove filter from index store");
+            if let Some(mut document) = self.remove_document(&key) {
+                document.path = Some(PathBuf::from(to));
+                self.insert_document(new_key.clone(), document);
+            }
+            renamed.push(new_key);
+        }
+        renamed
+    }
+
+    /// Remove the documents of the file at `path`, without checking the file system.
+    ///
+    /// Use it when a file is deleted: its filters, tags and aliases are dropped, e.g. `path#cell1` and `path#cell2` for a file ingested in chunks.
+    /// Documents ingested from other files are left unchanged, see [`FsIndex::prune`] to remove all the documents whose file no longer exists.
+    /// Return the removed keys, empty if nothing was ingested from `path`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a filter cannot be removed from the index store.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::restore("/foo/dump.json");
+    /// fs_index.remove("/foo/bar.txt");
+    /// fs_index.dump("/foo/dump.json");
+    /// # }
+    /// ```
+    pub fn remove(&mut self, path: &str) -> Vec<String> {
+        let keys: Vec<String> = self.keys_by_path.get(Path::new(path)).map(|keys| keys.iter().cloned().collect()).unwrap_or_default();
+        self.remove_filters(&keys);
+        keys
+    }
+
+    /// Replace the leading `from` of the keys, paths and aliases of the documents by `to`.
+    ///
+    /// Use it after a restore when the indexed tree is now at another location, e.g. an index built in `/build/src` on a build server and searched in `/home/me/src`.
+    /// Only whole path components are replaced: `/build/src` maps `/build/src/main.rs` but not `/build/srcs/main.rs`.
+    /// The filters are kept, nothing is read again. Return the number of documents moved.
+    ///
+    /// # Errors
+    ///
+    /// Return a [`PrefixCollision`] listing the names taken twice if a moved key or alias would be the key or alias of another document.
+    /// The index is left unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a filter cannot be moved in the index store.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::restore("/foo/dump.json");
+    /// fs_index.map_prefix("/build/src", "/home/me/src").expect("Documents already in /home/me/src");
+    /// # }
+    /// ```
+    pub fn map_prefix(&mut self, from: &str, to: &str) -> Result<usize, PrefixCollision> {
+        let moved: Vec<String> = self.documents.iter()
+            .filter(|(key, document)| mapped_prefix(key, from, to).is_some()
+                || document.path.as_ref().and_then(|path| path.to_str()).and_then(|path| mapped_prefix(path, from, to)).is_some()
+                || document.aliases.iter().any(|alias| mapped_prefix(alias, from, to).is_some()))
+            .map(|(key, _)| key.clone())
+            .collect();
+        let moving: BTreeSet<&String> = moved.iter().collect();
+        let mut taken: BTreeSet<String> = self.documents.iter()
+            .filter(|(key, _)| !moving.contains(key))
+            .flat_map(|(key, document)| std::iter::once(key).chain(&document.aliases).cloned())
+            .collect();
+        let mut collisions = BTreeSet::new();
+        for key in &moved {
+            let names = std::iter::once(key).chain(&self.documents[key].aliases)
+                .map(|name| mapped_prefix(name, from, to).unwrap_or_else(|| name.clone()));
+            for name in names {
+                if !taken.insert(name.clone()) {
+                    collisions.insert(name);
+                }
+            }
+        }
+        if !collisions.is_empty() {
+            return Err(PrefixCollision { keys: collisions.into_iter().collect() });
+        }
+        for key in &moved {
+            let mut document = self.remove_document(key).unwrap();
+            let new_key = mapped_prefix(key, from, to).unwrap_or_else(|| key.clone());
+            if new_key != *key {
+                let filter = self.store.get(key).expect("Unable to read filter from index store").map(|filter| filter.into_owned());
+                if let Some(filter) = filter {
+                    self.store.insert(&new_key, filter).expect("Unable to insert filter in index store");
+                }
+                self.store.remove(key).expect("Unable to remove filter from index store");
+            }
+            document.path = document.path.map(|path| match path.to_str().and_then(|path| mapped_prefix(path, from, to)) {
+                Some(mapped) => PathBuf::from(mapped),
+                None => path
+            });
+            for alias in document.aliases.iter_mut() {
+                if let Some(mapped) = mapped_prefix(alias, from, to) {
+                    *alias = mapped;
+                }
+            }
+            document.preferred = document.preferred.map(|preferred| mapped_prefix(&preferred, from, to).unwrap_or(preferred));
+            self.insert_document(new_key, document);
+        }
+        Ok(moved.len())
+    }
+
+    /// Return the metadata of the document `key`, or `None` if it is not in the index.
+    ///
+    /// `key` can also be an alias of the document.
+    pub fn document(&self, key: &str) -> Option<&Document> {
+        self.resolve(key).and_then(|key| self.documents.get(key))
+    }
+
+    /// Return the key of the document whose key or alias is `name`, or `None` if it is not in the index.
+    pub fn resolve(&self, name: &str) -> Option<&str> {
+        match self.documents.get_key_value(name) {
+            Some((key, _)) => Some(key.as_str()),
+            None => self.aliases.get(name).map(|key| key.as_str())
+        }
+    }
+
+    /// Attach `alias` to the document `key`, as another name of the same content.
+    ///
+    /// The document keeps a single filter, and [`FsIndex::document`] finds it by any of its names.
+    /// Aliases are kept in the dump and when the document is ingested again.
+    /// Return `false` if `key` is not in the index or if `alias` already names another document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest("/foo/bar.txt");
+    /// fs_index.add_alias("/foo/bar.txt", "https://example.com/bar");
+    /// fs_index.prefer_alias("https://example.com/bar");
+    /// # }
+    /// ```
+    pub fn add_alias(&mut self, key: &str, alias: &str) -> bool {
+        if !self.documents.contains_key(key) || self.resolve(alias).map_or(false, |existing| existing != key) {
+            return false;
+        }
+        if alias != key {
+            let document = self.documents.get_mut(key).unwrap();
+            // Aliases are kept sorted, for reproducible dumps
+            if let Err(position) = document.aliases.binary_search_by(|existing| existing.as_str().cmp(alias)) {
+                document.aliases.insert(position, alias.to_string());
+            }
+            self.aliases.insert(alias.to_string(), key.to_string());
+        }
+        true
+    }
+
+    /// Detach `alias` from its document.
+    ///
+    /// Return `false` if `alias` is not an alias of a document.
+    pub fn remove_alias(&mut self, alias: &str) -> bool {
+        let key = match self.aliases.remove(alias) {
+            Some(key) => key,
+            None => return false
+        };
+        if let Some(document) = self.documents.get_mut(&key) {
+            document.aliases.retain(|existing| existing != alias);
+            if document.preferred.as_deref() == Some(alias) {
+                document.preferred = None;
+            }
+        }
+        true
+    }
+
+    /// Report the document named `alias` under this alias in search results, instead of its key.
+    ///
+    /// Preferring the key of the document reports it under its key again.
+    /// Return `false` if `alias` names no document.
+    pub fn prefer_alias(&mut self, alias: &str) -> bool {
+        let key = match self.resolve(alias) {
+            Some(key) => key.to_string(),
+            None => return false
+        };
+        let preferred = if alias == key { None } else { Some(alias.to_string()) };
+        self.documents.get_mut(&key).unwrap().preferred = preferred;
+        true
+    }
+
+    /// Compare an indexed document with its source on disk.
+    ///
+    /// The modification time is checked first, then the content hash when the times differ, so a file copied or checked out again with the same content stays fresh.
+    /// Return `None` if `key` is not in the index.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::{FsIndex, Staleness};
+    /// # fn search_index()  {
+    /// let fs_index = FsIndex::restore("/foo/dump.json");
+    /// if fs_index.staleness("/foo/bar.txt") == Some(Staleness::Modified) {
+    ///     println!("/foo/bar.txt must be ingested again");
+    /// }
+    /// # }
+    /// ```
+    pub fn staleness(&self, key: &str) -> Option<Staleness> {
+        self.documents.get(key).map(|document| document.staleness(self.file_system.as_ref()))
+    }
+
+    /// Summarize the documents of the index, their freshness and their tokens.
+    ///
+    /// Every document source is checked on disk (see [`FsIndex::staleness`]). Documents restored from dumps written before tokens were counted add no tokens.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let fs_index = FsIndex::restore("/foo/dump.json");
+    /// let stats = fs_index.stats();
+    /// println!("{} documents, {} modified", stats.documents, stats.modified);
+    /// # }
+    /// ```
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats::default();
+        for document in self.documents.values() {
+            stats.documents += 1;
+            match document.staleness(self.file_system.as_ref()) {
+                Staleness::Fresh => stats.fresh += 1,
+                Staleness::Modified => stats.modified += 1,
+                Staleness::Missing => stats.missing += 1,
+                Staleness::Unknown => stats.unknown += 1
+            }
+            stats.tokens += document.tokens.unwrap_or_default();
+            stats.unique_tokens += document.unique_tokens.unwrap_or_default();
+        }
+        stats
+    }
+
+    /// Compare the documents of the index with those of `other`, e.g. a later dump of the same files.
+    ///
+    /// Documents are compared by key and content hash, their filters are not read. Documents without hash, e.g. restored from old dumps, are never reported as modified.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndex;
+    /// # fn search_index()  {
+    /// let monday = FsIndex::restore("/foo/monday.json");
+    /// let tuesday = FsIndex::restore("/foo/tuesday.json");
+    /// println!("{} documents modified", monday.diff(&tuesday).modified.len());
+    /// # }
+    /// ```
+    pub fn diff<T: IndexStore>(&self, other: &FsIndex<T>) -> Changes {
+        let mut changes = Changes::default();
+        for (key, document) in &self.documents {
+            match other.documents.get(key) {
+                Some(other_document) => {
+                    if document.hash.is_some() && other_document.hash.is_some() && document.hash != other_document.hash {
+                        changes.modified.push(key.clone());
+                    }
+                },
+                None => changes.removed.push(key.clone())
+            }
+        }
+        changes.added = other.documents.keys().filter(|key| !self.documents.contains_key(*key)).cloned().collect();
+        changes
+    }
+
+    pub(crate) fn file_system(&self) -> &dyn FileSystem {
+        self.file_system.as_ref()
+    }
+
+    pub(crate) fn documents(&self) -> impl Iterator<Item = (&String, &Document)> {
+        self.documents.iter()
+    }
+
+    pub(crate) fn filter(&self, key: &str) -> Result<Option<Cow<'_, Filter>>, Error> {
+        Ok(self.store.get(key)?)
+    }
+
+    fn serialize_section(&self, keys: &[&String]) -> String {
+        let mut filters = BTreeMap::new();
+        for key in keys {
+            if let Some(filter) = self.store.get(key).expect("Unable to read filter from index store") {
+                filters.insert(key.as_str(), filter.to_raw());
+            }
+        }
+        serde_json::to_string(&filters).expect("Impossible to serialize file")
+    }
+
+    pub(crate) fn remove_filters(&mut self, keys: &[String]) {
+        for key in keys {
+            self.store.remove(key).expect("Unable to remove filter from index store");
+            self.remove_document(key);
+        }
+    }
+
+    fn insert_document(&mut self, key: String, mut document: Document) {
+        if let Some(path) = &document.path {
+            self.keys_by_path.entry(path.clone()).or_default().insert(key.clone());
+            if let Some(hash) = &document.hash {
+                self.paths_by_hash.insert(hash.clone(), path.clone());
+            }
+        }
+        // Aliases are kept when a document is ingested again
+        if let Some(previous) = self.documents.get(&key) {
+            for alias in &previous.aliases {
+                if !document.aliases.contains(alias) {
+                    document.aliases.push(alias.clone());
+                }
+            }
+            if document.preferred.is_none() {
+                document.preferred = previous.preferred.clone();
+            }
+            for (name, value) in &previous.tags {
+                document.tags.entry(name.clone()).or_insert_with(|| value.clone());
+            }
+            // A file found unchanged is not read again, its tokens are the same
+            if document.tokens.is_none() && document.hash == previous.hash {
+                document.tokens = previous.tokens;
+                document.unique_tokens = previous.unique_tokens;
+            }
+        }
+        document.aliases.sort();
+        for alias in &document.aliases {
+            self.aliases.insert(alias.clone(), key.clone());
+        }
+        if let Some(previous) = self.documents.insert(key.clone(), document) {
+            if previous.path != self.documents[&key].path {
+                self.unlink_path(&key, previous.path);
+            }
+        }
+    }
+
+    // `document` with the tags of the documents ingested from now on
+    fn tagged(&self, mut document: Document) -> Document {
+        document.tags.extend(self.tags.iter().map(|(name, value)| (name.clone(), value.clone())));
+        document
+    }
+
+    fn remove_document(&mut self, key: &str) -> Option<Document> {
+        let document = self.documents.remove(key)?;
+        self.unlink_path(key, document.path.clone());
+        if let (Some(hash), Some(path)) = (&document.hash, &document.path) {
+            if self.paths_by_hash.get(hash) == Some(path) && !self.keys_by_path.contains_key(path) {
+                self.paths_by_hash.remove(hash);
+            }
+        }
+        for alias in &document.aliases {
+            self.aliases.remove(alias);
+        }
+        Some(document)
+    }
+
+    // The path under which the file was already ingested, during this walk or as an alias of a document
+    fn linked_path(&self, path: &Path, metadata: &FileMetadata, visited: &mut HashMap<(u64, u64), PathBuf>) -> Option<PathBuf> {
+        if let Some(key) = self.key_of(path).and_then(|alias| self.aliases.get(&alias)) {
+            return self.documents[key].path.clone();
+        }
+        let id = metadata.id?;
+        match visited.get(&id) {
+            Some(first) => Some(first.clone()),
+            None => {
+                visited.insert(id, path.to_path_buf());
+                None
+            }
+        }
+    }
+
+    // Add the documents of the file at `path` as aliases of the documents of `linked`
+    fn alias_file(&mut self, linked: &Path, path: &Path) {
+        let (linked_key, alias) = match (self.key_of(linked), self.key_of(path)) {
+            (Some(linked_key), Some(alias)) => (linked_key, alias),
+            _ => return
+        };
+        let keys: Vec<String> = self.keys_by_path.get(linked).map(|keys| keys.iter().cloned().collect()).unwrap_or_default();
+        for key in keys {
+            // Documents split from the file keep their suffix, e.g. the cell of a notebook
+            let suffix = key.strip_prefix(linked_key.as_str()).unwrap_or("");
+            self.add_alias(&key, &format!("{}{}", alias, suffix));
+        }
+    }
+
+    // Key of the documents of the file at `path`, before any suffix
+    fn key_of(&self, path: &Path) -> Option<String> {
+        let key = match &self.key_mapper {
+            Some(mapper) => mapper(path),
+            None => path.to_str()?.to_string()
+        };
+        Some(format!("{}{}", self.key_prefix, key))
+    }
+
+    fn unlink_path(&mut self, key: &str, path: Option<PathBuf>) {
+        if let Some(path) = path {
+            if let Some(keys) = self.keys_by_path.get_mut(&path) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    self.keys_by_path.remove(&path);
+                }
+            }
+        }
+    }
+
+    fn matching<'a, I: Iterator<Item = (&'a String, &'a Document)>>(&'a self, keywords: &str, documents: I) -> Result<Option<Vec<&'a String>>, Error> {
+        let tokens = self.keyword_tokens(keywords);
+        let mut hits = Vec::new();
+        for (key, document) in documents {
+            if self.matches(key, &tokens)? {
+                hits.push(document.preferred.as_ref().unwrap_or(key));
+            }
+        }
+        if hits.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(hits))
+        }
+    }
+
+    // Analyzed and normalized once, then looked up in each filter
+    fn keyword_tokens(&self, keywords: &str) -> Vec<String> {
+        membership::tokens(&self.analyzer.analyze(keywords)).collect()
+    }
+
+    fn matches(&self, key: &str, tokens: &[String]) -> Result<bool, Error> {
+        match self.store.get(key)? {
+            Some(filter) => Ok(filter.contains_tokens(tokens)?),
+            None => Ok(false)
+        }
+    }
+
+    fn score(&self, name: &str, query: &Query) -> usize {
+        let key = match self.resolve(name) {
+            Some(key) => key,
+            None => return 0
+        };
+        let filter = self.store.get(key).expect("Unable to read filter from index store");
+        filter.map_or(0, |filter| query.score(&filter, &self.analyzer).unwrap_or_else(|error| panic!("Error while scoring {} : {}", name, error)))
+    }
+
+    fn matches_query(&self, key: &str, query: &Query) -> Result<bool, Error> {
+        match self.store.get(key)? {
+            Some(filter) => query.evaluate(&filter, &self.analyzer),
+            None => Ok(false)
+        }
+    }
+
+    // Errors on the entries of the directory are added to the report instead of aborting the ingestion, unless in fail fast mode
+    // Files met several times through hard links or bind mounts are ingested once, the other paths becoming aliases
+    fn index_directory(&mut self, path: PathBuf, recursive: bool, report: &mut IngestReport, visited: &mut HashMap<(u64, u64), PathBuf>) -> Result<(), Error> {
+        for entry_path in self.file_system.read_dir(&path)? {
+            if self.cancelled(report) {
+                return Ok(());
+            }
+            let metadata = match self.file_system.metadata(&entry_path) {
+                Ok(metadata) => metadata,
+                Err(error) => {
+                    self.fail(report, &entry_path, error.into())?;
+                    continue;
+                }
+            };
+            if metadata.is_file {
+                if let Some(linked) = self.linked_path(&entry_path, &metadata, visited) {
+                    self.alias_file(&linked, &entry_path);
+                    self.observe(|observer| observer.on_skip(&entry_path, &format!("same file as {}", linked.display())));
+                    report.skipped.push(Issue { reason: format!("same file as {}", linked.display()), path: entry_path });
+                    continue;
+                }
+                // Failures are already in the report
+                if let Err(error) = self.ingest_file(&entry_path, true, report) {
+                    if self.fail_fast {
+                        return Err(error);
+                    }
+                }
+            } else if recursive && metadata.is_dir {
+                if let Err(error) = self.index_directory(entry_path.clone(), recursive, report, visited) {
+                    self.fail(report, &entry_path, error)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Whether the ingestion was cancelled, recorded in the report
+    pub(crate) fn cancelled(&self, report: &mut IngestReport) -> bool {
+        let cancelled = self.cancel.as_ref().map_or(false, |cancel| cancel.load(Ordering::Relaxed));
+        report.cancelled |= cancelled;
+        cancelled
+    }
+
+    // Add a failure to the report, or return it in fail fast mode
+    fn fail(&self, report: &mut IngestReport, path: &Path, error: Error) -> Result<(), Error> {
+        if self.fail_fast {
+            return Err(error);
+        }
+        self.observe(|observer| observer.on_error(path, &error.to_string()));
+        report.failed.push(Issue { path: path.to_path_buf(), reason: error.to_string() });
+        Ok(())
+    }
+
+    // Notify the progress observer, if any
+    pub(crate) fn observe<F: FnOnce(&dyn ProgressObserver)>(&self, notify: F) {
+        if let Some(observer) = &self.progress {
+            notify(observer.as_ref());
+        }
+    }
+
+    // Ingest a file and record the outcome, skipping files which are not UTF-8 text if `lenient`
+    fn ingest_file(&mut self, path: &Path, lenient: bool, report: &mut IngestReport) -> Result<(), Error> {
+        self.observe(|observer| observer.on_file_start(path));
+        match self.index_file(path) {
+            Ok((Outcome::Skipped, _, Some(original))) => {
+                let reason = format!("same content as {}", original.display());
+                self.observe(|observer| observer.on_skip(path, &reason));
+                self.notify_file_hook(path, Outcome::Skipped);
+                self.audit(path, Outcome::Skipped, Some(&reason));
+                report.skipped.push(Issue { path: path.to_path_buf(), reason });
+                Ok(())
+            },
+            Ok((outcome, bytes, _)) => {
+                match outcome {
+                    Outcome::Unchanged => report.unchanged += 1,
+                    _ => report.indexed += 1
+                }
+                report.bytes += bytes;
+                self.observe(|observer| observer.on_file_done(path, outcome, bytes));
+                self.audit(path, outcome, None);
+                Ok(())
+            },
+            Err(error) => {
+                let issue = Issue {
+                    path: path.to_path_buf(),
+                    reason: error.to_string()
+                };
+                match error {
+                    Error::IndexInvalidData(_) if lenient => {
+                        self.observe(|observer| observer.on_skip(path, &issue.reason));
+                        self.notify_file_hook(path, Outcome::Skipped);
+                        self.audit(path, Outcome::Skipped, Some(&issue.reason));
+                        report.skipped.push(issue);
+                        Ok(())
+                    },
+                    _ => {
+                        self.observe(|observer| observer.on_error(path, &issue.reason));
+                        self.notify_file_hook(path, Outcome::Failed);
+                        self.audit(path, Outcome::Failed, Some(&issue.reason));
+                        report.failed.push(issue);
+                        Err(error)
+                    }
+                }
+            }
+        }
+    }
+
+    fn notify_hook(&self, key: &str, tokens: usize, outcome: Outcome) {
+        if let Some(hook) = &self.ingest_hook {
+            hook(key, tokens, outcome);
+        }
+    }
+
+    // A file without documents is notified under the key it would have
+    fn notify_file_hook(&self, path: &Path, outcome: Outcome) {
+        if let Some(key) = self.key_of(path) {
+            self.notify_hook(&key, 0, outcome);
+        }
+    }
+
+    fn audit(&mut self, path: &Path, outcome: Outcome, reason: Option<&str>) {
+        if let Some(audit) = &mut self.audit {
+            audit.record(path, outcome, reason);
+        }
+    }
+
+    // Return the outcome, the number of bytes read and the file with the same content, if the file is skipped as a duplicate
+    fn index_file(&mut self, path: &Path) -> Result<(Outcome, u64, Option<PathBuf>), Error> {
+        #[cfg(feature = "archive")]
+        {
+            if archive::is_archive(path) {
+                return self.index_archive(path).map(|(outcome, size)| (outcome, size, None));
+            }
+        }
+        let file_system = &self.file_system;
+        let (bytes, modified) = self.retry.run(|| Ok((file_system.read(path)?, file_system.metadata(path)?.modified)))?;
+        let size = bytes.len() as u64;
+        let key = self.key_of(path).unwrap();
+        let mut document = self.tagged(Document::from_file(path, &bytes, modified));
+        document.split = self.split;
+        let indexed_keys: Vec<String> = self.keys_by_path.get(path).map(|keys| keys.iter().cloned().collect()).unwrap_or_default();
+        // A file keyed differently since it was indexed, e.g. with another prefix, or split differently is indexed again
+        let unchanged = !indexed_keys.is_empty() && indexed_keys.iter().all(|indexed_key| {
+            let indexed = &self.documents[indexed_key];
+            indexed_key.starts_with(key.as_str()) && document.hash.is_some() && indexed.hash == document.hash && indexed.split == document.split
+        });
+        if unchanged {
+            for key in indexed_keys {
+                self.insert_document(key.clone(), document.clone());
+                self.notify_hook(&key, 0, Outcome::Unchanged);
+            }
+            return Ok((Outcome::Unchanged, size, None));
+        }
+        if self.dedup && indexed_keys.is_empty() {
+            if let Some(original) = self.duplicate_of(path, &document) {
+                self.alias_file(&original, path);
+                return Ok((Outcome::Skipped, size, Some(original)));
+            }
+        }
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+        let content = self.extractors.extract(None, extension, bytes)?;
+        document.tags.extend(front_matter(&content));
+        let parts = split_documents(&key, path, &content, &self.split);
+        for indexed_key in indexed_keys {
+            if !parts.iter().any(|(part_key, _)| part_key == &indexed_key) {
+                self.store.remove(&indexed_key)?;
+                self.remove_document(&indexed_key);
+            }
+        }
+        for (part_key, text) in parts {
+            let analyzed = self.analyzer.analyze(&text);
+            self.store.insert(&part_key, Filter::with_backend(self.backend, self.hash_function, self.error_rate, &analyzed)?)?;
+            let mut part = document.clone();
+            part.count_tokens(&analyzed);
+            let tokens = part.tokens.unwrap_or_default();
+            self.insert_document(part_key.clone(), part);
+            self.notify_hook(&part_key, tokens, Outcome::Ingested);
+        }
+        Ok((Outcome::Ingested, size, None))
+    }
+
+    // Another file indexed with the content of `document`, still the same content
+    fn duplicate_of(&self, path: &Path, document: &Document) -> Option<PathBuf> {
+        let original = self.paths_by_hash.get(document.hash.as_ref()?)?;
+        let keys = self.keys_by_path.get(original)?;
+        let same = original != path && keys.iter().all(|key| self.documents[key].hash == document.hash);
+        if same { Some(original.clone()) } else { None }
+    }
+
+    // Index each text file of the archive at `path` as a document keyed path!entry, streaming the archive
+    #[cfg(feature = "archive")]
+    fn index_archive(&mut self, path: &Path) -> Result<(Outcome, u64), Error> {
+        let file_system = self.file_system.clone();
+        // The archive is read twice, first to know whether it changed, without holding it in memory
+        let (hash, size) = self.retry.run(|| archive::stream_hash(file_system.open(path)?))?;
+        let document = self.tagged(Document::from_hash(path, hash, file_system.metadata(path)?.modified));
+        let key = self.key_of(path).unwrap();
+        let indexed_keys: Vec<String> = self.keys_by_path.get(path).map(|keys| keys.iter().cloned().collect()).unwrap_or_default();
+        let unchanged = !indexed_keys.is_empty() && indexed_keys.iter().all(|indexed_key| indexed_key.starts_with(key.as_str()) && self.documents[indexed_key].hash == document.hash);
+        if unchanged {
+            for key in indexed_keys {
+                self.insert_document(key.clone(), document.clone());
+                self.notify_hook(&key, 0, Outcome::Unchanged);
+            }
+            return Ok((Outcome::Unchanged, size));
+        }
+        let analyzer = self.analyzer.clone();
+        let mut entry_keys = BTreeSet::new();
+        archive::for_each_entry(path, file_system.open(path)?, &analyzer, |entry, tokens| {
+            let entry_key = format!("{}!{}", key, entry);
+            // Tokens are already analyzed
+            self.store.insert(&entry_key, Filter::with_backend(self.backend, self.hash_function, self.error_rate, tokens)?)?;
+            let mut entry_document = document.clone();
+            entry_document.count_tokens(tokens);
+            let count = entry_document.tokens.unwrap_or_default();
+            self.insert_document(entry_key.clone(), entry_document);
+            self.notify_hook(&entry_key, count, Outcome::Ingested);
+            entry_keys.insert(entry_key);
+            Ok(())
+        })?;
+        for indexed_key in indexed_keys {
+            if !entry_keys.contains(&indexed_key) {
+                self.store.remove(&indexed_key)?;
+                self.remove_document(&indexed_key);
+            }
+        }
+        Ok((Outcome::Ingested, size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::fs::File;
+    use super::*;
+    use crate::{FsIndexBuilder, MemoryFileSystem};
+
+    #[test]
+    fn index_source_is_file() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest("./test/data/simple_content.txt");
+        assert_eq!(vec!["./test/data/simple_content.txt"], index.search("word1").unwrap());
+    }
+
+    #[test]
+    fn index_source_is_directory() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest("./test/data/simple_directory");
+        assert_eq!(vec!["./test/data/simple_directory/file1.txt"], index.search("word1").unwrap());
+        assert_eq!(vec!["./test/data/simple_directory/file2.txt"], index.search("word4").unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected="Error source must be an UTF-8 text file")]
+    fn index_source_is_binary_file() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest("./test/data/image_file.png");
+    }
+
+    #[test]
+    #[should_panic(expected="source type must be file or directory")]
+    fn index_source_is_unsupported() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest("./test/unknown_source");
+    }
+
+    #[test]
+    fn index_source_is_directory_with_mixed_content() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest("./test/data/directory_with_mixed_content");
+        assert_eq!(vec!["./test/data/directory_with_mixed_content/simple_content.txt"], index.search("word1").unwrap());
+    }
+
+    #[test]
+    fn file_simple_content() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest("./test/data/simple_content.txt");
+        assert_eq!(vec!["./test/data/simple_content.txt"], index.search("word1").unwrap());
+        assert_eq!(vec!["./test/data/simple_content.txt"], index.search("word2").unwrap());
+        assert_eq!(vec!["./test/data/simple_content.txt"], index.search("word3").unwrap());
+        assert_eq!(vec!["./test/data/simple_content.txt"], index.search("word4").unwrap());
+    }
+
+    #[test]
+    fn simple_directory_content() {
+       let mut index = FsIndex::new(0.01);
+       index.ingest("./test/data/simple_directory");
+       assert_eq!(vec!["./test/data/simple_directory/file1.txt"], index.search("word1").unwrap());
+       assert_eq!(vec!["./test/data/simple_directory/file1.txt"], index.search("word2").unwrap());
+       assert_eq!(vec!["./test/data/simple_directory/file1.txt"], index.search("word3").unwrap());
+       assert_eq!(vec!["./test/data/simple_directory/file2.txt"], index.search("word4").unwrap());
+       assert_eq!(vec!["./test/data/simple_directory/file2.txt"], index.search("word5").unwrap());
+    }
+
+    #[test]
+    fn random_directory_content() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest("./test/data/random_directory");
+        assert_eq!(vec!["./test/data/random_directory/file1.txt"], index.search("word1").unwrap());
+        assert_eq!(vec!["./test/data/random_directory/file1.txt"], index.search("word2").unwrap());
+        assert_eq!(vec!["./test/data/random_directory/file1.txt"], index.search("word3").unwrap());
+        assert_eq!(None, index.search("word4"));
+        assert_eq!(None, index.search("word5"));
+    }
+
+    #[test]
+    fn several_matches() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest("./test/data/several_matches_directory");
+        let expected = vec!["./test/data/several_matches_directory/file1.txt"];
+        assert_eq!(expected, index.search("word2").unwrap());
+        let expected = vec!["./test/data/several_matches_directory/file1.txt", "./test/data/several_matches_directory/file2.txt"];
+        assert_eq!(index.search("word1").unwrap(), expected);
+        assert_eq!(index.search("word3").unwrap(), expected);
+    }
+
+    #[test]
+    fn multi_keywords_search() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest("./test/data/several_matches_directory");
+        let expected = vec!["./test/data/several_matches_directory/file1.txt"];
+        assert_eq!(expected, index.search("word1 word2").unwrap());
+    }
+
+    #[test]
+    fn clean_keywords_before_search() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest("./test/data/simple_directory");
+        let expected = vec!["./test/data/simple_directory/file1.txt"];
+        assert_eq!(index.search("(word1) Word2, word3?").unwrap(), expected);
+    }
+
+    #[test]
+    fn restore_index() {
+        let index = FsIndex::restore("./test/data/simple_dump.json");
+        let expected = vec!["./test/data/simple_directory/file1.txt"];
+        assert_eq!(index.search("(word1) Word2, word3?").unwrap(), expected);
+    }
+
+    #[test]
+    #[should_panic(expected="Unable to read dump file ./test/data/image_file.png")]
+    fn restore_wrong_file() {
+        FsIndex::restore("./test/data/image_file.png");
+    }
+
+    #[test]
+    #[should_panic(expected="File not found ./test/data/foobar")]
+    fn restore_unknown_file() {
+        FsIndex::restore("./test/data/foobar");
+    }
+
+    #[test]
+    fn dump_index() {
+        let mut index = FsIndex::new(0.1);
+        index.ingest("./test/data/simple_content.txt");
+        let mut dest_file = std::env::temp_dir();
+        dest_file.push("bloom_dump.json");
+        index.dump(dest_file.as_path().to_str().unwrap());
+        let modified = fs::metadata("./test/data/simple_content.txt").unwrap().modified().unwrap().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let expected = format!("{{\"version\":3,\"error_rate\":0.1,\"bloom_filters\":{{\"./test/data/simple_content.txt\":{{\"key_size\":4,\"bitfield\":[189,105,3],\"bitfield_size\":20,\"hash\":\"fnv\"}}}},\"documents\":{{\"./test/data/simple_content.txt\":{{\"hash\":\"0a7c92a760908813ee7dc6bdbae8454c55cffc03d3b7c2540def80d0bbb0955d\",\"modified\":{},\"path\":\"./test/data/simple_content.txt\",\"tokens\":4,\"unique_tokens\":4}}}}}}\n", modified);
+        let actual = fs::read_to_string(&dest_file).unwrap();
+        assert_eq!(expected, actual);
+        fs::remove_file(dest_file).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected="Impossible to create dump file ./test/data")]
+    fn dump_in_directory() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest("./test/data/simple_content.txt");
+        index.dump("./test/data");
+    }
+
+    #[test]
+    fn restore_dumped_documents() {
+        let mut index = FsIndex::new(0.1);
+        index.ingest("./test/data/simple_content.txt");
+        let mut dest_file = std::env::temp_dir();
+        dest_file.push("bloom_dump_documents.json");
+        index.dump(dest_file.as_path().to_str().unwrap());
+        let restored = FsIndex::restore(dest_file.as_path().to_str().unwrap());
+        assert_eq!(Some(Staleness::Fresh), restored.staleness("./test/data/simple_content.txt"));
+        fs::remove_file(dest_file).unwrap();
+    }
+
+    #[test]
+    fn staleness_of_legacy_dump_is_unknown() {
+        let index = FsIndex::restore("./test/data/simple_dump.json");
+        assert_eq!(Some(Staleness::Unknown), index.staleness("./test/data/simple_directory/file1.txt"));
+        assert_eq!(None, index.staleness("./test/data/simple_directory/file2.txt"));
+    }
+
+    #[test]
+    fn staleness_follows_content_hash() {
+        let mut source = std::env::temp_dir();
+        source.push("bloom_staleness.txt");
+        let key = source.as_path().to_str().unwrap();
+        fs::write(&source, "word1 word2").unwrap();
+        let mut index = FsIndex::new(0.01);
+        index.ingest(key);
+        assert_eq!(Some(Staleness::Fresh), index.staleness(key));
+        fs::write(&source, "word3 word4").unwrap();
+        let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        File::options().write(true).open(&source).unwrap().set_modified(modified).unwrap();
+        assert_eq!(Some(Staleness::Modified), index.staleness(key));
+        index.ingest(key);
+        assert_eq!(Some(Staleness::Fresh), index.staleness(key));
+        fs::remove_file(&source).unwrap();
+        assert_eq!(Some(Staleness::Missing), index.staleness(key));
+        let stats = index.stats();
+        assert_eq!(1, stats.documents);
+        assert_eq!(1, stats.missing);
+    }
+
+    #[test]
+    fn prune_deleted_files() {
+        let mut source = std::env::temp_dir();
+        source.push("bloom_prune.txt");
+        let key = source.as_path().to_str().unwrap();
+        fs::write(&source, "word1 word2").unwrap();
+        let mut index = FsIndex::new(0.01);
+        index.ingest(key);
+        index.ingest("./test/data/simple_content.txt");
+        assert!(index.prune().is_empty());
+        fs::remove_file(&source).unwrap();
+        assert_eq!(vec![key.to_string()], index.prune());
+        assert_eq!(None, index.staleness(key));
+        assert_eq!(vec!["./test/data/simple_content.txt"], index.search("word1").unwrap());
+    }
+
+    #[test]
+    fn refresh_changed_files() {
+        let mut changed = std::env::temp_dir();
+        changed.push("bloom_refresh_changed.txt");
+        let mut deleted = std::env::temp_dir();
+        deleted.push("bloom_refresh_deleted.txt");
+        let changed_key = changed.as_path().to_str().unwrap();
+        let deleted_key = deleted.as_path().to_str().unwrap();
+        fs::write(&changed, "word1 word2").unwrap();
+        fs::write(&deleted, "word1").unwrap();
+        let mut index = FsIndex::new(0.01);
+        index.ingest(changed_key);
+        index.ingest(deleted_key);
+        fs::write(&changed, "word3 word4").unwrap();
+        let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        File::options().write(true).open(&changed).unwrap().set_modified(modified).unwrap();
+        fs::remove_file(&deleted).unwrap();
+        let refresh = index.refresh();
+        assert_eq!(vec![deleted_key.to_string()], refresh.pruned);
+        assert_eq!(vec![changed_key.to_string()], refresh.updated);
+        assert_eq!(vec![changed_key], index.search("word3").unwrap());
+        assert_eq!(None, index.search("word1"));
+        fs::remove_file(&changed).unwrap();
+    }
+
+    #[test]
+    fn export_keys_in_order() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest("./test/data/simple_directory");
+        index.ingest("./test/data/simple_content.txt");
+        let mut keys = Vec::new();
+        index.export_keys(&mut keys).unwrap();
+        let expected = "./test/data/simple_content.txt\n./test/data/simple_directory/file1.txt\n./test/data/simple_directory/file2.txt\n";
+        assert_eq!(expected, String::from_utf8(keys).unwrap());
+    }
+
+    #[test]
+    fn ingest_pre_tokenized_content() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest_tokens("doc-1", vec!["word1", "word2"].into_iter());
+        index.ingest_tokens("doc-2", "word2 word3".split(' '));
+        assert_eq!(vec!["doc-1"], index.search("word1").unwrap());
+        assert_eq!(vec!["doc-1", "doc-2"], index.search("word2").unwrap());
+        assert_eq!(Some(Staleness::Unknown), index.staleness("doc-1"));
+        assert!(index.prune().is_empty());
+    }
+
+    #[test]
+    fn index_with_store() {
+        let mut index = FsIndex::with_store(0.01, MemoryStore::default());
+        index.ingest("./test/data/simple_content.txt");
+        let restored = FsIndex::with_store(0.01, index.store);
+        assert_eq!(vec!["./test/data/simple_content.txt"], restored.search("word1").unwrap());
+    }
+
+    #[test]
+    fn restore_filtered_index() {
+        let mut dump = std::env::temp_dir();
+        dump.push("bloom_filtered_dump.json");
+        let mut index = FsIndex::new(0.01);
+        index.ingest("./test/data/simple_directory");
+        index.ingest("./test/data/simple_content.txt");
+        index.dump(dump.to_str().unwrap());
+        let restored = FsIndex::restore_filtered(dump.to_str().unwrap(), "./test/data/simple_directory/");
+        assert_eq!(vec!["./test/data/simple_directory/file1.txt"], restored.search("word1").unwrap());
+        assert_eq!(Some(Staleness::Fresh), restored.staleness("./test/data/simple_directory/file2.txt"));
+        assert_eq!(None, restored.staleness("./test/data/simple_content.txt"));
+        fs::remove_file(&dump).unwrap();
+    }
+
+    #[test]
+    fn recursive_directory_content() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest_recursive("./test/data/random_directory");
+        assert_eq!(vec!["./test/data/random_directory/file1.txt"], index.search("word1").unwrap());
+        assert_eq!(vec!["./test/data/random_directory/sub_directory/file2.txt"], index.search("word4").unwrap());
+    }
 
     #[test]
-    fn index_source_is_file() {
+    fn dump_and_restore_sections() {
+        let mut dump = std::env::temp_dir();
+        dump.push("bloom_dump.sections");
         let mut index = FsIndex::new(0.01);
+        index.ingest("./test/data/simple_directory");
         index.ingest("./test/data/simple_content.txt");
-        assert_eq!(vec!["./test/data/simple_content.txt"], index.search("word1").unwrap());
+        index.dump_sections(dump.to_str().unwrap());
+        let restored = FsIndex::restore(dump.to_str().unwrap());
+        assert_eq!(index.search("word1"), restored.search("word1"));
+        assert_eq!(Some(Staleness::Fresh), restored.staleness("./test/data/simple_content.txt"));
+        fs::remove_file(&dump).unwrap();
     }
 
     #[test]
-    fn index_source_is_directory() {
+    fn notebook_cells_content() {
+        let mut notebook = std::env::temp_dir();
+        notebook.push("bloom_notebook.ipynb");
+        let key = notebook.as_path().to_str().unwrap();
+        fs::write(&notebook, r#"{"cells": [{"cell_type": "markdown", "source": ["word1"]}, {"cell_type": "code", "source": "word2", "outputs": [{"text": "word3"}]}]}"#).unwrap();
+        let cell0 = format!("{}#cell0", key);
+        let cell1 = format!("{}#cell1", key);
+        let mut index = FsIndex::new(0.01);
+        index.ingest(key);
+        assert_eq!(vec![&cell0], index.search("word1").unwrap());
+        assert_eq!(vec![&cell1], index.search("word2").unwrap());
+        assert_eq!(None, index.search("word3"));
+        fs::write(&notebook, r#"{"cells": [{"cell_type": "markdown", "source": ["word2"]}]}"#).unwrap();
+        File::options().write(true).open(&notebook).unwrap().set_modified(std::time::UNIX_EPOCH).unwrap();
+        index.refresh();
+        assert_eq!(vec![&cell0], index.search("word2").unwrap());
+        assert_eq!(None, index.search("word1"));
+        fs::remove_file(&notebook).unwrap();
+        assert_eq!(vec![cell0.clone()], index.prune());
+    }
+
+    #[test]
+    fn audit_directory_ingestion() {
+        let mut log = std::env::temp_dir();
+        log.push("bloom_audit.jsonl");
+        let _ = fs::remove_file(&log);
+        let mut index = FsIndex::new(0.01);
+        index.set_audit_log(log.to_str().unwrap());
+        index.ingest("./test/data/directory_with_mixed_content");
+        index.ingest("./test/data/directory_with_mixed_content/simple_content.txt");
+        let records: Vec<serde_json::Value> = fs::read_to_string(&log).unwrap().lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let outcome = |path: &str| records.iter()
+            .filter(|record| record["path"] == path)
+            .map(|record| record["outcome"].as_str().unwrap())
+            .collect::<Vec<&str>>();
+        assert_eq!(3, records.len());
+        assert_eq!(vec!["ingested", "unchanged"], outcome("./test/data/directory_with_mixed_content/simple_content.txt"));
+        assert_eq!(vec!["skipped"], outcome("./test/data/directory_with_mixed_content/image_file.png"));
+        assert!(records.iter().any(|record| record["outcome"] == "ingested" && record["bytes"] == 25));
+        fs::remove_file(&log).unwrap();
+    }
+
+    #[test]
+    fn ingest_report() {
+        let mut index = FsIndex::new(0.01);
+        let report = index.ingest("./test/data/directory_with_mixed_content");
+        assert_eq!(1, report.indexed);
+        assert_eq!(0, report.unchanged);
+        assert_eq!(25, report.bytes);
+        assert_eq!(vec![PathBuf::from("./test/data/directory_with_mixed_content/image_file.png")], report.skipped.iter().map(|issue| issue.path.clone()).collect::<Vec<PathBuf>>());
+        assert!(report.failed.is_empty());
+        let report = index.ingest("./test/data/directory_with_mixed_content");
+        assert_eq!(0, report.indexed);
+        assert_eq!(1, report.unchanged);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn continue_after_failed_file() {
+        let mut source = std::env::temp_dir();
+        source.push("bloom_continue_on_error");
+        let _ = fs::remove_dir_all(&source);
+        fs::create_dir(&source).unwrap();
+        fs::write(source.join("content.txt"), "word1 word2").unwrap();
+        std::os::unix::fs::symlink(source.join("missing.txt"), source.join("dangling.txt")).unwrap();
+        let mut index = FsIndex::new(0.01);
+        let report = index.ingest(source.to_str().unwrap());
+        assert_eq!(1, report.indexed);
+        assert_eq!(vec![source.join("dangling.txt")], report.failed.iter().map(|issue| issue.path.clone()).collect::<Vec<PathBuf>>());
+        assert_eq!(1, index.search("word1").unwrap().len());
+        fs::remove_dir_all(&source).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    #[should_panic(expected="Error reading file")]
+    fn fail_fast_on_failed_file() {
+        let mut source = std::env::temp_dir();
+        source.push("bloom_fail_fast");
+        let _ = fs::remove_dir_all(&source);
+        fs::create_dir(&source).unwrap();
+        std::os::unix::fs::symlink(source.join("missing.txt"), source.join("dangling.txt")).unwrap();
+        let mut index = FsIndexBuilder::new(0.01).fail_fast(true).build();
+        index.ingest(source.to_str().unwrap());
+    }
+
+    #[test]
+    fn clone_and_snapshot() {
         let mut index = FsIndex::new(0.01);
+        index.ingest("./test/data/simple_content.txt");
+        let copy = index.clone();
+        let snapshot = index.snapshot();
         index.ingest("./test/data/simple_directory");
-        assert_eq!(vec!["./test/data/simple_directory/file1.txt"], index.search("word1").unwrap());
-        assert_eq!(vec!["./test/data/simple_directory/file2.txt"], index.search("word4").unwrap());
+        assert_eq!(2, index.search("word1").unwrap().len());
+        assert_eq!(vec!["./test/data/simple_content.txt"], copy.search("word1").unwrap());
+        assert_eq!(vec!["./test/data/simple_content.txt"], snapshot.search("word1").unwrap());
+        assert_eq!(None, snapshot.search("word5"));
     }
 
     #[test]
-    #[should_panic(expected="Error source must be an UTF-8 text file")]
-    fn index_source_is_binary_file() {
+    fn display_summary() {
         let mut index = FsIndex::new(0.01);
-        index.ingest("./test/data/image_file.png");
+        index.ingest("./test/data/simple_directory");
+        assert!(index.memory_usage() > 2 * ENTRY_OVERHEAD);
+        assert_eq!("2 documents, error rate 0.01", index.to_string());
+        assert_eq!("FsIndex { documents: 2, error_rate: 0.01 }", format!("{:?}", index));
     }
 
     #[test]
-    #[should_panic(expected="source type must be file or directory")]
-    fn index_source_is_unsupported() {
+    fn keys_in_order() {
         let mut index = FsIndex::new(0.01);
-        index.ingest("./test/unknown_source");
+        index.ingest("./test/data/simple_directory");
+        index.ingest("./test/data/simple_content.txt");
+        let expected = vec!["./test/data/simple_content.txt", "./test/data/simple_directory/file1.txt", "./test/data/simple_directory/file2.txt"];
+        assert_eq!(expected, index.keys().collect::<Vec<&str>>());
     }
 
     #[test]
-    fn index_source_is_directory_with_mixed_content() {
+    fn search_lazily() {
         let mut index = FsIndex::new(0.01);
-        index.ingest("./test/data/directory_with_mixed_content");
-        assert_eq!(vec!["./test/data/directory_with_mixed_content/simple_content.txt"], index.search("word1").unwrap());
+        index.ingest("./test/data/simple_directory");
+        index.ingest("./test/data/simple_content.txt");
+        assert_eq!(vec!["./test/data/simple_content.txt"], index.search_iter("word1").take(1).collect::<Vec<&String>>());
+        assert_eq!(index.search("word1").unwrap(), index.search_iter("word1").collect::<Vec<&String>>());
+        assert_eq!(None, index.search_iter("word6").next());
     }
 
     #[test]
-    fn file_simple_content() {
+    fn search_owned_results() {
         let mut index = FsIndex::new(0.01);
         index.ingest("./test/data/simple_content.txt");
-        assert_eq!(vec!["./test/data/simple_content.txt"], index.search("word1").unwrap());
-        assert_eq!(vec!["./test/data/simple_content.txt"], index.search("word2").unwrap());
-        assert_eq!(vec!["./test/data/simple_content.txt"], index.search("word3").unwrap());
-        assert_eq!(vec!["./test/data/simple_content.txt"], index.search("word4").unwrap());
+        let results = index.search_owned("word1");
+        index.ingest("./test/data/simple_directory");
+        assert_eq!(Some(vec!["./test/data/simple_content.txt".to_string()]), results);
+        assert_eq!(None, index.search_owned("word6"));
     }
 
     #[test]
-    fn simple_directory_content() {
-       let mut index = FsIndex::new(0.01);
-       index.ingest("./test/data/simple_directory");
-       assert_eq!(vec!["./test/data/simple_directory/file1.txt"], index.search("word1").unwrap());
-       assert_eq!(vec!["./test/data/simple_directory/file1.txt"], index.search("word2").unwrap());
-       assert_eq!(vec!["./test/data/simple_directory/file1.txt"], index.search("word3").unwrap());
-       assert_eq!(vec!["./test/data/simple_directory/file2.txt"], index.search("word4").unwrap());
-       assert_eq!(vec!["./test/data/simple_directory/file2.txt"], index.search("word5").unwrap());
+    fn search_matching_paths() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest("./test/data/simple_directory");
+        index.ingest_tokens("tokens", vec!["word1"].into_iter());
+        assert_eq!(vec![PathBuf::from("./test/data/simple_directory/file1.txt")], index.search_paths("word1"));
+        assert!(index.search_paths("word6").is_empty());
     }
 
     #[test]
-    fn random_directory_content() {
+    fn structured_query() {
         let mut index = FsIndex::new(0.01);
-        index.ingest("./test/data/random_directory");
-        assert_eq!(vec!["./test/data/random_directory/file1.txt"], index.search("word1").unwrap());
-        assert_eq!(vec!["./test/data/random_directory/file1.txt"], index.search("word2").unwrap());
-        assert_eq!(vec!["./test/data/random_directory/file1.txt"], index.search("word3").unwrap());
-        assert_eq!(None, index.search("word4"));
-        assert_eq!(None, index.search("word5"));
+        index.ingest("./test/data/simple_directory");
+        index.ingest("./test/data/simple_content.txt");
+        assert_eq!(vec!["./test/data/simple_directory/file1.txt"], index.query(&Query::term("word1").not(Query::term("word4"))).unwrap());
+        assert_eq!(3, index.query(&Query::term("word5").or(Query::term("word1 word2"))).unwrap().len());
+        assert_eq!(None, index.query(&Query::term("word5").and(Query::term("word1"))));
     }
 
     #[test]
-    fn several_matches() {
+    fn ingest_listed_files() {
         let mut index = FsIndex::new(0.01);
-        index.ingest("./test/data/several_matches_directory");
-        let expected = vec!["./test/data/several_matches_directory/file1.txt"];
-        assert_eq!(expected, index.search("word2").unwrap());
-        let expected = vec!["./test/data/several_matches_directory/file1.txt", "./test/data/several_matches_directory/file2.txt"];
-        assert_eq!(index.search("word1").unwrap(), expected);
-        assert_eq!(index.search("word3").unwrap(), expected);
+        let report = index.ingest_files(vec!["./test/data/simple_content.txt", "./test/data/image_file.png", "./test/data/simple_directory", "./test/data/unknown_file"]);
+        assert_eq!(1, report.indexed);
+        assert_eq!(2, report.skipped.len());
+        assert_eq!(vec![PathBuf::from("./test/data/unknown_file")], report.failed.iter().map(|issue| issue.path.clone()).collect::<Vec<PathBuf>>());
+        assert_eq!(vec!["./test/data/simple_content.txt"], index.search("word1").unwrap());
     }
 
     #[test]
-    fn multi_keywords_search() {
+    #[cfg(unix)]
+    fn hard_links_are_aliases() {
+        let mut source = std::env::temp_dir();
+        source.push("bloom_hard_links");
+        let _ = fs::remove_dir_all(&source);
+        fs::create_dir(&source).unwrap();
+        fs::write(source.join("original.txt"), "word1 word2").unwrap();
+        fs::hard_link(source.join("original.txt"), source.join("link.txt")).unwrap();
         let mut index = FsIndex::new(0.01);
-        index.ingest("./test/data/several_matches_directory");
-        let expected = vec!["./test/data/several_matches_directory/file1.txt"];
-        assert_eq!(expected, index.search("word1 word2").unwrap());
+        let report = index.ingest(source.to_str().unwrap());
+        assert_eq!(1, report.indexed);
+        assert_eq!(1, report.skipped.len());
+        let keys: Vec<String> = index.keys().map(|key| key.to_string()).collect();
+        assert_eq!(1, keys.len());
+        let alias = if keys[0].ends_with("original.txt") { "link.txt" } else { "original.txt" };
+        assert_eq!(vec![source.join(alias).to_str().unwrap().to_string()], index.document(&keys[0]).unwrap().aliases);
+        index.ingest(source.to_str().unwrap());
+        assert_eq!(1, index.keys().count());
+        assert_eq!(1, index.document(&keys[0]).unwrap().aliases.len());
+        fs::remove_dir_all(&source).unwrap();
     }
 
     #[test]
-    fn clean_keywords_before_search() {
+    fn chunk_large_files() {
+        let mut large = std::env::temp_dir();
+        large.push("bloom_chunk_large.txt");
+        let key = large.as_path().to_str().unwrap();
+        fs::write(&large, "word1 word2 word3 word4").unwrap();
         let mut index = FsIndex::new(0.01);
+        index.set_chunk_size(12);
+        index.ingest(key);
+        assert_eq!(vec![format!("{}#chunk0", key)], index.search("word1").unwrap().into_iter().cloned().collect::<Vec<String>>());
+        assert_eq!(vec![format!("{}#chunk1", key)], index.search("word4").unwrap().into_iter().cloned().collect::<Vec<String>>());
+        fs::write(&large, "word5").unwrap();
+        index.ingest(key);
+        assert_eq!(vec![key], index.search("word5").unwrap());
+        assert_eq!(None, index.search("word1"));
+        fs::remove_file(&large).unwrap();
+    }
+
+    #[test]
+    fn index_paragraphs() {
+        let mut book = std::env::temp_dir();
+        book.push("bloom_paragraphs_book.txt");
+        let key = book.as_path().to_str().unwrap();
+        fs::write(&book, "word1 word2\nword3\n\nword4\n").unwrap();
+        let mut index = FsIndexBuilder::new(0.01).paragraphs(true).build();
+        index.ingest(key);
+        assert_eq!(vec![format!("{}¶0", key)], index.search("word3").unwrap().into_iter().cloned().collect::<Vec<String>>());
+        assert_eq!(vec![format!("{}¶1", key)], index.search("word4").unwrap().into_iter().cloned().collect::<Vec<String>>());
+        fs::remove_file(&book).unwrap();
+    }
+
+    #[test]
+    fn rename_without_reading_again() {
+        let mut from = std::env::temp_dir();
+        from.push("bloom_rename_from.txt");
+        let mut to = std::env::temp_dir();
+        to.push("bloom_rename_to.txt");
+        let from_key = from.as_path().to_str().unwrap();
+        let to_key = to.as_path().to_str().unwrap();
+        fs::write(&from, "word1 word2").unwrap();
+        let mut index = FsIndex::new(0.01);
+        index.ingest(from_key);
+        fs::remove_file(&from).unwrap();
+        assert_eq!(vec![to_key.to_string()], index.rename(from_key, to_key));
+        assert_eq!(vec![to_key], index.search("word1").unwrap());
+        assert_eq!(Some(to.clone()), index.document(to_key).unwrap().path);
+        assert!(index.document(from_key).is_none());
+        assert!(index.rename(from_key, to_key).is_empty());
+    }
+
+    #[test]
+    fn map_document_keys() {
+        let mut index = FsIndexBuilder::new(0.01)
+            .key_mapper(|path| format!("doc:{}", path.file_name().unwrap().to_str().unwrap()))
+            .build();
         index.ingest("./test/data/simple_directory");
-        let expected = vec!["./test/data/simple_directory/file1.txt"];
-        assert_eq!(index.search("(word1) Word2, word3?").unwrap(), expected);
+        assert_eq!(vec!["doc:file1.txt"], index.search("word1").unwrap());
+        assert_eq!(Some(PathBuf::from("./test/data/simple_directory/file1.txt")), index.document("doc:file1.txt").unwrap().path);
+        let report = index.ingest("./test/data/simple_directory");
+        assert_eq!(0, report.indexed);
     }
 
     #[test]
-    fn restore_index() {
-        let index = FsIndex::restore("./test/data/simple_dump.json");
-        let expected = vec!["./test/data/simple_directory/file1.txt"];
-        assert_eq!(index.search("(word1) Word2, word3?").unwrap(), expected);
+    fn prefix_document_keys() {
+        let mut index = FsIndexBuilder::new(0.01).key_prefix("projA:").build();
+        index.ingest("./test/data/simple_content.txt");
+        index.set_key_prefix("projB:");
+        index.ingest("./test/data/simple_directory");
+        assert_eq!(vec!["projA:./test/data/simple_content.txt", "projB:./test/data/simple_directory/file1.txt"], index.search("word1").unwrap());
     }
 
     #[test]
-    #[should_panic(expected="Unable to read dump file ./test/data/image_file.png")]
-    fn restore_wrong_file() {
-        FsIndex::restore("./test/data/image_file.png");
+    fn report_preferred_alias() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest("./test/data/simple_directory");
+        let key = "./test/data/simple_directory/file1.txt";
+        assert!(index.add_alias(key, "doc:file1"));
+        assert!(!index.add_alias(key, "./test/data/simple_directory/file2.txt"));
+        assert!(!index.add_alias("unknown", "doc:unknown"));
+        assert_eq!(Some(key), index.resolve("doc:file1"));
+        assert_eq!(index.document(key), index.document("doc:file1"));
+        assert_eq!(vec![key], index.search("word1").unwrap());
+        assert!(index.prefer_alias("doc:file1"));
+        assert_eq!(vec!["doc:file1"], index.search("word1").unwrap());
+        assert_eq!(vec![PathBuf::from(key)], index.search_paths("word1"));
+        index.ingest("./test/data/simple_directory");
+        assert_eq!(vec!["doc:file1"], index.search("word1").unwrap());
+        assert!(index.remove_alias("doc:file1"));
+        assert_eq!(vec![key], index.search("word1").unwrap());
+        assert_eq!(None, index.resolve("doc:file1"));
     }
 
     #[test]
-    #[should_panic(expected="File not found ./test/data/foobar")]
-    fn restore_unknown_file() {
-        FsIndex::restore("./test/data/foobar");
+    fn ingest_many_sources() {
+        let mut index = FsIndex::new(0.01);
+        let report = index.ingest_many(&["./test/data/simple_directory", "./test/data/simple_content.txt"]);
+        assert_eq!(3, report.indexed);
+        assert_eq!(vec!["./test/data/simple_content.txt", "./test/data/simple_directory/file1.txt"], index.search("word1").unwrap());
     }
 
     #[test]
-    fn dump_index() {
-        let mut index = FsIndex::new(0.1);
+    fn estimated_false_positive_rate() {
+        let mut index = FsIndex::new(0.01);
         index.ingest("./test/data/simple_content.txt");
+        let rate = index.false_positive_rate("./test/data/simple_content.txt").unwrap();
+        assert!(rate > 0.0 && rate < 0.1);
+        assert_eq!(None, index.false_positive_rate("unknown"));
+    }
+
+    #[test]
+    #[cfg(feature = "xor")]
+    fn dump_and_restore_xor_backend() {
+        let mut dump = std::env::temp_dir();
+        dump.push("bloom_dump_xor.json");
+        let mut index = FsIndex::new(0.01);
+        index.set_backend(Backend::Xor);
+        index.ingest("./test/data/simple_directory");
+        index.dump(dump.to_str().unwrap());
+        let restored = FsIndex::restore(dump.to_str().unwrap());
+        assert_eq!(vec!["./test/data/simple_directory/file1.txt"], restored.search("word1").unwrap());
+        assert_eq!(Backend::Xor, restored.backend);
+        fs::remove_file(&dump).unwrap();
+    }
+
+    #[test]
+    fn append_to_document() {
+        let mut dump = std::env::temp_dir();
+        dump.push("bloom_dump_append.json");
+        let mut index = FsIndex::new(0.01);
+        index.append("app.log", "word1 word2");
+        for position in 0..100 {
+            index.append("app.log", &format!("line{}", position));
+        }
+        assert_eq!(vec!["app.log"], index.search("word1 line99").unwrap());
+        assert_eq!(None, index.search("line100"));
+        assert!(index.false_positive_rate("app.log").unwrap() < 0.05);
+        index.dump(dump.to_str().unwrap());
+        let restored = FsIndex::restore(dump.to_str().unwrap());
+        assert_eq!(vec!["app.log"], restored.search("line0 word2").unwrap());
+        fs::remove_file(&dump).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "counting")]
+    fn dump_and_restore_hash_function() {
+        let mut dump = std::env::temp_dir();
+        dump.push("bloom_dump_siphash.json");
+        let mut index = FsIndex::new(0.01);
+        index.set_backend(Backend::Counting);
+        index.set_hash_function(HashFunction::SipHash { key: [1, 2] });
+        index.ingest("./test/data/simple_directory");
+        index.dump(dump.to_str().unwrap());
+        let restored = FsIndex::restore(dump.to_str().unwrap());
+        assert_eq!(vec!["./test/data/simple_directory/file1.txt"], restored.search("word1").unwrap());
+        assert_eq!(HashFunction::SipHash { key: [1, 2] }, restored.hash_function);
+        fs::remove_file(&dump).unwrap();
+    }
+
+    #[test]
+    fn dump_and_restore_analyzer() {
+        let mut dump = std::env::temp_dir();
+        dump.push("bloom_dump_analyzer.json");
+        let mut index = FsIndex::new(0.01);
+        index.set_analyzer(Analyzer {
+            fold_diacritics: true,
+            stop_words: vec!["the".to_string()].into_iter().collect(),
+            stemming: true,
+            ..Analyzer::default()
+        });
+        index.append("doc", "The Queries, été");
+        assert_eq!(vec!["doc"], index.search("query ETE").unwrap());
+        assert_eq!(vec!["doc"], index.query(&Query::term("queries")).unwrap());
+        index.dump(dump.to_str().unwrap());
+        let restored = FsIndex::restore(dump.to_str().unwrap());
+        assert_eq!(index.analyzer(), restored.analyzer());
+        assert_eq!(vec!["doc"], restored.search("queries été").unwrap());
+        assert_eq!(vec!["doc"], restored.snapshot().search("query").unwrap());
+        fs::remove_file(&dump).unwrap();
+    }
+
+    #[test]
+    fn migrate_legacy_dump() {
         let mut dest_file = std::env::temp_dir();
-        dest_file.push("bloom_dump.json");
-        index.dump(dest_file.as_path().to_str().unwrap());
-        let expected = "{\"error_rate\":0.1,\"bloom_filters\":{\"./test/data/simple_content.txt\":{\"key_size\":4,\"bitfield\":[248,242,8],\"bitfield_size\":20}}}\n";
-        let actual = fs::read_to_string(&dest_file).unwrap();
-        assert_eq!(actual, expected);
+        dest_file.push("bloom_dump_migrated.json");
+        assert_eq!(1, FsIndex::dump_version("./test/data/simple_dump.json"));
+        assert_eq!(1, FsIndex::migrate("./test/data/simple_dump.json", dest_file.to_str().unwrap()));
+        assert_eq!(DUMP_VERSION, FsIndex::dump_version(dest_file.to_str().unwrap()));
+        let migrated = FsIndex::restore(dest_file.to_str().unwrap());
+        assert_eq!(vec!["./test/data/simple_directory/file1.txt"], migrated.search("word1").unwrap());
+        assert_eq!(Some(PathBuf::from("./test/data/simple_directory/file1.txt")), migrated.document("./test/data/simple_directory/file1.txt").unwrap().path);
         fs::remove_file(dest_file).unwrap();
     }
 
     #[test]
-    #[should_panic(expected="Impossible to create dump file ./test/data")]
-    fn dump_in_directory() {
+    #[should_panic(expected="dump format version 99 is newer than the supported version")]
+    fn restore_newer_dump_version() {
+        let mut dest_file = std::env::temp_dir();
+        dest_file.push("bloom_dump_newer.json");
+        fs::write(&dest_file, "{\"version\":99,\"error_rate\":0.1,\"bloom_filters\":{}}").unwrap();
+        FsIndex::restore(dest_file.to_str().unwrap());
+    }
+
+    #[test]
+    fn ingest_from_memory_file_system() {
+        let files = MemoryFileSystem::default();
+        files.insert("/foo/bar.txt", "word1 word2");
+        files.insert("/foo/baz/qux.txt", "word3");
+        let mut index = FsIndexBuilder::new(0.01).file_system(files.clone()).build();
+        let report = index.ingest_recursive("/foo");
+        assert_eq!(2, report.indexed);
+        assert_eq!(vec!["/foo/baz/qux.txt"], index.search("word3").unwrap());
+        files.insert("/foo/baz/qux.txt", "word4");
+        files.set_modified("/foo/baz/qux.txt", std::time::UNIX_EPOCH);
+        files.remove("/foo/bar.txt");
+        let refresh = index.refresh();
+        assert_eq!(vec!["/foo/bar.txt"], refresh.pruned);
+        assert_eq!(vec!["/foo/baz/qux.txt"], refresh.updated);
+        assert!(index.search("word4").is_some());
+    }
+
+    #[test]
+    fn ingest_documents_map() {
         let mut index = FsIndex::new(0.01);
-        index.ingest("./test/data/simple_content.txt");
-        index.dump("./test/data");
+        index.ingest_tokens("doc-1", vec!["word0"].into_iter());
+        index.ingest_map((1..100).map(|id| (format!("doc-{}", id), format!("word{} common", id))));
+        assert_eq!(99, index.keys().count());
+        assert_eq!(vec!["doc-1"], index.search("word1").unwrap());
+        assert_eq!(None, index.search("word0"));
+        assert_eq!(99, index.search("common").unwrap().len());
+    }
+
+    #[test]
+    #[cfg(feature = "archive")]
+    fn ingest_archive_entries() {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, content) in [("docs/a.txt", "word1 word2"), ("docs/b.txt", "word3")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_cksum();
+            builder.append_data(&mut header, name, content.as_bytes()).unwrap();
+        }
+        let files = MemoryFileSystem::default();
+        files.insert("/foo/docs.tar", builder.into_inner().unwrap());
+        let mut index = FsIndexBuilder::new(0.01).file_system(files).build();
+        let report = index.ingest("/foo/docs.tar");
+        assert_eq!(1, report.indexed);
+        assert_eq!(vec!["/foo/docs.tar!docs/b.txt"], index.search("word3").unwrap());
+        assert_eq!(Outcome::Unchanged, index.index_file(Path::new("/foo/docs.tar")).unwrap().0);
+    }
+
+    #[test]
+    #[cfg(feature = "compressed")]
+    fn ingest_compressed_reader() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest_reader("plain", &b"word1 word2"[..]);
+        index.ingest_reader("zstd", zstd::encode_all(&b"word3 word4"[..], 0).unwrap().as_slice());
+        assert_eq!(vec!["plain"], index.search("word2").unwrap());
+        assert_eq!(vec!["zstd"], index.search("word3").unwrap());
+    }
+
+    #[test]
+    fn dedup_identical_files() {
+        let files = MemoryFileSystem::default();
+        files.insert("/foo/a.txt", "word1 word2");
+        files.insert("/foo/b.txt", "word1 word2");
+        files.insert("/foo/c.txt", "word3");
+        let mut index = FsIndex::new(0.01);
+        index.set_file_system(files.clone());
+        index.set_dedup(true);
+        let report = index.ingest("/foo");
+        assert_eq!(2, report.indexed);
+        assert_eq!(vec![Issue { path: PathBuf::from("/foo/b.txt"), reason: "same content as /foo/a.txt".to_string() }], report.skipped);
+        assert_eq!(vec!["/foo/a.txt"], index.search("word2").unwrap());
+        assert_eq!(Some("/foo/a.txt"), index.resolve("/foo/b.txt"));
+        // The copy of a changed file is not a duplicate any more
+        files.insert("/foo/a.txt", "word4");
+        index.ingest("/foo/a.txt");
+        files.insert("/foo/d.txt", "word1 word2");
+        assert_eq!(1, index.ingest("/foo/d.txt").indexed);
+    }
+
+    #[test]
+    fn map_prefix_of_moved_tree() {
+        let files = MemoryFileSystem::default();
+        files.insert("/build/src/a.txt", "word1 word2");
+        files.insert("/build/srcs/b.txt", "word1");
+        let mut index = FsIndex::new(0.01);
+        index.set_file_system(files);
+        index.ingest_recursive("/build");
+        index.add_alias("/build/src/a.txt", "/build/src/c.txt");
+        assert_eq!(Ok(1), index.map_prefix("/build/src", "/home/me/src"));
+        assert_eq!(vec!["/home/me/src/a.txt"], index.search("word2").unwrap());
+        assert_eq!(Some(PathBuf::from("/home/me/src/a.txt")), index.document("/home/me/src/a.txt").unwrap().path);
+        assert_eq!(Some("/home/me/src/a.txt"), index.resolve("/home/me/src/c.txt"));
+        assert!(index.document("/build/srcs/b.txt").is_some());
+    }
+
+    #[test]
+    fn query_keys_matching_glob() {
+        let files = MemoryFileSystem::default();
+        files.insert("/foo/src/a.rs", "word1");
+        files.insert("/foo/src/b.txt", "word1");
+        files.insert("/foo/tests/c.rs", "word1");
+        let mut index = FsIndex::new(0.01);
+        index.set_file_system(files);
+        index.ingest_recursive("/foo");
+        assert_eq!(vec!["/foo/src/a.rs"], index.query_matching(&Query::term("word1"), &Glob::new("src/**/*.rs")).unwrap());
+        assert!(index.query_matching(&Query::term("word1"), &Glob::new("/bar/**")).is_none());
+    }
+
+    #[test]
+    fn search_under_prefix() {
+        let files = MemoryFileSystem::default();
+        files.insert("/foo/src/a.txt", "word1");
+        files.insert("/foo/src/lib/b.txt", "word1");
+        files.insert("/foo/srcs/c.txt", "word1");
+        files.insert("/foo/tests/d.txt", "word1");
+        let mut index = FsIndex::new(0.01);
+        index.set_file_system(files);
+        index.ingest_recursive("/foo");
+        assert_eq!(vec!["/foo/src/a.txt", "/foo/src/lib/b.txt"], index.search_under("word1", "/foo/src/").unwrap());
+        assert!(index.search_under("word1", "/bar/").is_none());
+    }
+
+    #[test]
+    fn search_limited_hits() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest_map((0..10).map(|i| (format!("doc{}", i), "word1".to_string())));
+        assert_eq!(vec!["doc0", "doc1", "doc2"], index.search_limited("word1", 3).unwrap());
+        assert!(index.search_limited("word2", 3).is_none());
+        let query = Query::term("word1");
+        assert_eq!(vec!["doc1", "doc3"], index.query_where(&query, |key| key.ends_with('1') || key.ends_with('3')).take(2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn sort_search_hits() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest_map(vec![("doc1".to_string(), "word1".to_string()), ("doc2".to_string(), "word1 word2".to_string()), ("doc3".to_string(), "word2".to_string())]);
+        let query = Query::parse("word1 OR word2").unwrap();
+        let mut hits = index.query(&query).unwrap();
+        index.sort_hits(&mut hits, Sort::Score, &query);
+        assert_eq!(vec!["doc2", "doc1", "doc3"], hits);
+        index.sort_hits(&mut hits, Sort::Modified, &query);
+        assert_eq!(vec!["doc1", "doc2", "doc3"], hits);
+    }
+
+    #[test]
+    fn search_pages() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest_map((0..5).map(|i| (format!("doc{}", i), "word1".to_string())));
+        assert_eq!(vec!["doc0", "doc1"], index.search_page("word1", 0, 2));
+        assert_eq!(vec!["doc2", "doc3"], index.search_page("word1", 2, 2));
+        assert_eq!(vec!["doc4"], index.search_page("word1", 4, 2));
+        assert!(index.search_page("word1", 6, 2).is_empty());
+    }
+
+    #[test]
+    fn reproducible_dumps() {
+        let documents: Vec<(String, String)> = (0..20).map(|i| (format!("doc{}", i), format!("word{}", i))).collect();
+        let mut index = FsIndex::new(0.01);
+        index.ingest_map(documents.clone());
+        index.add_alias("doc1", "alias2");
+        index.add_alias("doc1", "alias1");
+        let mut reversed = FsIndex::new(0.01);
+        reversed.ingest_map(documents.into_iter().rev());
+        reversed.add_alias("doc1", "alias1");
+        reversed.add_alias("doc1", "alias2");
+        let dir = std::env::temp_dir();
+        for (first, second) in [("bloom_reproducible1.json", "bloom_reproducible2.json"), ("bloom_reproducible1.sections", "bloom_reproducible2.sections")] {
+            let (first, second) = (dir.join(first), dir.join(second));
+            if first.extension().unwrap() == "json" {
+                index.dump(first.to_str().unwrap());
+                reversed.dump(second.to_str().unwrap());
+            } else {
+                index.dump_sections(first.to_str().unwrap());
+                reversed.dump_sections(second.to_str().unwrap());
+            }
+            assert_eq!(fs::read(&first).unwrap(), fs::read(&second).unwrap());
+            fs::remove_file(first).unwrap();
+            fs::remove_file(second).unwrap();
+        }
+    }
+
+    #[test]
+    fn dump_and_restore_pretty() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest_map(vec![("doc1".to_string(), "word1".to_string()), ("doc2".to_string(), "word2".to_string())]);
+        let mut dest_file = std::env::temp_dir();
+        dest_file.push("bloom_dump_pretty.json");
+        index.dump_pretty(dest_file.to_str().unwrap());
+        let dumped = fs::read_to_string(&dest_file).unwrap();
+        assert_eq!(2, dumped.lines().filter(|line| line.starts_with("    \"doc1\": {")).count());
+        let restored = FsIndex::restore(dest_file.to_str().unwrap());
+        assert_eq!(vec!["doc2"], restored.search("word2").unwrap());
+        fs::remove_file(dest_file).unwrap();
+    }
+
+    #[test]
+    fn query_within_timeout() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest_map((0..5).map(|i| (format!("doc{}", i), "word1".to_string())));
+        let hits = index.query_within(&Query::term("word1"), Duration::from_secs(60));
+        assert_eq!(5, hits.keys.len());
+        assert!(!hits.truncated);
+        let hits = index.query_within(&Query::term("word1"), Duration::from_secs(0));
+        assert!(hits.keys.is_empty());
+        assert!(hits.truncated);
+    }
+
+    #[test]
+    fn cancel_ingestion() {
+        let files = MemoryFileSystem::default();
+        files.insert("/foo/a.txt", "word1");
+        files.insert("/foo/b.txt", "word2");
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut index = FsIndex::new(0.01);
+        index.set_file_system(files);
+        index.set_cancel_flag(cancel.clone());
+        let paths = vec!["/foo/a.txt", "/foo/b.txt"].into_iter().enumerate()
+            .inspect(|(position, _)| cancel.store(*position == 1, Ordering::Relaxed))
+            .map(|(_, path)| path);
+        let report = index.ingest_files(paths);
+        assert_eq!(1, report.indexed);
+        assert!(report.cancelled);
+        assert_eq!(vec!["/foo/a.txt"], index.keys().collect::<Vec<_>>());
+        cancel.store(false, Ordering::Relaxed);
+        let report = index.ingest("/foo");
+        assert_eq!(1, report.indexed);
+        assert!(!report.cancelled);
+    }
+
+    #[test]
+    fn observe_ingestion_progress() {
+        struct Recorder(Arc<std::sync::Mutex<Vec<String>>>);
+        impl ProgressObserver for Recorder {
+            fn on_file_start(&self, path: &Path) {
+                self.0.lock().unwrap().push(format!("start {}", path.display()));
+            }
+            fn on_file_done(&self, path: &Path, outcome: Outcome, bytes: u64) {
+                self.0.lock().unwrap().push(format!("done {} {:?} {}", path.display(), outcome, bytes));
+            }
+            fn on_skip(&self, path: &Path, reason: &str) {
+                self.0.lock().unwrap().push(format!("skip {} {}", path.display(), reason));
+            }
+            fn on_finish(&self, report: &IngestReport) {
+                self.0.lock().unwrap().push(format!("finish {}", report.indexed));
+            }
+        }
+        let files = MemoryFileSystem::default();
+        files.insert("/foo/a.txt", "word1");
+        files.insert("/foo/b.bin", b"\x00\xff".to_vec());
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut index = FsIndex::new(0.01);
+        index.set_file_system(files);
+        index.set_progress_observer(Recorder(events.clone()));
+        index.ingest("/foo");
+        let expected = vec!["start /foo/a.txt", "done /foo/a.txt Ingested 5", "start /foo/b.bin", "skip /foo/b.bin Error source must be an UTF-8 text file", "finish 1"];
+        assert_eq!(expected, *events.lock().unwrap());
+    }
+
+    #[test]
+    fn ingest_hook_per_document() {
+        let files = MemoryFileSystem::default();
+        files.insert("/foo/a.txt", "word1 word2, word3");
+        files.insert("/foo/b.bin", b"\x00\xff".to_vec());
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = calls.clone();
+        let mut index = FsIndex::new(0.01);
+        index.set_file_system(files);
+        index.set_ingest_hook(move |key, tokens, outcome| recorded.lock().unwrap().push((key.to_string(), tokens, outcome)));
+        index.ingest("/foo");
+        index.ingest("/foo/a.txt");
+        let expected = vec![
+            ("/foo/a.txt".to_string(), 3, Outcome::Ingested),
+            ("/foo/b.bin".to_string(), 0, Outcome::Skipped),
+            ("/foo/a.txt".to_string(), 0, Outcome::Unchanged)
+        ];
+        assert_eq!(expected, *calls.lock().unwrap());
+    }
+
+    #[test]
+    fn tag_documents() {
+        let files = MemoryFileSystem::default();
+        files.insert("/foo/a.md", "---\ntitle: \"Home page\"\nproject: blog\nauthors:\n  - me\n---\nword1 word2");
+        files.insert("/foo/b.txt", "word1");
+        let mut index = FsIndex::new(0.01);
+        index.set_file_system(files);
+        index.set_tag("project", "web");
+        index.ingest("/foo");
+        let tags: Vec<(&str, &str)> = index.document("/foo/a.md").unwrap().tags.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+        assert_eq!(vec![("project", "blog"), ("title", "Home page")], tags);
+        assert!(index.document("/foo/b.txt").unwrap().has_tag("project", "web"));
+        let dump_file = std::env::temp_dir().join("bloom_tags.json");
+        index.dump(dump_file.to_str().unwrap());
+        let restored = FsIndex::restore(dump_file.to_str().unwrap());
+        let query = Query::term("word1");
+        let hits: Vec<&String> = restored.query_where(&query, |key| restored.document(key).unwrap().has_tag("project", "web")).collect();
+        assert_eq!(vec!["/foo/b.txt"], hits);
+    }
+
+    #[test]
+    fn count_document_tokens() {
+        let files = MemoryFileSystem::default();
+        files.insert("/foo/a.txt", "word1 word2, word1");
+        let mut index = FsIndex::new(0.01);
+        index.set_file_system(files);
+        index.ingest("/foo");
+        index.ingest("/foo/a.txt");
+        let document = index.document("/foo/a.txt").unwrap();
+        assert_eq!((Some(3), Some(2)), (document.tokens, document.unique_tokens));
+        index.append("/foo/a.txt", "word3");
+        index.append("app.log", "word1 word2");
+        let document = index.document("/foo/a.txt").unwrap();
+        assert_eq!((Some(4), Some(3)), (document.tokens, document.unique_tokens));
+        let stats = index.stats();
+        assert_eq!((6, 5), (stats.tokens, stats.unique_tokens));
+    }
+
+    #[test]
+    fn compact_oversized_filters() {
+        let dir = std::env::temp_dir().join("bloom_compact");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+        let words: Vec<String> = (0..50).map(|i| format!("word{}", i)).collect();
+        fs::write(dir.join("a.txt"), words.join(" ")).unwrap();
+        let mut index = FsIndex::new(0.000001);
+        index.ingest(dir.to_str().unwrap());
+        index.ingest_map(vec![("doc".to_string(), words.join(" "))]);
+        let dump = dir.join("dump.json");
+        index.dump(dump.to_str().unwrap());
+        // The filters are sized for an error rate much lower than the one of the index
+        let mut serialized: serde_json::Value = serde_json::from_str(&fs::read_to_string(&dump).unwrap()).unwrap();
+        serialized["error_rate"] = serde_json::json!(0.1);
+        fs::write(&dump, serialized.to_string()).unwrap();
+        let mut restored = FsIndex::restore(dump.to_str().unwrap());
+        let compaction = restored.compact();
+        let key = dir.join("a.txt").to_str().unwrap().to_string();
+        assert_eq!(vec![key.clone()], compaction.rebuilt);
+        assert_eq!(vec!["doc"], compaction.skipped);
+        assert!(compaction.bits_after * 2 < compaction.bits_before);
+        assert_eq!(vec![key.as_str(), "doc"], restored.search("word7 word42").unwrap());
+        assert!(restored.compact().rebuilt.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rename_keeps_documents_keyed_otherwise() {
+        let from = std::env::temp_dir().join("bloom_rename_prefixed.txt");
+        let to = std::env::temp_dir().join("bloom_rename_prefixed_to.txt");
+        fs::write(&from, "word1 word2").unwrap();
+        let mut index = FsIndex::new(0.01);
+        index.set_key_prefix("projA:");
+        index.ingest(from.to_str().unwrap());
+        fs::remove_file(&from).unwrap();
+        index.set_key_prefix("projB:");
+        assert!(index.rename(from.to_str().unwrap(), to.to_str().unwrap()).is_empty());
+        let key = format!("projA:{}", from.to_str().unwrap());
+        assert_eq!(vec![key.as_str()], index.search("word1").unwrap());
+        assert_eq!(Some(from.clone()), index.document(&key).unwrap().path);
+    }
+
+    #[test]
+    fn ingest_hook_for_tokens_and_maps() {
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = calls.clone();
+        let mut index = FsIndex::new(0.01);
+        index.set_ingest_hook(move |key, tokens, outcome| recorded.lock().unwrap().push((key.to_string(), tokens, outcome)));
+        index.ingest_tokens("doc-1", vec!["stemmed", "token", "token"].into_iter());
+        index.ingest_map(vec![("doc-2".to_string(), "word1 word2".to_string())]);
+        index.append("doc-2", "word3");
+        let expected = vec![
+            ("doc-1".to_string(), 3, Outcome::Ingested),
+            ("doc-2".to_string(), 2, Outcome::Ingested),
+            ("doc-2".to_string(), 1, Outcome::Ingested)
+        ];
+        assert_eq!(expected, *calls.lock().unwrap());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn remove_deleted_file() {
+        let source = std::env::temp_dir().join("bloom_remove");
+        let _ = fs::remove_dir_all(&source);
+        fs::create_dir(&source).unwrap();
+        fs::write(source.join("original.txt"), "word1 word2").unwrap();
+        fs::hard_link(source.join("original.txt"), source.join("link.txt")).unwrap();
+        fs::write(source.join("other.txt"), "word1 word3").unwrap();
+        let mut index = FsIndex::new(0.01);
+        index.ingest(source.to_str().unwrap());
+        let other = source.join("other.txt").to_str().unwrap().to_string();
+        let key = index.keys().find(|key| *key != other).unwrap().to_string();
+        let alias = index.document(&key).unwrap().aliases[0].clone();
+        assert_eq!(vec![key.clone()], index.remove(&key));
+        assert!(index.document(&key).is_none());
+        assert!(index.resolve(&alias).is_none());
+        assert_eq!(vec![other.as_str()], index.search("word1").unwrap());
+        assert!(index.search("word2").unwrap().is_empty());
+        assert!(index.remove(&key).is_empty());
+        fs::remove_dir_all(&source).unwrap();
     }
-}
 
+    #[test]
+    fn hash_function_of_bloom_filters() {
+        let dump = std::env::temp_dir().join("bloom_dump_bloom_siphash.json");
+        let mut index = FsIndex::new(0.01);
+        index.set_backend(Backend::Bloom);
+        index.set_hash_function(HashFunction::SipHash { key: [1, 2] });
+        index.ingest("./test/data/simple_directory");
+        index.dump(dump.to_str().unwrap());
+        let mut restored = FsIndex::restore(dump.to_str().unwrap());
+        assert_eq!(vec!["./test/data/simple_directory/file1.txt"], restored.search("word1").unwrap());
+        restored.set_hash_function(HashFunction::Fnv);
+        restored.ingest("./test/data/simple_content.txt");
+        assert_eq!(vec!["./test/data/simple_content.txt", "./test/data/simple_directory/file1.txt"], restored.search("word1").unwrap());
+        fs::remove_file(&dump).unwrap();
+    }
+
+    #[test]
+    fn map_prefixes_onto_same_key() {
+        let files = MemoryFileSystem::default();
+        files.insert("/build/a/main.rs", "word1");
+        files.insert("/build/b/main.rs", "word2");
+        files.insert("/build/b/lib.rs", "word3");
+        let mut index = FsIndex::new(0.01);
+        index.set_file_system(files);
+        index.ingest_recursive("/build");
+        assert_eq!(Ok(1), index.map_prefix("/build/a", "/src"));
+        let collision = index.map_prefix("/build/b", "/src").unwrap_err();
+        assert_eq!(&["/src/main.rs".to_string()], collision.keys());
+        assert!(index.document("/build/b/main.rs").is_some());
+        assert!(index.document("/build/b/lib.rs").is_some());
+        assert_eq!(vec!["/src/main.rs"], index.search("word1").unwrap());
+    }
+
+    #[test]
+    fn split_again_when_settings_change() {
+        let files = MemoryFileSystem::default();
+        files.insert("/foo/log.txt", "word1 word2 word3 word4");
+        let mut index = FsIndex::new(0.01);
+        index.set_file_system(files);
+        index.set_chunk_size(12);
+        assert_eq!(1, index.ingest("/foo/log.txt").indexed);
+        assert_eq!(1, index.ingest("/foo/log.txt").unchanged);
+        assert_eq!(vec!["/foo/log.txt#chunk1"], index.search("word4").unwrap());
+        index.set_chunk_size(64);
+        assert_eq!(1, index.ingest("/foo/log.txt").indexed);
+        assert_eq!(vec!["/foo/log.txt"], index.search("word4").unwrap());
+        index.set_paragraphs(true);
+        assert_eq!(1, index.ingest("/foo/log.txt").indexed);
+        assert_eq!(vec!["/foo/log.txt¶0"], index.search("word4").unwrap());
+    }
+
+    #[test]
+    fn diff_of_indexes() {
+        let files = MemoryFileSystem::default();
+        files.insert("/foo/a.txt", "word1");
+        files.insert("/foo/b.txt", "word2");
+        files.insert("/foo/c.txt", "word3");
+        let mut before = FsIndex::new(0.01);
+        before.set_file_system(files.clone());
+        before.ingest_recursive("/foo");
+        files.remove("/foo/a.txt");
+        files.insert("/foo/b.txt", "word4");
+        files.insert("/foo/d.txt", "word5");
+        let mut after = FsIndex::new(0.01);
+        after.set_file_system(files);
+        after.ingest_recursive("/foo");
+        let changes = before.diff(&after);
+        assert_eq!(vec!["/foo/d.txt"], changes.added);
+        assert_eq!(vec!["/foo/a.txt"], changes.removed);
+        assert_eq!(vec!["/foo/b.txt"], changes.modified);
+        assert_eq!(Changes::default(), after.diff(&after));
+    }
+}
@@ -0,0 +1,173 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::Serialize;
+use crate::lock::{read_locked, write_locked};
+
+/// A copy of a dump file taken at some point in time, optionally labelled.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Generation {
+    /// Seconds since the Unix epoch when the copy was taken.
+    pub timestamp: u64,
+    pub label: Option<String>,
+    /// Path of the copy, which can be restored like any dump.
+    pub path: PathBuf
+}
+
+impl Generation {
+    /// The label of the generation, or its timestamp when it has none.
+    pub fn name(&self) -> String {
+        match &self.label {
+            Some(label) => label.clone(),
+            None => self.timestamp.to_string()
+        }
+    }
+
+    fn from_path(path: PathBuf) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?.to_string();
+        // The lock files of the generations are not generations
+        if file_name.ends_with(".lock") {
+            return None;
+        }
+        let (timestamp, label) = match file_name.split_once('-') {
+            Some((timestamp, label)) => (timestamp, Some(label.to_string())),
+            None => (file_name.as_str(), None)
+        };
+        Some(Generation { timestamp: timestamp.parse().ok()?, label, path })
+    }
+}
+
+/// The generations of a dump file, kept in a `<dump>.snapshots` directory next to it.
+///
+/// # Example
+///
+/// ```
+/// # use cli_bloom::Generations;
+/// # fn snapshot_index()  {
+/// let generations = Generations::of("/foo/dump.json");
+/// generations.save(Some("monday"));
+/// for generation in generations.list() {
+///     println!("{} {}", generation.name(), generation.path.display());
+/// }
+/// # }
+/// ```
+pub struct Generations {
+    dump: PathBuf,
+    directory: PathBuf
+}
+
+impl Generations {
+    /// The generations of the dump file at `dump`.
+    pub fn of(dump: &str) -> Self {
+        Generations {
+            dump: PathBuf::from(dump),
+            directory: PathBuf::from(format!("{}.snapshots", dump))
+        }
+    }
+
+    /// Copy the current dump file as a new generation, labelled with `label` if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the label is empty or contains a path separator, if a generation already has the same timestamp and label or if the dump file cannot be copied.
+    pub fn save(&self, label: Option<&str>) -> Generation {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0);
+        let file_name = match label {
+            Some(label) if label.is_empty() || label.contains(|c| c == '/' || c == '\\') => panic!("Invalid snapshot label {}", label),
+            Some(label) => format!("{}-{}", timestamp, label),
+            None => timestamp.to_string()
+        };
+        let path = self.directory.join(file_name);
+        if path.exists() {
+            panic!("Snapshot {} already exists", path.display());
+        }
+        fs::create_dir_all(&self.directory).expect(format!("Unable to create snapshots directory {}", self.directory.display()).as_str());
+        copy_locked(&self.dump, &path);
+        Generation { timestamp, label: label.map(String::from), path }
+    }
+
+    /// All the generations, from the oldest to the newest.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the snapshots directory exists but cannot be read.
+    pub fn list(&self) -> Vec<Generation> {
+        if !self.directory.is_dir() {
+            return Vec::new();
+        }
+        let entries = fs::read_dir(&self.directory).expect(format!("Unable to read snapshots directory {}", self.directory.display()).as_str());
+        let mut generations: Vec<Generation> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| Generation::from_path(entry.path()))
+            .collect();
+        generations.sort_by(|left, right| (left.timestamp, &left.label).cmp(&(right.timestamp, &right.label)));
+        generations
+    }
+
+    /// The newest generation whose label or timestamp is `name`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the snapshots directory exists but cannot be read.
+    pub fn find(&self, name: &str) -> Option<Generation> {
+        self.list().into_iter().rev().find(|generation| generation.label.as_deref() == Some(name) || generation.timestamp.to_string() == name)
+    }
+
+    /// Replace the dump file by the generation named `name`, see [`Generations::find`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no such generation or if it cannot be copied.
+    pub fn checkout(&self, name: &str) -> Generation {
+        let generation = self.find(name).unwrap_or_else(|| panic!("Unknown snapshot {}", name));
+        copy_locked(&generation.path, &self.dump);
+        generation
+    }
+
+    /// Delete the generation named `name`, see [`Generations::find`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no such generation or if it cannot be deleted.
+    pub fn delete(&self, name: &str) -> Generation {
+        let generation = self.find(name).unwrap_or_else(|| panic!("Unknown snapshot {}", name));
+        fs::remove_file(&generation.path).expect(format!("Unable to delete snapshot {}", generation.path.display()).as_str());
+        generation
+    }
+}
+
+// Copy with the locks of dump files, so that a dump being written is never copied halfway
+fn copy_locked(from: &Path, to: &Path) {
+    let content = read_locked(from).expect(format!("Unable to read dump file {}", from.display()).as_str());
+    write_locked(to, |file| file.write_all(content.as_bytes())).expect(format!("Unable to write file {}", to.display()).as_str());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_checkout_and_delete_generations() {
+        let mut dump = std::env::temp_dir();
+        dump.push("bloom_generations_dump.json");
+        let dump_path = dump.to_str().unwrap();
+        let generations = Generations::of(dump_path);
+        let _ = fs::remove_dir_all(format!("{}.snapshots", dump_path));
+        fs::write(&dump, "first").unwrap();
+        let first = generations.save(Some("first"));
+        fs::write(&dump, "second").unwrap();
+        let second = generations.save(None);
+        let listed = generations.list();
+        assert_eq!(2, listed.len());
+        assert!(listed.contains(&first) && listed.contains(&second));
+        assert_eq!(Some(first.clone()), generations.find("first"));
+        assert_eq!(first, generations.checkout("first"));
+        assert_eq!("first", fs::read_to_string(&dump).unwrap());
+        generations.delete("first");
+        assert_eq!(vec![second], generations.list());
+        assert_eq!(None, generations.find("first"));
+        fs::remove_dir_all(format!("{}.snapshots", dump_path)).unwrap();
+        fs::remove_file(&dump).unwrap();
+    }
+}
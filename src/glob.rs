@@ -0,0 +1,83 @@
+use std::fmt;
+
+/// A pattern on document keys, e.g. `src/**/*.rs`.
+///
+/// `*` matches any characters but `/`, `**` any characters including `/` (so `src/**/*.rs` also matches `src/main.rs`) and `?` a single character but `/`.
+/// A pattern starting with `/` matches whole keys, other patterns match the end of keys from any directory, e.g. `src/*.rs` matches `/foo/src/main.rs`.
+///
+/// # Example
+///
+/// ```
+/// # use cli_bloom::Glob;
+/// let glob = Glob::new("src/**/*.rs");
+/// assert!(glob.matches("/foo/src/bin/main.rs"));
+/// assert!(!glob.matches("/foo/tests/main.rs"));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Glob {
+    pattern: Vec<char>
+}
+
+impl Glob {
+    /// Constructs a `Glob` from its `pattern`.
+    pub fn new(pattern: &str) -> Self {
+        Glob {
+            pattern: pattern.chars().collect()
+        }
+    }
+
+    /// Return whether `key` matches the pattern.
+    pub fn matches(&self, key: &str) -> bool {
+        let key: Vec<char> = key.chars().collect();
+        if self.pattern.first() == Some(&'/') {
+            return matches_from(&self.pattern, &key);
+        }
+        (0..key.len()).filter(|&start| start == 0 || key[start - 1] == '/')
+            .any(|start| matches_from(&self.pattern, &key[start..]))
+    }
+}
+
+impl fmt::Display for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.pattern.iter().collect::<String>())
+    }
+}
+
+fn matches_from(pattern: &[char], key: &[char]) -> bool {
+    match pattern {
+        [] => key.is_empty(),
+        ['*', '*', rest @ ..] => {
+            // `a/**/b` also matches `a/b`
+            if let ['/', after @ ..] = rest {
+                if matches_from(after, key) {
+                    return true;
+                }
+            }
+            (0..=key.len()).any(|start| matches_from(rest, &key[start..]))
+        },
+        ['*', rest @ ..] => {
+            let segment = key.iter().position(|&c| c == '/').unwrap_or(key.len());
+            (0..=segment).any(|start| matches_from(rest, &key[start..]))
+        },
+        ['?', rest @ ..] => matches!(key.first(), Some(&c) if c != '/') && matches_from(rest, &key[1..]),
+        [c, rest @ ..] => key.first() == Some(c) && matches_from(rest, &key[1..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_wildcards() {
+        let glob = Glob::new("src/**/*.rs");
+        assert!(glob.matches("src/main.rs"));
+        assert!(glob.matches("/foo/src/bin/cli/main.rs"));
+        assert!(!glob.matches("/foo/src/main.rs.bak"));
+        assert!(!glob.matches("/foo/mysrc/main.rs"));
+        assert!(Glob::new("*.t?t").matches("/foo/bar.txt"));
+        assert!(!Glob::new("/foo/*.txt").matches("/foo/bar/baz.txt"));
+        assert!(!Glob::new("/foo/*.txt").matches("/bar/foo/baz.txt"));
+        assert!(Glob::new("/foo/**").matches("/foo/bar/baz.txt"));
+    }
+}
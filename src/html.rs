@@ -0,0 +1,111 @@
+use std::borrow::Cow;
+
+// Text and link targets of an HTML page, without its markup, comments, scripts and styles
+pub(crate) fn html_text(html: &str) -> (String, Vec<String>) {
+    let mut text = String::with_capacity(html.len());
+    let mut links = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&decode_entities(&rest[..start]));
+        text.push(' ');
+        rest = &rest[start..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map(|end| &comment[end + 3..]).unwrap_or("");
+            continue;
+        }
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None => return (text, links)
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        let name = tag.split(|c: char| !c.is_ascii_alphanumeric()).next().unwrap_or_default().to_ascii_lowercase();
+        if name == "script" || name == "style" {
+            // ASCII lowercasing keeps the byte positions
+            rest = rest.to_ascii_lowercase().find(&format!("</{}", name)).map(|end| &rest[end..]).unwrap_or("");
+        } else if name == "a" {
+            if let Some(href) = attribute(tag, "href") {
+                links.push(decode_entities(href).into_owned());
+            }
+        }
+    }
+    text.push_str(&decode_entities(rest));
+    (text, links)
+}
+
+// Value of the attribute `name` of a tag, quoted or not
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let lowercase = tag.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(position) = lowercase[from..].find(name) {
+        let start = from + position;
+        from = start + name.len();
+        if !lowercase[..start].ends_with(char::is_whitespace) {
+            continue;
+        }
+        let value = match tag[from..].trim_start().strip_prefix('=') {
+            Some(value) => value.trim_start(),
+            None => continue
+        };
+        return match value.chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => value[1..].split(quote).next(),
+            _ => value.split(char::is_whitespace).next()
+        };
+    }
+    None
+}
+
+// Replace the character references of HTML text, e.g. &amp; or &#233;
+fn decode_entities(text: &str) -> Cow<'_, str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let reference = rest[1..].find(';').filter(|&end| end <= 10).map(|end| &rest[1..end + 1]);
+        let character = reference.and_then(|reference| match reference {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => match reference.strip_prefix('#') {
+                Some(hexadecimal) if hexadecimal.starts_with(['x', 'X']) => u32::from_str_radix(&hexadecimal[1..], 16).ok().and_then(char::from_u32),
+                Some(decimal) => decimal.parse().ok().and_then(char::from_u32),
+                None => None
+            }
+        });
+        match (reference, character) {
+            (Some(reference), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[reference.len() + 2..];
+            },
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    Cow::Owned(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_html_text_and_links() {
+        let html = "<html><head><title>Word1</title><style>p { color: red }</style></head>\
+            <body><!-- word2 --><p>word3&amp;word4 caf&#233;</p><SCRIPT>var word5;</SCRIPT>\
+            <a class=\"nav\" href=\"/docs/page.html#top\">Next</a> <a href=other.html>word6</a></body></html>";
+        let (text, links) = html_text(html);
+        let words: Vec<&str> = text.split_whitespace().collect();
+        assert_eq!(vec!["Word1", "word3&word4", "café", "Next", "word6"], words);
+        assert_eq!(vec!["/docs/page.html#top", "other.html"], links);
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use serde::{Serialize, Deserialize};
+use crate::fs_loader::FsIndex;
+
+const MANIFEST_FILE: &str = "indexes.json";
+
+/// A set of named `FsIndex`, dumped and restored together.
+///
+/// Each index is independent, e.g. one per project, and is selected by its name.
+///
+/// # Example
+///
+/// ```
+/// # use cli_bloom::{FsIndex, FsIndexSet};
+/// # fn search_indexes()  {
+/// let mut indexes = FsIndexSet::new();
+/// indexes.insert("projA", FsIndex::new(0.00001));
+/// indexes.get_mut("projA").unwrap().ingest("/foo/projA");
+/// indexes.dump("/foo/indexes");
+/// # }
+/// ```
+#[derive(Default)]
+pub struct FsIndexSet {
+    indexes: BTreeMap<String, FsIndex>
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    indexes: BTreeMap<String, String>
+}
+
+impl FsIndexSet {
+    /// Constructs a new, empty `FsIndexSet`.
+    pub fn new() -> Self {
+        FsIndexSet::default()
+    }
+
+    /// Add the `index` named `name`, returning the index previously named so if any.
+    pub fn insert(&mut self, name: &str, index: FsIndex) -> Option<FsIndex> {
+        self.indexes.insert(name.to_string(), index)
+    }
+
+    /// Remove the index `name` from the set and return it, if any.
+    pub fn remove(&mut self, name: &str) -> Option<FsIndex> {
+        self.indexes.remove(name)
+    }
+
+    /// Return the index `name`, if any.
+    pub fn get(&self, name: &str) -> Option<&FsIndex> {
+        self.indexes.get(name)
+    }
+
+    /// Return the index `name` to update it, if any.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut FsIndex> {
+        self.indexes.get_mut(name)
+    }
+
+    /// Return the names of the indexes, in ascending order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.indexes.keys().map(|name| name.as_str())
+    }
+
+    /// Return the indexes with their names, in ascending name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &FsIndex)> {
+        self.indexes.iter().map(|(name, index)| (name.as_str(), index))
+    }
+
+    /// Return the number of indexes.
+    pub fn len(&self) -> usize {
+        self.indexes.len()
+    }
+
+    /// Return `true` if the set has no index.
+    pub fn is_empty(&self) -> bool {
+        self.indexes.is_empty()
+    }
+
+    /// Search keywords in every index, tagging each hit with the name of its index.
+    ///
+    /// Hits are grouped by index, in ascending index name order.
+    /// Return `None` if nothing match.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `keywords` cannot be processed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::FsIndexSet;
+    /// # fn search_indexes()  {
+    /// let indexes = FsIndexSet::restore("/foo/indexes");
+    /// for (name, key) in indexes.search_all("content").unwrap_or_default() {
+    ///     println!("{}: {}", name, key);
+    /// }
+    /// # }
+    /// ```
+    pub fn search_all(&self, keywords: &str) -> Option<Vec<(&str, &String)>> {
+        let hits: Vec<(&str, &String)> = self.indexes.iter()
+            .filter_map(|(name, index)| index.search(keywords).map(|keys| (name, keys)))
+            .flat_map(|(name, keys)| keys.into_iter().map(move |key| (name.as_str(), key)))
+            .collect();
+        if hits.is_empty() {
+            None
+        } else {
+            Some(hits)
+        }
+    }
+
+    /// Dump every index in the `directory`, with a manifest naming them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if it is not possible to create the directory or to write the dumps.
+    pub fn dump(&self, directory: &str) {
+        let directory = Path::new(directory);
+        fs::create_dir_all(directory).expect(format!("Impossible to create dump directory {}", directory.display()).as_str());
+        let mut manifest = Manifest {
+            indexes: BTreeMap::new()
+        };
+        for (position, (name, index)) in self.indexes.iter().enumerate() {
+            let file_name = format!("index-{}.json", position);
+            index.dump(directory.join(&file_name).to_str().unwrap());
+            manifest.indexes.insert(name.clone(), file_name);
+        }
+        let serialized = serde_json::to_string(&manifest).expect("Impossible to serialize indexes");
+        fs::write(directory.join(MANIFEST_FILE), serialized).expect("Impossible to write indexes manifest");
+    }
+
+    /// Restore a `FsIndexSet` from a directory written by [`FsIndexSet::dump`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the directory does not contain a valid dump.
+    pub fn restore(directory: &str) -> Self {
+        let directory = Path::new(directory);
+        let manifest_path = directory.join(MANIFEST_FILE);
+        let serialized = fs::read_to_string(&manifest_path).expect(format!("Unable to read indexes manifest {}", manifest_path.display()).as_str());
+        let manifest: Manifest = serde_json::from_str(&serialized).expect(format!("Invalid indexes manifest {}", manifest_path.display()).as_str());
+        let indexes = manifest.indexes.into_iter()
+            .map(|(name, file_name)| (name, FsIndex::restore(directory.join(file_name).to_str().unwrap())))
+            .collect();
+        FsIndexSet {
+            indexes
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dump_and_restore_named_indexes() {
+        let mut indexes = FsIndexSet::new();
+        let mut content = FsIndex::new(0.01);
+        content.ingest("./test/data/simple_content.txt");
+        let mut directory = FsIndex::new(0.01);
+        directory.ingest("./test/data/simple_directory");
+        indexes.insert("content", content);
+        indexes.insert("directory", directory);
+        let mut dump = std::env::temp_dir();
+        dump.push("bloom_index_set");
+        indexes.dump(dump.to_str().unwrap());
+        let restored = FsIndexSet::restore(dump.to_str().unwrap());
+        assert_eq!(vec!["content", "directory"], restored.names().collect::<Vec<&str>>());
+        assert_eq!(vec!["./test/data/simple_directory/file1.txt"], restored.get("directory").unwrap().search("word1").unwrap());
+        assert!(restored.get("unknown").is_none());
+        fs::remove_dir_all(dump).unwrap();
+    }
+
+    #[test]
+    fn search_all_with_provenance() {
+        let mut indexes = FsIndexSet::new();
+        let mut content = FsIndex::new(0.01);
+        content.ingest("./test/data/simple_content.txt");
+        let mut directory = FsIndex::new(0.01);
+        directory.ingest("./test/data/simple_directory");
+        indexes.insert("content", content);
+        indexes.insert("directory", directory);
+        let content_key = "./test/data/simple_content.txt".to_string();
+        let directory_key = "./test/data/simple_directory/file1.txt".to_string();
+        assert_eq!(Some(vec![("content", &content_key), ("directory", &directory_key)]), indexes.search_all("word1"));
+        assert_eq!(None, indexes.search_all("unknown"));
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use serde::{Serialize, Deserialize};
+use serde::de::IgnoredAny;
+use crate::analyzer::Analyzer;
+use crate::fs_loader::legacy_version;
+use crate::lock::read_locked;
+use crate::membership::{Backend, HashFunction};
+use crate::sections::{SECTIONS_MAGIC, read_sections};
+
+/// The header of a dump file and a sample of its keys, read without building any filter.
+///
+/// A quick way to identify a dump file, e.g. with `cli-bloom inspect`.
+///
+/// # Example
+///
+/// ```
+/// # use cli_bloom::DumpInfo;
+/// # fn inspect_dump()  {
+/// let info = DumpInfo::read("/foo/dump.json", 10);
+/// println!("{}", info);
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DumpInfo {
+    /// Size of the dump file, in bytes.
+    pub size: usize,
+    /// `true` for a dump in sections, see [`FsIndex::dump_sections`](crate::FsIndex::dump_sections).
+    pub sections: bool,
+    /// Format version, see [`FsIndex::dump_version`](crate::FsIndex::dump_version).
+    pub version: u32,
+    pub error_rate: f32,
+    pub backend: Backend,
+    pub hash_function: HashFunction,
+    pub analyzer: Analyzer,
+    /// Number of documents.
+    pub documents: usize,
+    /// The first keys of the documents, in ascending order.
+    pub keys: Vec<String>,
+    /// Tokens of the documents counted at ingestion, see [`Document::tokens`](crate::Document::tokens).
+    pub tokens: usize,
+    /// The documents with the most distinct tokens, whose filters are the largest, in descending order.
+    pub largest: Vec<DocumentTokens>
+}
+
+/// The tokens of a document counted at ingestion, see [`DumpInfo::largest`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DocumentTokens {
+    pub key: String,
+    pub tokens: usize,
+    pub unique_tokens: usize
+}
+
+// Everything but the filters, whose content is skipped
+#[derive(Deserialize)]
+struct Header {
+    #[serde(default = "legacy_version")]
+    version: u32,
+    error_rate: f32,
+    #[serde(default)]
+    backend: Backend,
+    #[serde(default)]
+    hash_function: HashFunction,
+    #[serde(default)]
+    analyzer: Analyzer,
+    #[serde(default)]
+    bloom_filters: BTreeMap<String, IgnoredAny>,
+    documents: Option<BTreeMap<String, Counts>>
+}
+
+// The token counts of a document, its other metadata is skipped
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Counts {
+    tokens: Option<usize>,
+    unique_tokens: Option<usize>
+}
+
+impl DumpInfo {
+    /// Read the header of the dump at `path`, with at most `sample` keys and `sample` largest documents.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file cannot be read or is not a dump.
+    pub fn read(path: &str, sample: usize) -> Self {
+        let serialized = read_locked(Path::new(path)).expect(format!("Unable to read dump file {}", &path).as_str());
+        let sections = serialized.starts_with(SECTIONS_MAGIC);
+        let header = if sections {
+            // Documents are in the first section, the filters in the others
+            read_sections(&serialized).and_then(|sections| serde_json::from_str::<Header>(sections.first().copied().unwrap_or_default()))
+        } else {
+            serde_json::from_str::<Header>(&serialized)
+        };
+        let header = header.expect(format!("Invalid dump file {}", &path).as_str());
+        // Version 1 dumps of files only have no documents metadata
+        let documents = header.documents.unwrap_or_else(|| header.bloom_filters.into_keys().map(|key| (key, Counts::default())).collect());
+        let tokens = documents.values().filter_map(|counts| counts.tokens).sum();
+        let mut largest: Vec<DocumentTokens> = documents.iter()
+            .filter_map(|(key, counts)| Some(DocumentTokens { key: key.clone(), tokens: counts.tokens?, unique_tokens: counts.unique_tokens? }))
+            .collect();
+        largest.sort_by(|first, second| second.unique_tokens.cmp(&first.unique_tokens).then_with(|| first.key.cmp(&second.key)));
+        largest.truncate(sample);
+        DumpInfo {
+            size: serialized.len(),
+            sections,
+            version: header.version,
+            error_rate: header.error_rate,
+            backend: header.backend,
+            hash_function: header.hash_function,
+            analyzer: header.analyzer,
+            documents: documents.len(),
+            keys: documents.into_keys().take(sample).collect(),
+            tokens,
+            largest
+        }
+    }
+}
+
+/// The fields of the dump, one per line.
+impl fmt::Display for DumpInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Format      {}version {} ({} bytes)", if self.sections { "sections, " } else { "" }, self.version, self.size)?;
+        writeln!(f, "Error rate  {}", self.error_rate)?;
+        writeln!(f, "Backend     {}", self.backend)?;
+        let hash_function = match self.hash_function {
+            HashFunction::Fnv => "fnv",
+            HashFunction::XxHash => "xxhash",
+            HashFunction::SipHash { .. } => "siphash (keyed)"
+        };
+        writeln!(f, "Hash        {}", hash_function)?;
+        let mut analysis = Vec::new();
+        if self.analyzer.split_words {
+            analysis.push("split words".to_string());
+        }
+        if self.analyzer.fold_diacritics {
+            analysis.push("fold diacritics".to_string());
+        }
+        if self.analyzer.stemming {
+            analysis.push("stemming".to_string());
+        }
+        if !self.analyzer.stop_words.is_empty() {
+            analysis.push(format!("{} stop words", self.analyzer.stop_words.len()));
+        }
+        writeln!(f, "Analyzer    {}", if analysis.is_empty() { "default".to_string() } else { analysis.join(", ") })?;
+        write!(f, "Documents   {}", self.documents)?;
+        if !self.largest.is_empty() {
+            write!(f, "\nTokens      {}", self.tokens)?;
+        }
+        for (position, document) in self.largest.iter().enumerate() {
+            write!(f, "\n{}{} ({} tokens, {} distinct)", if position == 0 { "Largest     " } else { "            " }, document.key, document.tokens, document.unique_tokens)?;
+        }
+        for (position, key) in self.keys.iter().enumerate() {
+            write!(f, "\n{}{}", if position == 0 { "Keys        " } else { "            " }, key)?;
+        }
+        if self.documents > self.keys.len() && !self.keys.is_empty() {
+            write!(f, "\n            ... {} more", self.documents - self.keys.len())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::*;
+    use crate::FsIndex;
+
+    #[test]
+    fn inspect_legacy_dump() {
+        let info = DumpInfo::read("./test/data/simple_dump.json", 10);
+        assert_eq!(1, info.version);
+        assert!(!info.sections);
+        assert_eq!(0.1, info.error_rate);
+        assert_eq!(1, info.documents);
+        assert_eq!(vec!["./test/data/simple_directory/file1.txt"], info.keys);
+    }
+
+    #[test]
+    fn inspect_sections_dump() {
+        let mut dump = std::env::temp_dir();
+        dump.push("bloom_dump_inspect.sections");
+        let mut index = FsIndex::new(0.01);
+        index.ingest("./test/data/simple_directory");
+        index.dump_sections(dump.to_str().unwrap());
+        let info = DumpInfo::read(dump.to_str().unwrap(), 1);
+        assert!(info.sections);
+        assert_eq!(2, info.documents);
+        assert_eq!(vec!["./test/data/simple_directory/file1.txt"], info.keys);
+        assert!(info.to_string().ends_with("Keys        ./test/data/simple_directory/file1.txt\n            ... 1 more"));
+        fs::remove_file(&dump).unwrap();
+    }
+
+    #[test]
+    fn inspect_largest_documents() {
+        let mut dump = std::env::temp_dir();
+        dump.push("bloom_dump_inspect_tokens.json");
+        let mut index = FsIndex::new(0.01);
+        index.ingest_map(vec![("a".to_string(), "word1 word1".to_string()), ("b".to_string(), "word1 word2 word3".to_string()), ("c".to_string(), "word1 word2".to_string())]);
+        index.dump(dump.to_str().unwrap());
+        let info = DumpInfo::read(dump.to_str().unwrap(), 2);
+        assert_eq!(7, info.tokens);
+        let largest: Vec<(&str, usize, usize)> = info.largest.iter().map(|document| (document.key.as_str(), document.tokens, document.unique_tokens)).collect();
+        assert_eq!(vec![("b", 3, 3), ("c", 2, 2)], largest);
+        assert!(info.to_string().contains("Largest     b (3 tokens, 3 distinct)\n            c (2 tokens, 2 distinct)\nKeys"));
+        fs::remove_file(&dump).unwrap();
+    }
+}
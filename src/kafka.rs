@@ -0,0 +1,127 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{BaseConsumer, CommitMode, Consumer};
+use rdkafka::message::{Headers, Message};
+use crate::fs_loader::FsIndex;
+use crate::stats::{IngestReport, Issue};
+use crate::store::IndexStore;
+
+/// A consumer of a Kafka topic, whose messages are ingested with [`FsIndex::consume`].
+///
+/// Offsets are only committed by [`TopicConsumer::commit`], so that messages consumed since the last dump of the index are consumed again after a crash.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use cli_bloom::{FsIndex, TopicConsumer};
+/// let consumer = TopicConsumer::connect("localhost:9092", "events", "cli-bloom").key_header("request-id");
+/// let mut fs_index = FsIndex::new(0.00001);
+/// loop {
+///     fs_index.consume(&consumer, Duration::from_secs(60));
+///     fs_index.dump("/foo/dump.json");
+///     consumer.commit();
+/// }
+/// ```
+pub struct TopicConsumer {
+    consumer: BaseConsumer,
+    topic: String,
+    key_header: Option<String>
+}
+
+impl TopicConsumer {
+    /// Constructs a consumer of `topic` on the comma separated `brokers`, in the consumer group `group`, starting at the earliest offset the first time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the consumer cannot be created or subscribed to `topic`.
+    pub fn connect(brokers: &str, topic: &str, group: &str) -> Self {
+        let consumer: BaseConsumer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("group.id", group)
+            .set("enable.auto.commit", "false")
+            .set("auto.offset.reset", "earliest")
+            .create()
+            .unwrap_or_else(|error| panic!("Unable to connect to {} : {}", brokers, error));
+        consumer.subscribe(&[topic]).unwrap_or_else(|error| panic!("Unable to subscribe to {} : {}", topic, error));
+        TopicConsumer { consumer, topic: topic.to_string(), key_header: None }
+    }
+
+    /// The same consumer, keying the messages by the value of the header `name` when they have it.
+    pub fn key_header(self, name: &str) -> Self {
+        TopicConsumer { key_header: Some(name.to_string()), ..self }
+    }
+
+    /// Commit the offsets of the messages consumed so far.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the offsets cannot be committed.
+    pub fn commit(&self) {
+        self.consumer.commit_consumer_state(CommitMode::Sync).expect("Unable to commit the offsets");
+    }
+}
+
+impl<S: IndexStore> FsIndex<S> {
+    /// Ingest the messages of the topic of `consumer` for `duration`, each a document keyed `topic/partition/offset` or by its key header.
+    ///
+    /// Messages whose payload is not UTF-8 text are listed as skipped in the report, messages which cannot be received as failures, with their key as path.
+    /// The documents have no source file, so they are never pruned nor refreshed.
+    /// The consumption stops early, with a report flagged as cancelled, when the flag of [`FsIndex::set_cancel_flag`] is set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a message cannot be inserted in the index.
+    pub fn consume(&mut self, consumer: &TopicConsumer, duration: Duration) -> IngestReport {
+        let mut report = IngestReport::default();
+        let deadline = Instant::now() + duration;
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            if self.cancelled(&mut report) {
+                break;
+            }
+            // Polled by at most a second, so that a cancellation is noticed without a message
+            let message = match consumer.consumer.poll(remaining.min(Duration::from_secs(1))) {
+                Some(Ok(message)) => message,
+                Some(Err(error)) => {
+                    report.failed.push(Issue { path: PathBuf::from(&consumer.topic), reason: error.to_string() });
+                    continue;
+                },
+                None => continue
+            };
+            let header = consumer.key_header.as_deref().and_then(|name| {
+                message.headers().and_then(|headers| headers.iter().find(|header| header.key == name).and_then(|header| header.value))
+            });
+            let key = message_key(message.topic(), message.partition(), message.offset(), header);
+            match std::str::from_utf8(message.payload().unwrap_or_default()) {
+                Ok(text) => {
+                    self.insert_text(&key, text);
+                    report.indexed += 1;
+                    report.bytes += text.len() as u64;
+                },
+                Err(_) => report.skipped.push(Issue { path: PathBuf::from(key), reason: "not UTF-8 text".to_string() })
+            }
+        }
+        report
+    }
+}
+
+// Key of a message, the value of its key header if it is UTF-8, else its position in the topic
+fn message_key(topic: &str, partition: i32, offset: i64, header: Option<&[u8]>) -> String {
+    match header.and_then(|header| std::str::from_utf8(header).ok()) {
+        Some(key) => key.to_string(),
+        None => format!("{}/{}/{}", topic, partition, offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_of_message() {
+        assert_eq!("events/2/1042", message_key("events", 2, 1042, None));
+        assert_eq!("req-7", message_key("events", 2, 1042, Some(b"req-7")));
+        assert_eq!("events/2/1042", message_key("events", 2, 1042, Some(b"\xff")));
+    }
+}
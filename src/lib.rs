@@ -1,8 +1,10 @@
 //! # cli-bloom
 //!
-//! The `cli-bloom` crate provide a convenient way to ingest files in an `index-bloom`.
+//! The `cli-bloom` crate provide a convenient way to ingest files in an index of bloom filters, with the layout of `index-bloom`.
 //!
-//! Ingest text from a file or from all files in a directory (not recursively). All files must contains only valid UTF-8 characters.
+//! Ingest text from a file, from the files of a directory ([`FsIndex::ingest`]) or from a whole directory tree ([`FsIndex::ingest_recursive`]).
+//! HTML files are ingested without their markup, and other kinds of documents through a [`ContentExtractor`]. Files of a directory which are not UTF-8 text are skipped.
+//! Documents can also be read from any [`DataSource`], e.g. compressed streams, archives or remote sources enabled by Cargo features.
 //! When ingestion is done, it is possible to dump the index content in JSON format and to restore it later.
 //!
 //! # Quick start
@@ -43,6 +45,168 @@
 //! ```
 
 mod fs_loader;
-pub use fs_loader::FsIndex;
+pub use fs_loader::{FsIndex, PrefixCollision};
+
+mod builder;
+pub use builder::FsIndexBuilder;
+
+mod shared;
+pub use shared::SharedFsIndex;
+
+mod snapshot;
+pub use snapshot::Snapshot;
+
+mod generations;
+pub use generations::{Generation, Generations};
+
+mod query;
+pub use query::{Query, ParseError, Sort};
+
+mod config;
+pub use config::Config;
+
+mod glob;
+pub use glob::Glob;
+
+#[cfg(feature = "tui")]
+mod tui;
+#[cfg(feature = "tui")]
+pub use tui::browse;
+
+#[cfg(feature = "parquet")]
+mod parquet_export;
+
+mod partition;
+pub use partition::PartitionedIndex;
+
+mod index_set;
+pub use index_set::FsIndexSet;
+
+mod filter;
+pub use filter::Filter;
+
+mod membership;
+pub use membership::{Backend, HashFunction};
+
+mod analyzer;
+pub use analyzer::Analyzer;
+
+mod tokenize;
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub use tokenize::distinct_token_hashes;
+
+#[cfg(feature = "xor")]
+mod xor;
+
+#[cfg(feature = "cuckoo")]
+mod cuckoo;
+
+#[cfg(feature = "counting")]
+mod counting;
+
+mod tuning;
+pub use tuning::auto_error_rate;
+
+mod schema;
+pub use schema::{DUMP_SCHEMA, validate_dump, validate_dump_file};
+
+mod inspect;
+pub use inspect::{DumpInfo, DocumentTokens};
+
+mod doctor;
+pub use doctor::{Finding, Problem};
+
+mod dups;
+pub use dups::Duplicates;
+
+mod file_system;
+pub use file_system::{FileSystem, FileMetadata, RealFileSystem, MemoryFileSystem};
+
+mod store;
+pub use store::{IndexStore, MemoryStore, DirectoryStore};
+
+mod tiered;
+pub use tiered::TieredStore;
+
+#[cfg(feature = "rocksdb")]
+mod rocksdb_store;
+#[cfg(feature = "rocksdb")]
+pub use rocksdb_store::RocksDbStore;
+
+mod document;
+pub use document::{Document, Staleness};
+
+mod stats;
+pub use stats::{Stats, Refresh, Changes, Compaction, IngestReport, Issue, Hits};
 
 mod errors;
+
+mod pretty;
+
+mod audit;
+pub use audit::Outcome;
+
+mod progress;
+pub use progress::ProgressObserver;
+
+mod extract;
+
+mod extractor;
+pub use extractor::{ContentExtractor, HtmlExtractor};
+#[cfg(feature = "ocr")]
+pub use extractor::OcrExtractor;
+
+#[cfg(feature = "ocr")]
+mod ocr;
+
+#[cfg(feature = "archive")]
+mod archive;
+
+#[cfg(feature = "compressed")]
+mod compression;
+#[cfg(feature = "compressed")]
+pub use compression::decompressing;
+
+#[cfg(feature = "crawl")]
+mod crawl;
+
+mod html;
+
+#[cfg(any(feature = "crawl", feature = "object-store"))]
+mod limiter;
+
+#[cfg(feature = "object-store")]
+mod remote;
+
+#[cfg(feature = "imap")]
+mod mail;
+#[cfg(feature = "imap")]
+pub use mail::ImapAccount;
+
+mod logs;
+
+#[cfg(feature = "postgres")]
+mod sql;
+
+#[cfg(feature = "kafka")]
+mod kafka;
+#[cfg(feature = "kafka")]
+pub use kafka::TopicConsumer;
+
+mod source;
+pub use source::{DataSource, DirectorySource, ReaderSource};
+#[cfg(feature = "archive")]
+pub use source::ArchiveSource;
+#[cfg(feature = "crawl")]
+pub use source::UrlSource;
+
+mod retry;
+pub use retry::RetryPolicy;
+
+mod politeness;
+pub use politeness::Politeness;
+
+mod sections;
+
+mod lock;
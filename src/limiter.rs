@@ -0,0 +1,42 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Spaces the requests by at least `interval`, whatever the thread making them
+pub(crate) struct Limiter {
+    interval: Duration,
+    next: Mutex<Option<Instant>>
+}
+
+impl Limiter {
+    pub(crate) fn new(interval: Duration) -> Self {
+        Limiter { interval, next: Mutex::new(None) }
+    }
+
+    // Wait for the next free slot
+    pub(crate) fn wait(&self) {
+        let now = Instant::now();
+        let slot = {
+            let mut next = self.next.lock().unwrap();
+            let slot = next.map(|next| next.max(now)).unwrap_or(now);
+            *next = Some(slot + self.interval);
+            slot
+        };
+        thread::sleep(slot - now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_request_rate() {
+        let limiter = Limiter::new(Duration::from_millis(20));
+        let start = Instant::now();
+        for _ in 0..4 {
+            limiter.wait();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(60));
+    }
+}
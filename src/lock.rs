@@ -0,0 +1,140 @@
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use fs2::FileExt;
+
+// Dump files are locked while read or written, so that concurrent invocations wait for each other.
+// A dump is written in a temporary file renamed over the previous one, so an interrupted write never leaves a partial dump.
+// The lock is taken on a `<dump>.lock` sibling, which stays the same file when the dump is replaced.
+// Locks are advisory and released when the file is closed.
+
+// Temporary files written by this process, so that concurrent writes of the same dump never share one
+static TEMPORARIES: AtomicUsize = AtomicUsize::new(0);
+
+// Replace the dump file at `path` by the content written by `write`, once every other reader or writer is done with it
+pub(crate) fn write_locked<F: FnOnce(&mut BufWriter<File>) -> io::Result<()>>(path: &Path, write: F) -> io::Result<()> {
+    let lock = OpenOptions::new().write(true).create(true).truncate(false).open(lock_path(path))?;
+    lock.lock_exclusive()?;
+    remove_stale_temporaries(path);
+    let temporary = temporary_path(path);
+    let written = write_synced(&temporary, write).and_then(|_| fs::rename(&temporary, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    written
+}
+
+fn write_synced<F: FnOnce(&mut BufWriter<File>) -> io::Result<()>>(path: &Path, write: F) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write(&mut writer)?;
+    writer.flush()?;
+    writer.get_ref().sync_all()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+fn lock_path(path: &Path) -> PathBuf {
+    path.with_file_name(format!("{}.lock", file_name(path)))
+}
+
+// A sibling of `path`, so that it is renamed on the same file system
+fn temporary_path(path: &Path) -> PathBuf {
+    let count = TEMPORARIES.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{}.{}.{}.tmp", file_name(path), process::id(), count))
+}
+
+// The temporary files of writers killed before their rename, which no writer uses while the lock is held
+fn remove_stale_temporaries(path: &Path) {
+    let prefix = format!(".{}.", file_name(path));
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new(".")
+    };
+    if let Ok(entries) = fs::read_dir(directory) {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if is_temporary_of(&name, &prefix) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+// Whether `name` is `<prefix><process>.<count>.tmp`, and not the temporary file of another dump whose name starts the same
+fn is_temporary_of(name: &str, prefix: &str) -> bool {
+    let numbers = match name.strip_prefix(prefix).and_then(|rest| rest.strip_suffix(".tmp")) {
+        Some(numbers) => numbers,
+        None => return false
+    };
+    match numbers.split_once('.') {
+        Some((process, count)) => [process, count].iter().all(|number| !number.is_empty() && number.bytes().all(|byte| byte.is_ascii_digit())),
+        None => false
+    }
+}
+
+// Read the dump file at `path`, once no writer is using it
+pub(crate) fn read_locked(path: &Path) -> io::Result<String> {
+    // A missing dump is reported without leaving a lock file behind
+    fs::metadata(path)?;
+    let lock = OpenOptions::new().write(true).create(true).truncate(false).open(lock_path(path))?;
+    lock.lock_shared()?;
+    let mut file = File::open(path)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_and_read_locked() {
+        let mut path = std::env::temp_dir();
+        path.push("bloom_locked_dump.txt");
+        fs::write(&path, "a previous and longer content").unwrap();
+        write_locked(&path, |file| {
+            assert!(File::open(lock_path(&path)).unwrap().try_lock_shared().is_err());
+            write!(file, "content")
+        }).unwrap();
+        assert_eq!("content", read_locked(&path).unwrap());
+        assert!(File::open(lock_path(&path)).unwrap().try_lock_exclusive().is_ok());
+    }
+
+    #[test]
+    fn interrupted_write_keeps_previous_dump() {
+        let mut path = std::env::temp_dir();
+        path.push("bloom_interrupted_dump.txt");
+        fs::write(&path, "previous content").unwrap();
+        let written = write_locked(&path, |file| {
+            write!(file, "partial")?;
+            Err(io::Error::new(io::ErrorKind::Other, "interrupted"))
+        });
+        assert!(written.is_err());
+        assert_eq!("previous content", read_locked(&path).unwrap());
+        let temporaries = fs::read_dir(std::env::temp_dir()).unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| is_temporary_of(&entry.file_name().to_string_lossy(), ".bloom_interrupted_dump.txt."))
+            .count();
+        assert_eq!(0, temporaries);
+    }
+
+    #[test]
+    fn remove_stale_temporary_files() {
+        let mut path = std::env::temp_dir();
+        path.push("bloom_stale_dump.txt");
+        let stale = temporary_path(&path);
+        fs::write(&stale, "partial").unwrap();
+        assert_ne!(stale, temporary_path(&path));
+        write_locked(&path, |file| write!(file, "content")).unwrap();
+        assert!(!stale.exists());
+        assert_eq!("content", read_locked(&path).unwrap());
+        assert!(!is_temporary_of(".bloom_stale_dump.txt.gz.1.2.tmp", ".bloom_stale_dump.txt."));
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use serde_json::Value;
+use crate::fs_loader::FsIndex;
+use crate::stats::{IngestReport, Issue};
+use crate::store::IndexStore;
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+// Year of the timestamps without year, a leap year so that February 29 exists
+const YEARLESS: i64 = 2000;
+
+impl<S: IndexStore> FsIndex<S> {
+    /// Ingest the syslog file at `path`, one document per `window` of time keyed `path@start`, e.g. `/var/log/syslog@05-01T12:00:00`.
+    ///
+    /// Lines start with an RFC 3164 (`May  1 12:00:03`) or RFC 5424 (`2024-05-01T12:00:03`) timestamp, optionally preceded by a priority.
+    /// Lines without timestamp, e.g. the continuations of a stack trace, belong to the window of the previous line.
+    /// Windows are named after their start, with the year when the timestamps have one. A window seen again further in the file is appended to its document.
+    /// The documents have no source file, so they are never pruned nor refreshed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is less than a second or if a window cannot be inserted in the index.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use cli_bloom::FsIndex;
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest_syslog("/var/log/syslog", Duration::from_secs(3600));
+    /// ```
+    pub fn ingest_syslog<P: AsRef<Path>>(&mut self, path: P, window: Duration) -> IngestReport {
+        let path = path.as_ref();
+        let mut report = IngestReport::default();
+        let reader = match self.file_system().open(path) {
+            Ok(reader) => BufReader::new(reader),
+            Err(error) => {
+                report.failed.push(Issue { path: path.to_path_buf(), reason: error.to_string() });
+                return report;
+            }
+        };
+        let prefix = path.display().to_string();
+        let mut windows = Windows::new(window);
+        let mut seen = HashSet::new();
+        for line in reader.lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(error) => {
+                    report.failed.push(Issue { path: path.to_path_buf(), reason: error.to_string() });
+                    break;
+                }
+            };
+            if let Some((label, text)) = windows.push(parse_time(&line), &line) {
+                self.insert_window(&format!("{}@{}", prefix, label), &text, &mut seen, &mut report);
+            }
+        }
+        if let Some((label, text)) = windows.finish() {
+            self.insert_window(&format!("{}@{}", prefix, label), &text, &mut seen, &mut report);
+        }
+        report
+    }
+
+    /// Ingest the systemd journal, read with `journalctl` since `since` (e.g. `today` or `2024-05-01`) or from the start, one document per `window` of time keyed `journal@start`.
+    ///
+    /// The text of each entry is its host, identifier and message. Windows are named after their start in UTC, e.g. `journal@2024-05-01T12:00:00`.
+    /// Lines of `journalctl` which are not journal entries are listed as failures in the report.
+    /// The documents have no source file, so they are never pruned nor refreshed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is less than a second, if `journalctl` cannot be run or if a window cannot be inserted in the index.
+    pub fn ingest_journal(&mut self, since: Option<&str>, window: Duration) -> IngestReport {
+        let mut windows = Windows::new(window);
+        let mut command = Command::new("journalctl");
+        command.args(["--output", "json", "--no-pager"]);
+        if let Some(since) = since {
+            command.arg("--since").arg(since);
+        }
+        let mut child = command.stdout(Stdio::piped()).spawn().unwrap_or_else(|error| panic!("Unable to run journalctl : {}", error));
+        let mut report = IngestReport::default();
+        let mut seen = HashSet::new();
+        for line in BufReader::new(child.stdout.take().unwrap()).lines() {
+            let entry = match line {
+                Ok(line) => journal_entry(&line),
+                Err(error) => {
+                    report.failed.push(Issue { path: PathBuf::from("journal"), reason: error.to_string() });
+                    break;
+                }
+            };
+            let (time, text) = match entry {
+                Some(entry) => entry,
+                None => {
+                    report.failed.push(Issue { path: PathBuf::from("journal"), reason: "not a journal entry".to_string() });
+                    continue;
+                }
+            };
+            if let Some((label, text)) = windows.push(Some(time), &text) {
+                self.insert_window(&format!("journal@{}", label), &text, &mut seen, &mut report);
+            }
+        }
+        if let Some((label, text)) = windows.finish() {
+            self.insert_window(&format!("journal@{}", label), &text, &mut seen, &mut report);
+        }
+        let _ = child.wait();
+        report
+    }
+
+    fn insert_window(&mut self, key: &str, text: &str, seen: &mut HashSet<String>, report: &mut IngestReport) {
+        if seen.insert(key.to_string()) {
+            self.insert_text(key, text);
+            report.indexed += 1;
+        } else {
+            self.append(key, text);
+        }
+        report.bytes += text.len() as u64;
+    }
+}
+
+// A time in seconds since 1970, in 2000 when the log has no year
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Time {
+    seconds: i64,
+    year: bool
+}
+
+// Groups consecutive log lines by window of time
+struct Windows {
+    window: i64,
+    start: Option<Time>,
+    text: String
+}
+
+impl Windows {
+    fn new(window: Duration) -> Self {
+        if window.as_secs() == 0 {
+            panic!("The time window must be at least a second");
+        }
+        Windows { window: window.as_secs() as i64, start: None, text: String::new() }
+    }
+
+    // Add a line, returning the label and text of the previous window if the line starts a new one
+    fn push(&mut self, time: Option<Time>, line: &str) -> Option<(String, String)> {
+        let mut finished = None;
+        if let Some(time) = time {
+            let start = Time { seconds: time.seconds - time.seconds.rem_euclid(self.window), ..time };
+            if self.start.map(|current| current != start).unwrap_or(false) {
+                finished = self.finish();
+            }
+            self.start = Some(start);
+        }
+        self.text.push_str(line);
+        self.text.push('\n');
+        finished
+    }
+
+    // The label and text of the current window, if it has any line
+    fn finish(&mut self) -> Option<(String, String)> {
+        if self.text.is_empty() {
+            return None;
+        }
+        let label = self.start.map(label).unwrap_or_else(|| "undated".to_string());
+        Some((label, std::mem::take(&mut self.text)))
+    }
+}
+
+fn label(time: Time) -> String {
+    let (year, month, day) = civil_from_days(time.seconds.div_euclid(86400));
+    let seconds = time.seconds.rem_euclid(86400);
+    let clock = format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if time.year {
+        format!("{:04}-{:02}-{:02}T{}", year, month, day, clock)
+    } else {
+        format!("{:02}-{:02}T{}", month, day, clock)
+    }
+}
+
+// Time at the start of a syslog line
+fn parse_time(line: &str) -> Option<Time> {
+    let line = strip_priority(line);
+    parse_rfc5424(line).or_else(|| parse_rfc3164(line))
+}
+
+// The line without its `<priority>` and RFC 5424 version
+fn strip_priority(line: &str) -> &str {
+    if line.starts_with('<') {
+        if let Some(end) = line.find('>') {
+            let rest = &line[end + 1..];
+            return rest.strip_prefix("1 ").unwrap_or(rest);
+        }
+    }
+    line
+}
+
+// 2024-05-01T12:00:03, fractions and time zone ignored
+fn parse_rfc5424(line: &str) -> Option<Time> {
+    let date = line.get(..19)?;
+    let bytes = date.as_bytes();
+    if bytes[4] != b'-' || bytes[7] != b'-' || (bytes[10] != b'T' && bytes[10] != b' ') {
+        return None;
+    }
+    let number = |start: usize, end: usize| date.get(start..end).and_then(|digits| digits.parse().ok());
+    let days = days_from_civil(number(0, 4)?, number(5, 7)? as u32, number(8, 10)? as u32)?;
+    Some(Time { seconds: days * 86400 + parse_clock(date.get(11..)?)?, year: true })
+}
+
+// May  1 12:00:03
+fn parse_rfc3164(line: &str) -> Option<Time> {
+    let month = MONTHS.iter().position(|month| line.starts_with(month))? as u32 + 1;
+    let day = line.get(4..6)?.trim_start().parse().ok()?;
+    let days = days_from_civil(YEARLESS, month, day)?;
+    Some(Time { seconds: days * 86400 + parse_clock(line.get(7..15)?)?, year: false })
+}
+
+// Seconds since midnight of 12:00:03
+fn parse_clock(clock: &str) -> Option<i64> {
+    let mut parts = clock.split(':').map(|part| part.parse::<i64>().ok());
+    let (hours, minutes, seconds) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || hours > 23 || minutes > 59 || seconds > 60 || clock.len() != 8 {
+        return None;
+    }
+    Some(hours * 3600 + minutes * 60 + seconds)
+}
+
+// Days since 1970-01-01, from Howard Hinnant's date algorithms
+fn days_from_civil(year: i64, month: u32, day: u32) -> Option<i64> {
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month as i64 + 9) % 12) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146097 + day_of_era - 719468)
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// Time and text of a line of `journalctl --output json`
+fn journal_entry(line: &str) -> Option<(Time, String)> {
+    let entry: Value = serde_json::from_str(line).ok()?;
+    let microseconds: i64 = entry.get("__REALTIME_TIMESTAMP")?.as_str()?.parse().ok()?;
+    let message = match entry.get("MESSAGE")? {
+        Value::String(message) => message.clone(),
+        // Messages which are not UTF-8 are arrays of bytes
+        Value::Array(bytes) => String::from_utf8_lossy(&bytes.iter().filter_map(|byte| byte.as_u64().map(|byte| byte as u8)).collect::<Vec<u8>>()).into_owned(),
+        _ => return None
+    };
+    let field = |name: &str| entry.get(name).and_then(Value::as_str).unwrap_or_default().to_string();
+    let identifier = Some(field("SYSLOG_IDENTIFIER")).filter(|identifier| !identifier.is_empty()).unwrap_or_else(|| field("_COMM"));
+    let time = Time { seconds: microseconds.div_euclid(1_000_000), year: true };
+    Some((time, format!("{} {}: {}", field("_HOSTNAME"), identifier, message)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::MemoryFileSystem;
+
+    #[test]
+    fn syslog_timestamps() {
+        let time = parse_time("<34>1 2024-05-01T12:00:03.003Z host app - - - started").unwrap();
+        assert_eq!("2024-05-01T12:00:03", label(time));
+        assert_eq!("02-29T23:59:59", label(parse_time("Feb 29 23:59:59 host kernel: panic").unwrap()));
+        assert_eq!("05-01T08:00:00", label(parse_time("May  1 08:00:00 host cron[42]: job").unwrap()));
+        assert_eq!(None, parse_time("    at com.example.Main.run(Main.java:42)"));
+        assert_eq!(None, parse_time("May 32 08:00:00 host cron: job"));
+    }
+
+    #[test]
+    fn calendar_round_trip() {
+        for days in [-719468, -1, 0, 11016, 19844, 2932896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(Some(days), days_from_civil(year, month, day));
+        }
+        assert_eq!((2024, 5, 1), civil_from_days(19844));
+    }
+
+    #[test]
+    fn ingest_syslog_by_window() {
+        let files = MemoryFileSystem::default();
+        files.insert("/var/log/syslog", "May  1 08:10:00 host app: word1\n  continued word2\nMay  1 09:05:00 host app: word3\nMay  1 08:59:00 host app: word4\n");
+        let mut fs_index = FsIndex::new(0.00001);
+        fs_index.set_file_system(files);
+        let report = fs_index.ingest_syslog("/var/log/syslog", Duration::from_secs(3600));
+        assert_eq!(2, report.indexed);
+        assert_eq!(vec!["/var/log/syslog@05-01T08:00:00"], fs_index.search("word2 word4").unwrap());
+        assert_eq!(vec!["/var/log/syslog@05-01T09:00:00"], fs_index.search("word3").unwrap());
+    }
+
+    #[test]
+    fn journal_entries() {
+        let line = r#"{"__REALTIME_TIMESTAMP":"1714564803000000","_HOSTNAME":"host","SYSLOG_IDENTIFIER":"sshd","MESSAGE":"Accepted key"}"#;
+        let (time, text) = journal_entry(line).unwrap();
+        assert_eq!("2024-05-01T12:00:03", label(time));
+        assert_eq!("host sshd: Accepted key", text);
+        let bytes = r#"{"__REALTIME_TIMESTAMP":"1714564803000000","_HOSTNAME":"host","_COMM":"app","MESSAGE":[104,105,255]}"#;
+        assert_eq!("host app: hi\u{fffd}", journal_entry(bytes).unwrap().1);
+        assert_eq!(None, journal_entry("-- No entries --"));
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use mailparse::{MailHeaderMap, ParsedMail};
+use crate::fs_loader::FsIndex;
+use crate::html::html_text;
+use crate::stats::{IngestReport, Issue};
+use crate::store::IndexStore;
+
+// Messages fetched at a time
+const BATCH_SIZE: usize = 100;
+
+/// The IMAP account of a mailbox, whose folders are synchronized with [`FsIndex::sync_mailbox`].
+///
+/// The connection is always encrypted with TLS, on port 993 unless given otherwise.
+#[derive(Clone, Debug)]
+pub struct ImapAccount {
+    host: String,
+    port: u16,
+    user: String,
+    password: String
+}
+
+impl ImapAccount {
+    /// Constructs the account of `user` on the IMAP server `host`.
+    pub fn new(host: &str, user: &str, password: &str) -> Self {
+        ImapAccount { host: host.to_string(), port: 993, user: user.to_string(), password: password.to_string() }
+    }
+
+    /// The same account, on another port.
+    pub fn port(self, port: u16) -> Self {
+        ImapAccount { port, ..self }
+    }
+}
+
+impl<S: IndexStore> FsIndex<S> {
+    /// Synchronize the index with the messages of `folders` in the mailbox of `account`, each message a document keyed `folder/UID`.
+    ///
+    /// Only the messages which are not in the index yet are fetched, the others are counted as unchanged, so that running it again is cheap.
+    /// The documents of messages no longer in a folder are removed from the index.
+    /// The text of a message is its subject, sender, recipients and text parts, HTML parts stripped of their markup.
+    /// Messages which cannot be parsed are listed as failures in the report, with their key as path.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the server cannot be reached, if the login fails, if a folder cannot be selected or if a message cannot be inserted in the index.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use cli_bloom::{FsIndex, ImapAccount};
+    /// let account = ImapAccount::new("imap.example.com", "olivier", "secret");
+    /// let mut fs_index = FsIndex::restore("/foo/mail.json");
+    /// let report = fs_index.sync_mailbox(&account, &["INBOX", "Archives"]);
+    /// println!("{} new messages", report.indexed);
+    /// fs_index.dump("/foo/mail.json");
+    /// ```
+    pub fn sync_mailbox(&mut self, account: &ImapAccount, folders: &[&str]) -> IngestReport {
+        let tls = native_tls::TlsConnector::new().expect("Unable to initialize TLS");
+        let client = imap::connect((account.host.as_str(), account.port), &account.host, &tls)
+            .unwrap_or_else(|error| panic!("Unable to connect to {} : {}", account.host, error));
+        let mut session = client.login(&account.user, &account.password)
+            .unwrap_or_else(|(error, _)| panic!("Unable to log in as {} : {}", account.user, error));
+        let mut report = IngestReport::default();
+        for folder in folders {
+            session.select(folder).unwrap_or_else(|error| panic!("Unable to select {} : {}", folder, error));
+            let uids = session.uid_search("ALL").unwrap_or_else(|error| panic!("Unable to list the messages of {} : {}", folder, error));
+            let keys: HashSet<String> = uids.iter().map(|uid| message_key(folder, *uid)).collect();
+            let indexed: HashSet<String> = self.keys().filter(|key| in_folder(key, folder)).map(str::to_string).collect();
+            let removed: Vec<String> = indexed.difference(&keys).cloned().collect();
+            self.remove_filters(&removed);
+            let mut new: Vec<u32> = uids.into_iter().filter(|uid| !indexed.contains(&message_key(folder, *uid))).collect();
+            report.unchanged += keys.len() - new.len();
+            new.sort_unstable();
+            for batch in new.chunks(BATCH_SIZE) {
+                let set: Vec<String> = batch.iter().map(u32::to_string).collect();
+                let messages = session.uid_fetch(set.join(","), "RFC822")
+                    .unwrap_or_else(|error| panic!("Unable to fetch the messages of {} : {}", folder, error));
+                for message in messages.iter() {
+                    let (uid, body) = match (message.uid, message.body()) {
+                        (Some(uid), Some(body)) => (uid, body),
+                        _ => continue
+                    };
+                    let key = message_key(folder, uid);
+                    match message_text(body) {
+                        Ok(text) => {
+                            self.insert_text(&key, &text);
+                            report.indexed += 1;
+                            report.bytes += body.len() as u64;
+                        },
+                        Err(reason) => report.failed.push(Issue { path: PathBuf::from(key), reason })
+                    }
+                }
+            }
+        }
+        let _ = session.logout();
+        report
+    }
+}
+
+fn message_key(folder: &str, uid: u32) -> String {
+    format!("{}/{}", folder, uid)
+}
+
+// Whether `key` is the key of a message of `folder`, and not of one of its subfolders
+fn in_folder(key: &str, folder: &str) -> bool {
+    key.strip_prefix(folder)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .map(|uid| !uid.is_empty() && uid.bytes().all(|byte| byte.is_ascii_digit()))
+        .unwrap_or(false)
+}
+
+// Subject, sender, recipients and text of a message
+fn message_text(raw: &[u8]) -> Result<String, String> {
+    let mail = mailparse::parse_mail(raw).map_err(|error| error.to_string())?;
+    let mut text = String::new();
+    for header in ["Subject", "From", "To", "Cc"] {
+        if let Some(value) = mail.headers.get_first_value(header) {
+            text.push_str(&value);
+            text.push('\n');
+        }
+    }
+    push_parts(&mail, &mut text)?;
+    Ok(text)
+}
+
+// Text of the text/plain and text/html parts, attachments left out
+fn push_parts(part: &ParsedMail, text: &mut String) -> Result<(), String> {
+    if !part.subparts.is_empty() {
+        // The alternatives of a text are the same text, the last is the richest
+        let parts = if part.ctype.mimetype.eq_ignore_ascii_case("multipart/alternative") { &part.subparts[part.subparts.len() - 1..] } else { &part.subparts[..] };
+        for subpart in parts {
+            push_parts(subpart, text)?;
+        }
+        return Ok(());
+    }
+    if part.get_content_disposition().disposition == mailparse::DispositionType::Attachment {
+        return Ok(());
+    }
+    let mimetype = part.ctype.mimetype.to_ascii_lowercase();
+    if mimetype == "text/plain" {
+        text.push_str(&part.get_body().map_err(|error| error.to_string())?);
+    } else if mimetype == "text/html" {
+        text.push_str(&html_text(&part.get_body().map_err(|error| error.to_string())?).0);
+    }
+    text.push('\n');
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_of_folder() {
+        assert!(in_folder("INBOX/42", "INBOX"));
+        assert!(!in_folder("INBOX/Old/42", "INBOX"));
+        assert!(!in_folder("INBOXES/42", "INBOX"));
+        assert!(!in_folder("INBOX/", "INBOX"));
+    }
+
+    #[test]
+    fn text_of_message() {
+        let raw = "Subject: Word1\r\nFrom: a@example.com\r\nContent-Type: multipart/mixed; boundary=\"b1\"\r\n\r\n\
+            --b1\r\nContent-Type: multipart/alternative; boundary=\"b2\"\r\n\r\n\
+            --b2\r\nContent-Type: text/plain\r\n\r\nword2\r\n\
+            --b2\r\nContent-Type: text/html\r\n\r\n<p>word3</p>\r\n--b2--\r\n\
+            --b1\r\nContent-Type: text/plain\r\nContent-Disposition: attachment; filename=\"notes.txt\"\r\n\r\nword4\r\n--b1--\r\n";
+        let words: Vec<String> = message_text(raw.as_bytes()).unwrap().split_whitespace().map(str::to_string).collect();
+        assert_eq!(vec!["Word1", "a@example.com", "word3"], words);
+    }
+}
@@ -1,36 +1,1501 @@
-use clap::{App, Arg};
-use cli_bloom::FsIndex;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::IsTerminal;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::process::{Child, Command};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde::Serialize;
+use serde_json::json;
+use cli_bloom::{auto_error_rate, Analyzer, Backend, Config, DirectoryStore, DumpInfo, FsIndex, FsIndexBuilder, Generations, Glob, HashFunction, IndexStore, IngestReport, MemoryStore, Outcome, Problem, ProgressObserver, Query, ReaderSource, RetryPolicy, Sort, TieredStore, validate_dump_file};
 
 fn main() {
-    let matches = App::new("cli-bloom")
+    let app = App::new("cli-bloom")
                    .version("1.0")
                    .about("A command line app to manage a bloom index.")
+                   .arg(Arg::with_name("color")
+                        .long("color")
+                        .help("Colorize the output, auto disables colors when NO_COLOR is set or the output is not a terminal")
+                        .takes_value(true)
+                        .possible_values(&["auto", "always", "never"])
+                        .default_value("auto")
+                        .global(true))
+                   .arg(Arg::with_name("format")
+                        .long("format")
+                        .help("Output of the commands, json prints a single JSON document on the standard output, diagnostics stay on the standard error")
+                        .takes_value(true)
+                        .possible_values(&["plain", "json"])
+                        .default_value("plain")
+                        .global(true))
                    .arg(Arg::with_name("source")
                         .short("s")
                         .long("source")
-                        .help("Path to the file or directory to index")
+                        .help("Path to the file or directory to index, can be repeated, - for the standard input (decompressed if it is gzip, zstd or xz)")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1))
+                   .arg(Arg::with_name("files-from")
+                        .long("files-from")
+                        .help("Path to a list of files to index, separated by new lines or NUL characters, - for the standard input")
                         .takes_value(true))
                    .arg(Arg::with_name("restore")
                         .short("r")
                         .long("restore")
                         .help("Path to an index dump file")
                         .takes_value(true))
+                   .arg(Arg::with_name("only")
+                        .long("only")
+                        .help("Restore only the documents whose key starts with this prefix (a trailing * is allowed)")
+                        .takes_value(true)
+                        .requires("restore"))
+                   .arg(Arg::with_name("map-prefix")
+                        .long("map-prefix")
+                        .help("Replace the leading /old/root of the restored keys and paths by /new/root, given as /old/root=/new/root, can be repeated")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .requires("restore"))
+                   .arg(Arg::with_name("error-rate")
+                        .long("error-rate")
+                        .help("Probability of false positive of a new index, each halving costs about 1.44 bits per token, auto fits the sources in --memory-target")
+                        .takes_value(true)
+                        .default_value("0.00001"))
+                   .arg(Arg::with_name("bits-per-token")
+                        .long("bits-per-token")
+                        .help("Bits per distinct token of the filters of a new index, instead of --error-rate")
+                        .takes_value(true)
+                        .conflicts_with("restore"))
+                   .arg(Arg::with_name("hash-functions")
+                        .long("hash-functions")
+                        .help("Hash functions of the filters of a new index, instead of --error-rate")
+                        .takes_value(true)
+                        .conflicts_with("restore"))
+                   .arg(Arg::with_name("backend")
+                        .long("backend")
+                        .help("Structure of the filters of the ingested documents: bloom, or xor, cuckoo and counting when built with these features")
+                        .takes_value(true))
+                   .arg(Arg::with_name("hash-function")
+                        .long("hash-function")
+                        .help("Hash function of the tokens in the filters: fnv, xxhash or siphash (with a random key kept in the dump)")
+                        .takes_value(true))
+                   .arg(Arg::with_name("split-words")
+                        .long("split-words")
+                        .help("Split words on every character which is not alphanumeric, kept in the dump")
+                        .conflicts_with("restore"))
+                   .arg(Arg::with_name("fold-diacritics")
+                        .long("fold-diacritics")
+                        .help("Remove the diacritics of latin letters in documents and searches, kept in the dump")
+                        .conflicts_with("restore"))
+                   .arg(Arg::with_name("stemming")
+                        .long("stemming")
+                        .help("Reduce English plurals to their singular in documents and searches, kept in the dump")
+                        .conflicts_with("restore"))
+                   .arg(Arg::with_name("stop-words")
+                        .long("stop-words")
+                        .help("Comma separated words neither indexed nor searched, kept in the dump")
+                        .takes_value(true)
+                        .conflicts_with("restore"))
+                   .arg(Arg::with_name("memory-target")
+                        .long("memory-target")
+                        .help("Memory the filters of the sources should fit in (e.g. 256M) with --error-rate auto")
+                        .takes_value(true))
                    .arg(Arg::with_name("dump")
                         .short("d")
                         .long("dump")
                         .help("Path to dump the current index")
                         .takes_value(true))
-                   .get_matches();
+                   .arg(Arg::with_name("audit-log")
+                        .long("audit-log")
+                        .help("Path to a file where to append a JSON record for each ingested file")
+                        .takes_value(true))
+                   .arg(Arg::with_name("events")
+                        .long("events")
+                        .help("Write an event for each file processed by the ingestion, as JSON lines on the standard output (the standard error with --format json) or in --events-output")
+                        .takes_value(true)
+                        .possible_values(&["jsonl"]))
+                   .arg(Arg::with_name("events-output")
+                        .long("events-output")
+                        .help("Path to the file where to write the events of --events")
+                        .takes_value(true)
+                        .requires("events"))
+                   .arg(Arg::with_name("fail-fast")
+                        .long("fail-fast")
+                        .help("Abort on the first file which cannot be read instead of reporting it at the end"))
+                   .arg(Arg::with_name("dedup")
+                        .long("dedup")
+                        .help("Index the content of identical files once, the copies becoming aliases of the first file"))
+                   .arg(Arg::with_name("retries")
+                        .long("retries")
+                        .help("Number of times to read again a file failing with a transient error")
+                        .takes_value(true))
+                   .arg(Arg::with_name("chunk-size")
+                        .long("chunk-size")
+                        .help("Split files larger than this size (e.g. 64M) into chunks searched separately")
+                        .takes_value(true))
+                   .arg(Arg::with_name("paragraphs")
+                        .long("paragraphs")
+                        .help("Index each paragraph of the files as a document keyed path¶n"))
+                   .arg(Arg::with_name("key-template")
+                        .long("key-template")
+                        .help("Key of the documents, {path}, {relpath} (relative to the source) and {name} are replaced by the file path, relative path and name")
+                        .takes_value(true))
+                   .arg(Arg::with_name("subtitle-cues")
+                        .long("subtitle-cues")
+                        .help("Index the subtitle files (.srt and .vtt) by groups of this number of cues")
+                        .takes_value(true))
+                   .arg(Arg::with_name("stdin-key")
+                        .long("stdin-key")
+                        .help("Key of the document read from the standard input with --source -")
+                        .takes_value(true)
+                        .default_value("stdin"))
+                   .arg(Arg::with_name("key-prefix")
+                        .long("key-prefix")
+                        .help("Prefix of the keys of the documents ingested in this run, e.g. projA:")
+                        .takes_value(true))
+                   .arg(Arg::with_name("tag")
+                        .long("tag")
+                        .help("Tag of the documents ingested in this run, given as name=value, can be repeated")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1))
+                   .arg(Arg::with_name("max-memory")
+                        .long("max-memory")
+                        .help("Memory budget of the filters (e.g. 512M), the others are kept in a temporary directory until dumped")
+                        .takes_value(true))
+                   .arg(Arg::with_name("syslog")
+                        .long("syslog")
+                        .help("Path to a syslog file to index, one document per --window of time, can be repeated")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1))
+                   .arg(Arg::with_name("journal")
+                        .long("journal")
+                        .help("Index the systemd journal read with journalctl, one document per --window of time"))
+                   .arg(Arg::with_name("since")
+                        .long("since")
+                        .help("Index the journal since this time, as understood by journalctl, e.g. today or 2024-05-01")
+                        .takes_value(true)
+                        .requires("journal"))
+                   .arg(Arg::with_name("window")
+                        .long("window")
+                        .help("Number of seconds of logs in a document")
+                        .takes_value(true)
+                        .default_value("3600"))
+                   .arg(Arg::with_name("sections")
+                        .long("sections")
+                        .help("Dump in sections serialized in parallel, faster for large indexes")
+                        .requires("dump"))
+                   .arg(Arg::with_name("pretty")
+                        .long("pretty")
+                        .help("Dump with one line per document and per filter, for readable diffs in version control")
+                        .requires("dump")
+                        .conflicts_with("sections"))
+                   .subcommand(SubCommand::with_name("prune")
+                        .about("Remove documents whose files no longer exist")
+                        .arg(Arg::with_name("restore")
+                             .short("r")
+                             .long("restore")
+                             .help("Path to an index dump file")
+                             .takes_value(true)
+                             .required(true))
+                        .arg(Arg::with_name("dump")
+                             .short("d")
+                             .long("dump")
+                             .help("Path to dump the pruned index")
+                             .takes_value(true))
+                        .arg(Arg::with_name("map-prefix")
+                             .long("map-prefix")
+                             .help("Replace the leading /old/root of the restored keys and paths by /new/root, given as /old/root=/new/root, can be repeated")
+                             .takes_value(true)
+                             .multiple(true)
+                             .number_of_values(1)))
+                   .subcommand(SubCommand::with_name("refresh")
+                        .about("Remove deleted files and ingest again changed files")
+                        .arg(Arg::with_name("restore")
+                             .short("r")
+                             .long("restore")
+                             .help("Path to an index dump file")
+                             .takes_value(true)
+                             .required(true))
+                        .arg(Arg::with_name("dump")
+                             .short("d")
+                             .long("dump")
+                             .help("Path to dump the refreshed index")
+                             .takes_value(true))
+                        .arg(Arg::with_name("map-prefix")
+                             .long("map-prefix")
+                             .help("Replace the leading /old/root of the restored keys and paths by /new/root, given as /old/root=/new/root, can be repeated")
+                             .takes_value(true)
+                             .multiple(true)
+                             .number_of_values(1)))
+                   .subcommand(SubCommand::with_name("compact")
+                        .about("Rebuild the oversized and saturated filters from their files")
+                        .arg(Arg::with_name("restore")
+                             .short("r")
+                             .long("restore")
+                             .help("Path to an index dump file")
+                             .takes_value(true)
+                             .required(true))
+                        .arg(Arg::with_name("dump")
+                             .short("d")
+                             .long("dump")
+                             .help("Path to dump the compacted index")
+                             .takes_value(true)))
+                   .subcommand(SubCommand::with_name("search")
+                        .about("Search the documents matching a query")
+                        .arg(Arg::with_name("restore")
+                             .short("r")
+                             .long("restore")
+                             .help("Path to an index dump file")
+                             .takes_value(true)
+                             .required(true))
+                        .arg(Arg::with_name("query")
+                             .short("q")
+                             .long("query")
+                             .help("Keywords combined with AND, OR, NOT and parentheses, double quotes group keywords")
+                             .takes_value(true)
+                             .required_unless("QUERY"))
+                        .arg(Arg::with_name("QUERY")
+                             .help("Same as --query, or @name to run a query of the config file")
+                             .conflicts_with("query")
+                             .index(1))
+                        .arg(Arg::with_name("only")
+                             .long("only")
+                             .help("Search only the documents whose key starts with this prefix (a trailing * is allowed)")
+                             .takes_value(true))
+                        .arg(Arg::with_name("map-prefix")
+                             .long("map-prefix")
+                             .help("Replace the leading /old/root of the restored keys and paths by /new/root, given as /old/root=/new/root, can be repeated")
+                             .takes_value(true)
+                             .multiple(true)
+                             .number_of_values(1))
+                        .arg(Arg::with_name("path")
+                             .long("path")
+                             .help("Search only the documents whose key matches this glob, e.g. 'src/**/*.rs' (* and ? stop at /, ** does not)")
+                             .takes_value(true))
+                        .arg(Arg::with_name("tag")
+                             .long("tag")
+                             .help("Search only the documents with this tag, given as name=value, can be repeated")
+                             .takes_value(true)
+                             .multiple(true)
+                             .number_of_values(1))
+                        .arg(Arg::with_name("limit")
+                             .long("limit")
+                             .help("Stop the search after this number of hits, or keep the first ones with --sort")
+                             .takes_value(true))
+                        .arg(Arg::with_name("offset")
+                             .long("offset")
+                             .help("Leave out this number of first hits, to page through them with --limit")
+                             .takes_value(true))
+                        .arg(Arg::with_name("timeout")
+                             .long("timeout")
+                             .help("Stop the search after this number of seconds (e.g. 0.5), keeping the hits found so far")
+                             .takes_value(true))
+                        .arg(Arg::with_name("sort")
+                             .long("sort")
+                             .help("Order of the hits: path, modified (most recent first) or score (most keywords of the query first), instead of the key order")
+                             .takes_value(true)
+                             .possible_values(&["path", "modified", "score"]))
+                        .arg(Arg::with_name("saturation")
+                             .long("saturation")
+                             .help("Annotate the hits whose filter is saturated, with their estimated false positive rate above the error rate"))
+                        .arg(Arg::with_name("config")
+                             .short("c")
+                             .long("config")
+                             .help("Path to the config file, instead of cli-bloom/config.toml in the user config directory")
+                             .takes_value(true))
+                        .arg(Arg::with_name("exec")
+                             .long("exec")
+                             .help("Shell command to run for each matching file, {} is replaced by the file path (appended if absent)")
+                             .takes_value(true))
+                        .arg(Arg::with_name("jobs")
+                             .short("j")
+                             .long("jobs")
+                             .help("Number of commands run at the same time by --exec")
+                             .takes_value(true)
+                             .default_value("1")
+                             .requires("exec")))
+                   .subcommand(SubCommand::with_name("snapshots")
+                        .about("List, save, restore or delete the generations of an index dump file")
+                        .arg(Arg::with_name("restore")
+                             .short("r")
+                             .long("restore")
+                             .help("Path to an index dump file")
+                             .takes_value(true)
+                             .required(true))
+                        .arg(Arg::with_name("save")
+                             .long("save")
+                             .help("Save the current dump file as a new generation")
+                             .conflicts_with_all(&["checkout", "delete"]))
+                        .arg(Arg::with_name("label")
+                             .long("label")
+                             .help("Label of the saved generation")
+                             .takes_value(true)
+                             .requires("save"))
+                        .arg(Arg::with_name("checkout")
+                             .long("checkout")
+                             .help("Replace the dump file by the generation with this label or timestamp")
+                             .takes_value(true)
+                             .conflicts_with("delete"))
+                        .arg(Arg::with_name("delete")
+                             .long("delete")
+                             .help("Delete the generation with this label or timestamp")
+                             .takes_value(true)))
+                   .subcommand(SubCommand::with_name("migrate")
+                        .about("Rewrite an index dump file of an older format version in the current one")
+                        .arg(Arg::with_name("restore")
+                             .short("r")
+                             .long("restore")
+                             .help("Path to an index dump file")
+                             .takes_value(true)
+                             .required(true))
+                        .arg(Arg::with_name("dump")
+                             .short("d")
+                             .long("dump")
+                             .help("Path to dump the migrated index, the dump file itself by default")
+                             .takes_value(true)))
+                   .subcommand(SubCommand::with_name("verify")
+                        .about("Check that an index dump file can be restored")
+                        .arg(Arg::with_name("restore")
+                             .short("r")
+                             .long("restore")
+                             .help("Path to an index dump file")
+                             .takes_value(true)
+                             .required(true))
+                        .arg(Arg::with_name("schema")
+                             .long("schema")
+                             .help("Also validate the dump file against the JSON schema of dumps, reporting every violation")))
+                   .subcommand(SubCommand::with_name("inspect")
+                        .about("Describe an index dump file without restoring its filters")
+                        .arg(Arg::with_name("DUMP")
+                             .help("Path to an index dump file")
+                             .required(true)
+                             .index(1))
+                        .arg(Arg::with_name("keys")
+                             .long("keys")
+                             .help("Number of document keys and of largest documents shown")
+                             .takes_value(true)
+                             .default_value("10")))
+                   .subcommand(SubCommand::with_name("stats")
+                        .about("Count the documents of an index dump file by freshness and their tokens")
+                        .arg(Arg::with_name("restore")
+                             .short("r")
+                             .long("restore")
+                             .help("Path to an index dump file")
+                             .takes_value(true)
+                             .required(true)))
+                   .subcommand(SubCommand::with_name("diff")
+                        .about("List the documents added, removed and modified from an index dump file to another")
+                        .arg(Arg::with_name("OLD")
+                             .help("Path to the earlier index dump file")
+                             .required(true)
+                             .index(1))
+                        .arg(Arg::with_name("NEW")
+                             .help("Path to the later index dump file")
+                             .required(true)
+                             .index(2)))
+                   .subcommand(SubCommand::with_name("doctor")
+                        .about("Check an index dump file against its source directory and suggest fixes")
+                        .arg(Arg::with_name("restore")
+                             .short("r")
+                             .long("restore")
+                             .help("Path to an index dump file")
+                             .takes_value(true)
+                             .required(true))
+                        .arg(Arg::with_name("source")
+                             .short("s")
+                             .long("source")
+                             .help("Path to the directory the documents were ingested from")
+                             .takes_value(true)
+                             .required(true)))
+                   .subcommand(SubCommand::with_name("dups")
+                        .about("Report the documents with the same content or nearly the same tokens")
+                        .arg(Arg::with_name("restore")
+                             .short("r")
+                             .long("restore")
+                             .help("Path to an index dump file")
+                             .takes_value(true)
+                             .required(true))
+                        .arg(Arg::with_name("similarity")
+                             .long("similarity")
+                             .help("Lowest proportion of bits set in both filters among those set in either, for documents with nearly the same tokens")
+                             .takes_value(true)
+                             .default_value("0.9")))
+                   .subcommand(SubCommand::with_name("list")
+                        .about("List the keys of all documents")
+                        .arg(Arg::with_name("restore")
+                             .short("r")
+                             .long("restore")
+                             .help("Path to an index dump file")
+                             .takes_value(true)
+                             .required(true))
+                        .arg(Arg::with_name("only")
+                             .long("only")
+                             .help("List only the documents whose key starts with this prefix (a trailing * is allowed)")
+                             .takes_value(true))
+                        .arg(Arg::with_name("map-prefix")
+                             .long("map-prefix")
+                             .help("Replace the leading /old/root of the restored keys and paths by /new/root, given as /old/root=/new/root, can be repeated")
+                             .takes_value(true)
+                             .multiple(true)
+                             .number_of_values(1))
+                        .arg(Arg::with_name("output")
+                             .short("o")
+                             .long("output")
+                             .help("Path to write the keys to instead of the standard output")
+                             .takes_value(true)));
+    #[cfg(feature = "postgres")]
+    let app = app.arg(Arg::with_name("pg")
+                        .long("pg")
+                        .help("URL of a PostgreSQL database to index the rows of --query from, e.g. postgres://user@localhost/blog")
+                        .takes_value(true)
+                        .requires("query"))
+                 .arg(Arg::with_name("query")
+                        .long("query")
+                        .help("SQL query whose rows are indexed, keyed by their first column")
+                        .takes_value(true)
+                        .requires("pg"));
+    #[cfg(feature = "kafka")]
+    let app = app.subcommand(SubCommand::with_name("consume")
+                        .about("Index the messages of a Kafka topic as they arrive, dumping the index periodically")
+                        .arg(Arg::with_name("brokers")
+                             .long("brokers")
+                             .help("Comma separated list of Kafka brokers, e.g. localhost:9092")
+                             .takes_value(true)
+                             .required(true))
+                        .arg(Arg::with_name("topic")
+                             .long("topic")
+                             .help("Topic to consume")
+                             .takes_value(true)
+                             .required(true))
+                        .arg(Arg::with_name("group")
+                             .long("group")
+                             .help("Consumer group, whose offsets are committed after each dump")
+                             .takes_value(true)
+                             .default_value("cli-bloom"))
+                        .arg(Arg::with_name("key-header")
+                             .long("key-header")
+                             .help("Header whose value keys the messages, instead of topic/partition/offset")
+                             .takes_value(true))
+                        .arg(Arg::with_name("dump-every")
+                             .long("dump-every")
+                             .help("Number of seconds between two dumps")
+                             .takes_value(true)
+                             .default_value("60"))
+                        .arg(Arg::with_name("restore")
+                             .short("r")
+                             .long("restore")
+                             .help("Path to an index dump file to add the messages to")
+                             .takes_value(true))
+                        .arg(Arg::with_name("error-rate")
+                             .long("error-rate")
+                             .help("Probability of false positive of a new index")
+                             .takes_value(true)
+                             .default_value("0.00001")
+                             .conflicts_with("restore"))
+                        .arg(Arg::with_name("dump")
+                             .short("d")
+                             .long("dump")
+                             .help("Path to dump the index")
+                             .takes_value(true)
+                             .required(true)));
+    #[cfg(feature = "imap")]
+    let app = app.subcommand(SubCommand::with_name("mail")
+                        .about("Index the messages of IMAP folders, fetching only the new ones when the index is restored")
+                        .arg(Arg::with_name("host")
+                             .long("host")
+                             .help("IMAP server, reached with TLS")
+                             .takes_value(true)
+                             .required(true))
+                        .arg(Arg::with_name("port")
+                             .long("port")
+                             .help("Port of the IMAP server")
+                             .takes_value(true)
+                             .default_value("993"))
+                        .arg(Arg::with_name("user")
+                             .long("user")
+                             .help("User to log in as, with the password in the IMAP_PASSWORD environment variable")
+                             .takes_value(true)
+                             .required(true))
+                        .arg(Arg::with_name("folder")
+                             .long("folder")
+                             .help("Folder to index, can be repeated")
+                             .takes_value(true)
+                             .multiple(true)
+                             .number_of_values(1)
+                             .default_value("INBOX"))
+                        .arg(Arg::with_name("restore")
+                             .short("r")
+                             .long("restore")
+                             .help("Path to an index dump file to synchronize")
+                             .takes_value(true))
+                        .arg(Arg::with_name("error-rate")
+                             .long("error-rate")
+                             .help("Probability of false positive of a new index")
+                             .takes_value(true)
+                             .default_value("0.00001")
+                             .conflicts_with("restore"))
+                        .arg(Arg::with_name("dump")
+                             .short("d")
+                             .long("dump")
+                             .help("Path to dump the index")
+                             .takes_value(true)
+                             .required(true)));
+    #[cfg(feature = "crawl")]
+    let app = app.subcommand(SubCommand::with_name("crawl")
+                        .about("Index the pages of a web site, following the links to the same origin")
+                        .arg(Arg::with_name("url")
+                             .long("url")
+                             .help("URL of the first page")
+                             .takes_value(true)
+                             .required(true))
+                        .arg(Arg::with_name("depth")
+                             .long("depth")
+                             .help("Number of links followed from the first page")
+                             .takes_value(true)
+                             .default_value("1"))
+                        .arg(Arg::with_name("concurrency")
+                             .long("concurrency")
+                             .help("Maximum number of pages fetched at a time")
+                             .takes_value(true)
+                             .default_value("4"))
+                        .arg(Arg::with_name("rate")
+                             .long("rate")
+                             .help("Maximum number of requests per second")
+                             .takes_value(true)
+                             .default_value("5"))
+                        .arg(Arg::with_name("ignore-robots")
+                             .long("ignore-robots")
+                             .help("Fetch the pages disallowed by robots.txt"))
+                        .arg(Arg::with_name("restore")
+                             .short("r")
+                             .long("restore")
+                             .help("Path to an index dump file to add the pages to")
+                             .takes_value(true))
+                        .arg(Arg::with_name("error-rate")
+                             .long("error-rate")
+                             .help("Probability of false positive of a new index")
+                             .takes_value(true)
+                             .default_value("0.00001")
+                             .conflicts_with("restore"))
+                        .arg(Arg::with_name("dump")
+                             .short("d")
+                             .long("dump")
+                             .help("Path to dump the index")
+                             .takes_value(true)
+                             .required(true)));
+    #[cfg(feature = "parquet")]
+    let app = app.subcommand(SubCommand::with_name("parquet")
+                        .about("Export the documents metadata to a Parquet file")
+                        .arg(Arg::with_name("restore")
+                             .short("r")
+                             .long("restore")
+                             .help("Path to an index dump file")
+                             .takes_value(true)
+                             .required(true))
+                        .arg(Arg::with_name("output")
+                             .short("o")
+                             .long("output")
+                             .help("Path to the Parquet file to write")
+                             .takes_value(true)
+                             .required(true)));
+    #[cfg(feature = "tui")]
+    let app = app.subcommand(SubCommand::with_name("tui")
+                        .about("Browse the search results in the terminal")
+                        .arg(Arg::with_name("restore")
+                             .short("r")
+                             .long("restore")
+                             .help("Path to an index dump file")
+                             .takes_value(true)
+                             .required(true)));
+    let matches = app.get_matches();
+
+    match matches.subcommand() {
+        ("prune", Some(prune_matches)) => prune(prune_matches),
+        ("search", Some(search_matches)) => search(search_matches),
+        #[cfg(feature = "tui")]
+        ("tui", Some(tui_matches)) => tui(tui_matches),
+        ("refresh", Some(refresh_matches)) => refresh(refresh_matches),
+        ("compact", Some(compact_matches)) => compact(compact_matches),
+        ("list", Some(list_matches)) => list(list_matches),
+        ("snapshots", Some(snapshots_matches)) => snapshots(snapshots_matches),
+        ("migrate", Some(migrate_matches)) => migrate(migrate_matches),
+        ("verify", Some(verify_matches)) => verify(verify_matches),
+        ("inspect", Some(inspect_matches)) => inspect(inspect_matches),
+        ("stats", Some(stats_matches)) => stats(stats_matches),
+        ("diff", Some(diff_matches)) => diff(diff_matches),
+        ("doctor", Some(doctor_matches)) => doctor(doctor_matches),
+        ("dups", Some(dups_matches)) => dups(dups_matches),
+        #[cfg(feature = "kafka")]
+        ("consume", Some(consume_matches)) => consume(consume_matches),
+        #[cfg(feature = "imap")]
+        ("mail", Some(mail_matches)) => mail(mail_matches),
+        #[cfg(feature = "crawl")]
+        ("crawl", Some(crawl_matches)) => crawl(crawl_matches),
+        #[cfg(feature = "parquet")]
+        ("parquet", Some(parquet_matches)) => parquet(parquet_matches),
+        _ => ingest(&matches)
+    }
+}
 
+fn analyzer(matches: &ArgMatches) -> Analyzer {
+    Analyzer {
+        split_words: matches.is_present("split-words"),
+        fold_diacritics: matches.is_present("fold-diacritics"),
+        stop_words: matches.value_of("stop-words").unwrap_or_default()
+            .split(',')
+            .map(|word| word.trim().to_lowercase())
+            .filter(|word| !word.is_empty())
+            .collect(),
+        stemming: matches.is_present("stemming")
+    }
+}
+
+fn ingest(matches: &ArgMatches) {
+    match matches.value_of("max-memory") {
+        Some(max_memory) => {
+            // Filters exceeding the budget are spilled to a temporary directory, until they are dumped
+            let budget = parse_size(max_memory).unwrap_or_else(|| panic!("Invalid memory size {}", max_memory));
+            let spill_directory = SpillDirectory::create().expect("Impossible to create spill directory");
+            let cold = DirectoryStore::open(&spill_directory.path).expect("Impossible to create spill directory");
+            ingest_in(matches, TieredStore::new(cold, budget));
+        },
+        None => ingest_in(matches, MemoryStore::default())
+    }
+}
+
+fn ingest_in<S: IndexStore + Sync>(matches: &ArgMatches, store: S) {
+    let dump_file = dump_file(matches);
     let mut index = match matches.value_of("restore") {
-        Some(restore_file) => FsIndex::restore(restore_file),
-        None => FsIndex::new(0.00001)
+        Some(restore_file) => restore(restore_file, matches, store),
+        None => {
+            let mut builder = FsIndexBuilder::new(error_rate(matches)).store(store);
+            if let Some(bits) = matches.value_of("bits-per-token") {
+                builder = builder.bits_per_token(bits.parse().expect("The bits per token must be a number"));
+            }
+            if let Some(count) = matches.value_of("hash-functions") {
+                builder = builder.hash_functions(count.parse().expect("The number of hash functions must be a positive integer"));
+            }
+            builder.analyzer(analyzer(matches)).build()
+        }
     };
-    if let Some(source) = matches.value_of("source") {
-        index.ingest(source);
+    if let Some(audit_log) = matches.value_of("audit-log") {
+        index.set_audit_log(audit_log);
+    }
+    if matches.value_of("events") == Some("jsonl") {
+        index.set_progress_observer(EventStream::open(matches.value_of("events-output"), Format::of(matches)));
+    }
+    index.set_fail_fast(matches.is_present("fail-fast"));
+    index.set_dedup(matches.is_present("dedup"));
+    if let Some(retries) = matches.value_of("retries") {
+        let retries = retries.parse().expect("The number of retries must be a positive integer");
+        index.set_retry_policy(RetryPolicy::new(retries, Duration::from_millis(100)));
+    }
+    if let Some(chunk_size) = matches.value_of("chunk-size") {
+        index.set_chunk_size(parse_size(chunk_size).unwrap_or_else(|| panic!("Invalid chunk size {}", chunk_size)));
+    }
+    index.set_paragraphs(matches.is_present("paragraphs"));
+    if let Some(cues) = matches.value_of("subtitle-cues") {
+        index.set_subtitle_cues(cues.parse().expect("The number of subtitle cues must be a positive integer"));
+    }
+    if let Some(prefix) = matches.value_of("key-prefix") {
+        index.set_key_prefix(prefix);
+    }
+    for (name, value) in tags(matches) {
+        index.set_tag(name, value);
+    }
+    if let Some(backend) = matches.value_of("backend") {
+        index.set_backend(backend.parse::<Backend>().unwrap_or_else(|error| panic!("{}", error)));
+    }
+    if let Some(hash_function) = matches.value_of("hash-function") {
+        index.set_hash_function(hash_function.parse::<HashFunction>().unwrap_or_else(|error| panic!("{}", error)));
+    }
+    if let Some(template) = matches.value_of("key-template") {
+        let mut bases: Vec<PathBuf> = matches.values_of("source").into_iter().flatten()
+            .map(Path::new)
+            .map(|source| if source.is_dir() { source.to_path_buf() } else { source.parent().map(Path::to_path_buf).unwrap_or_default() })
+            .collect();
+        bases.push(PathBuf::from("."));
+        let template = template.to_string();
+        index.set_key_mapper(move |path| {
+            // Relative to the source the file comes from
+            let base = bases.iter().find(|base| path.starts_with(base)).map(PathBuf::as_path).unwrap_or_else(|| Path::new(""));
+            expand_key_template(&template, base, path)
+        });
+    }
+    let mut report = IngestReport::default();
+    if let Some(sources) = matches.values_of("source") {
+        let (stdin, sources): (Vec<&str>, Vec<&str>) = sources.partition(|source| *source == "-");
+        if !stdin.is_empty() {
+            if matches.value_of("files-from") == Some("-") {
+                panic!("The standard input cannot be both a source and the list of files");
+            }
+            report.merge(index.ingest_from(ReaderSource::stdin(matches.value_of("stdin-key").unwrap())));
+        }
+        report.merge(index.ingest_many(sources));
+    }
+    if let Some(files_from) = matches.value_of("files-from") {
+        report.merge(index.ingest_files(read_file_list(files_from)));
+    }
+    let window = Duration::from_secs(matches.value_of("window").unwrap().parse().expect("The window must be a positive number of seconds"));
+    for syslog in matches.values_of("syslog").into_iter().flatten() {
+        report.merge(index.ingest_syslog(syslog, window));
+    }
+    if matches.is_present("journal") {
+        report.merge(index.ingest_journal(matches.value_of("since"), window));
+    }
+    #[cfg(feature = "postgres")]
+    if let Some(url) = matches.value_of("pg") {
+        report.merge(index.ingest_query(url, matches.value_of("query").unwrap()));
+    }
+    let ingested = ["source", "files-from", "syslog", "journal", "pg"].iter().any(|arg| matches.is_present(arg));
+    if ingested && Format::of(matches) == Format::Json {
+        print_json(&report);
+    } else if ingested {
+        for issue in &report.failed {
+            eprintln!("Failed {} : {}", issue.path.display(), issue.reason);
+        }
+        eprintln!("{} indexed, {} unchanged, {} skipped, {} failed ({} bytes)", report.indexed, report.unchanged, report.skipped.len(), report.failed.len(), report.bytes);
+    }
+    if let Some(dump_file) = dump_file {
+        if matches.is_present("sections") {
+            index.dump_sections(dump_file);
+        } else if matches.is_present("pretty") {
+            index.dump_pretty(dump_file);
+        } else {
+            index.dump(dump_file);
+        }
+    }
+}
+
+fn error_rate(matches: &ArgMatches) -> f32 {
+    match matches.value_of("error-rate").unwrap() {
+        "auto" => {
+            let sources: Vec<&str> = matches.values_of("source").expect("An automatic error rate needs a --source to scan").collect();
+            let memory_target = matches.value_of("memory-target").expect("An automatic error rate needs a --memory-target");
+            let memory_target = parse_size(memory_target).unwrap_or_else(|| panic!("Invalid memory size {}", memory_target));
+            let error_rate = auto_error_rate(&sources, memory_target);
+            eprintln!("Error rate {}", error_rate);
+            error_rate
+        },
+        error_rate => error_rate.parse().unwrap_or_else(|_| panic!("Invalid error rate {} : it must be a number, e.g. 0.00001, or auto", error_rate))
+    }
+}
+
+fn expand_key_template(template: &str, base: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(base).unwrap_or(path);
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    template.replace("{path}", &path.to_string_lossy())
+        .replace("{relpath}", &relative.to_string_lossy())
+        .replace("{name}", &name)
+}
+
+// A number of bytes, with an optional K, M or G suffix
+fn parse_size(size: &str) -> Option<usize> {
+    let size = size.trim();
+    let (digits, unit) = match size.char_indices().last()? {
+        (position, 'K') | (position, 'k') => (&size[..position], 1 << 10),
+        (position, 'M') | (position, 'm') => (&size[..position], 1 << 20),
+        (position, 'G') | (position, 'g') => (&size[..position], 1 << 30),
+        _ => (size, 1)
+    };
+    digits.parse::<usize>().ok()?.checked_mul(unit)
+}
+
+// Paths listed in a file or the standard input, separated by NUL characters if any, else by new lines
+fn read_file_list(files_from: &str) -> Vec<String> {
+    let mut content = Vec::new();
+    let read = if files_from == "-" {
+        io::stdin().lock().read_to_end(&mut content)
+    } else {
+        File::open(files_from).and_then(|mut file| file.read_to_end(&mut content))
+    };
+    read.expect(format!("Unable to read file list {}", files_from).as_str());
+    let content = String::from_utf8(content).expect("File list must be UTF-8 text");
+    let separator = if content.contains('\0') { '\0' } else { '\n' };
+    content.split(separator)
+        .map(|path| path.trim_end_matches('\r'))
+        .filter(|path| !path.is_empty())
+        .map(|path| path.to_string())
+        .collect()
+}
+
+fn prune(matches: &ArgMatches) {
+    let dump_file = dump_file(matches);
+    let mut index = restore(matches.value_of("restore").unwrap(), matches, MemoryStore::default());
+    let pruned = index.prune();
+    if Format::of(matches) == Format::Json {
+        print_json(&json!({ "pruned": pruned }));
+    } else {
+        let painter = Painter::new(matches);
+        for key in pruned {
+            println!("{}", painter.paint(&key, RED));
+        }
     }
-    if let Some(dump_file) = matches.value_of("dump") {
+    if let Some(dump_file) = dump_file {
         index.dump(dump_file);
     }
 }
 
+fn migrate(matches: &ArgMatches) {
+    let restore_file = matches.value_of("restore").unwrap();
+    let dump_file = matches.value_of("dump").unwrap_or(restore_file);
+    let previous = FsIndex::migrate(restore_file, dump_file);
+    let version = FsIndex::dump_version(dump_file);
+    if Format::of(matches) == Format::Json {
+        print_json(&json!({ "dump": dump_file, "previous_version": previous, "version": version }));
+    } else {
+        println!("{} : format version {} migrated to version {}", dump_file, previous, version);
+    }
+}
+
+fn verify(matches: &ArgMatches) {
+    let restore_file = matches.value_of("restore").unwrap();
+    if matches.is_present("schema") {
+        let violations = validate_dump_file(restore_file);
+        if !violations.is_empty() {
+            if Format::of(matches) == Format::Json {
+                print_json(&json!({ "dump": restore_file, "valid": false, "violations": violations }));
+            } else {
+                for violation in &violations {
+                    eprintln!("{}", violation);
+                }
+                eprintln!("{} : {} schema violations", restore_file, violations.len());
+            }
+            process::exit(1);
+        }
+    }
+    let index = FsIndex::restore(restore_file);
+    let version = FsIndex::dump_version(restore_file);
+    if Format::of(matches) == Format::Json {
+        print_json(&json!({
+            "dump": restore_file,
+            "valid": true,
+            "version": version,
+            "documents": index.keys().count(),
+            "error_rate": index.error_rate(),
+            "memory_usage": index.memory_usage()
+        }));
+    } else {
+        println!("{} : valid, format version {}, {}, about {} bytes in memory", restore_file, version, index, index.memory_usage());
+    }
+}
+
+fn inspect(matches: &ArgMatches) {
+    let dump_file = matches.value_of("DUMP").unwrap();
+    let keys = matches.value_of("keys").unwrap().parse().expect("The number of keys must be a positive integer");
+    let info = DumpInfo::read(dump_file, keys);
+    if Format::of(matches) == Format::Json {
+        let mut info = serde_json::to_value(info).expect("Impossible to serialize output");
+        info["dump"] = json!(dump_file);
+        print_json(&info);
+    } else {
+        println!("File        {}", dump_file);
+        println!("{}", info);
+    }
+}
+
+fn doctor(matches: &ArgMatches) {
+    let index = FsIndex::restore(matches.value_of("restore").unwrap());
+    let findings = index.diagnose(Path::new(matches.value_of("source").unwrap()));
+    if Format::of(matches) == Format::Json {
+        let findings: Vec<serde_json::Value> = findings.iter().map(|finding| {
+            let mut entry = serde_json::to_value(finding).expect("Impossible to serialize output");
+            entry["suggestion"] = json!(finding.problem.suggestion());
+            entry
+        }).collect();
+        print_json(&findings);
+    } else {
+        let painter = Painter::new(matches);
+        for finding in &findings {
+            println!("{} {} : {}", painter.paint(problem_label(finding.problem), RED), finding.subject, finding.detail);
+            println!("  {}", finding.problem.suggestion());
+        }
+        if findings.is_empty() {
+            println!("No problem found");
+        }
+    }
+    if !findings.is_empty() {
+        process::exit(1);
+    }
+}
+
+fn dups(matches: &ArgMatches) {
+    let index = restore(matches.value_of("restore").unwrap(), matches, MemoryStore::default());
+    let similarity = matches.value_of("similarity").unwrap().parse().expect("The similarity must be a number between 0 and 1");
+    let sets = index.duplicates(similarity);
+    if Format::of(matches) == Format::Json {
+        print_json(&sets);
+        return;
+    }
+    let painter = Painter::new(matches);
+    for set in &sets {
+        if set.identical {
+            println!("{}", painter.paint("identical", YELLOW));
+        } else {
+            println!("{}", painter.paint(&format!("similar {:.2}", set.similarity), YELLOW));
+        }
+        for key in &set.keys {
+            println!("  {}", key);
+        }
+    }
+    if sets.is_empty() {
+        println!("No duplicate found");
+    }
+}
+
+fn problem_label(problem: Problem) -> &'static str {
+    match problem {
+        Problem::Missing => "missing",
+        Problem::Unreadable => "unreadable",
+        Problem::PermissionDenied => "permission denied",
+        Problem::Encoding => "encoding",
+        Problem::Saturated => "saturated"
+    }
+}
+
+fn refresh(matches: &ArgMatches) {
+    let dump_file = dump_file(matches);
+    let mut index = restore(matches.value_of("restore").unwrap(), matches, MemoryStore::default());
+    let refresh = index.refresh();
+    if Format::of(matches) == Format::Json {
+        print_json(&refresh);
+    } else {
+        let painter = Painter::new(matches);
+        for key in refresh.pruned {
+            println!("{} {}", painter.paint("D", RED), key);
+        }
+        for key in refresh.updated {
+            println!("{} {}", painter.paint("M", YELLOW), key);
+        }
+    }
+    if let Some(dump_file) = dump_file {
+        index.dump(dump_file);
+    }
+}
+
+fn compact(matches: &ArgMatches) {
+    let dump_file = dump_file(matches);
+    let mut index = restore(matches.value_of("restore").unwrap(), matches, MemoryStore::default());
+    let compaction = index.compact();
+    if Format::of(matches) == Format::Json {
+        print_json(&compaction);
+    } else {
+        let painter = Painter::new(matches);
+        for key in &compaction.rebuilt {
+            println!("{} {}", painter.paint("C", YELLOW), key);
+        }
+        for key in &compaction.skipped {
+            println!("{} {} (cannot be read again)", painter.paint("S", RED), key);
+        }
+        println!("{} filters rebuilt, from {} to {} bits", compaction.rebuilt.len(), compaction.bits_before, compaction.bits_after);
+    }
+    if let Some(dump_file) = dump_file {
+        index.dump(dump_file);
+    }
+}
+
+fn search(matches: &ArgMatches) {
+    let index = restore(matches.value_of("restore").unwrap(), matches, MemoryStore::default());
+    let keywords = matches.value_of("query").or_else(|| matches.value_of("QUERY")).unwrap();
+    let config = load_config(matches);
+    let keywords = config.expand(keywords).unwrap_or_else(|| panic!("Unknown query {}", keywords));
+    let query = Query::parse(keywords).unwrap_or_else(|error| panic!("Invalid query {} : {}", keywords, error));
+    let glob = matches.value_of("path").map(Glob::new);
+    let tags = tags(matches);
+    let offset = matches.value_of("offset").map(|offset| offset.parse().expect("The offset must be a positive integer")).unwrap_or(0);
+    let limit = matches.value_of("limit").map(|limit| limit.parse().expect("The limit must be a positive integer")).unwrap_or(usize::MAX);
+    let deadline = matches.value_of("timeout").map(|timeout| Instant::now() + Duration::from_secs_f64(timeout.parse().expect("The timeout must be a number of seconds")));
+    let truncated = Cell::new(false);
+    // Past the deadline, the remaining documents are left out without being evaluated
+    let hits = index.query_where(&query, |key| {
+        if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+            truncated.set(true);
+            return false;
+        }
+        glob.as_ref().map_or(true, |glob| glob.matches(key))
+            && tags.iter().all(|(name, value)| index.document(key).map_or(false, |document| document.has_tag(name, value)))
+    });
+    let hits: Vec<&String> = match matches.value_of("sort") {
+        Some(sort) => {
+            let mut hits: Vec<&String> = hits.collect();
+            index.sort_hits(&mut hits, sort.parse::<Sort>().unwrap_or_else(|error| panic!("{}", error)), &query);
+            hits.into_iter().skip(offset).take(limit).collect()
+        },
+        None => hits.skip(offset).take(limit).collect()
+    };
+    if truncated.get() {
+        eprintln!("Search stopped after {} seconds, some hits may be missing", matches.value_of("timeout").unwrap());
+    }
+    if let Some(command) = matches.value_of("exec") {
+        let jobs = matches.value_of("jobs").unwrap().parse().expect("The number of jobs must be a positive integer");
+        let mut paths = Vec::new();
+        for key in hits {
+            if let Some(path) = index.document(key).and_then(|document| document.path.as_ref()) {
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+        execute(command, &paths, jobs);
+        return;
+    }
+    if Format::of(matches) == Format::Json {
+        let hits: Vec<serde_json::Value> = hits.into_iter().map(|key| {
+            let mut hit = json!({ "key": key, "path": index.document(key).and_then(|document| document.path.as_ref()) });
+            if matches.is_present("saturation") {
+                hit["false_positive_rate"] = json!(index.false_positive_rate(key).unwrap_or(0.0));
+            }
+            hit
+        }).collect();
+        print_json(&hits);
+        return;
+    }
+    let painter = Painter::new(matches);
+    let keywords = query.keywords();
+    for key in hits {
+        let false_positive_rate = if matches.is_present("saturation") { index.false_positive_rate(key).unwrap_or(0.0) } else { 0.0 };
+        if false_positive_rate > index.error_rate() as f64 {
+            println!("{} {}", painter.highlight(key, &keywords), painter.paint(&format!("(saturated, false positive rate {:.6})", false_positive_rate), YELLOW));
+        } else {
+            println!("{}", painter.highlight(key, &keywords));
+        }
+    }
+}
+
+// Tags of the command line, given as name=value
+fn tags<'a>(matches: &'a ArgMatches) -> Vec<(&'a str, &'a str)> {
+    matches.values_of("tag").into_iter().flatten()
+        .map(|tag| tag.split_once('=').unwrap_or_else(|| panic!("Invalid tag {}, expected name=value", tag)))
+        .collect()
+}
+
+// Run `command` with the shell for each path, at most `jobs` at the same time
+fn execute(command: &str, paths: &[&PathBuf], jobs: usize) {
+    // The path is given to the shell as a positional parameter, so it never needs quoting
+    let script = shell_script(command);
+    let mut running: VecDeque<(Child, &PathBuf)> = VecDeque::new();
+    for path in paths {
+        if running.len() >= jobs.max(1) {
+            wait(running.pop_front().unwrap());
+        }
+        let child = Command::new("sh").arg("-c").arg(&script).arg("cli-bloom").arg(path).spawn()
+            .expect("Impossible to run the shell");
+        running.push_back((child, path));
+    }
+    for child in running {
+        wait(child);
+    }
+}
+
+// Script of `command` taking the path as $1, each {} being replaced by $1 quoted as fits where it stands, e.g. in '{}'
+fn shell_script(command: &str) -> String {
+    if !command.contains("{}") {
+        return format!("{} \"$1\"", command);
+    }
+    let mut script = String::with_capacity(command.len());
+    let mut quote = None;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '{' && chars.peek() == Some(&'}') {
+            chars.next();
+            script.push_str(match quote {
+                None => "\"$1\"",
+                Some('"') => "${1}",
+                // A single quoted string is closed around the parameter
+                _ => "'\"$1\"'"
+            });
+            continue;
+        }
+        script.push(c);
+        match (quote, c) {
+            // The escaped character is neither a quote nor the start of {}
+            (None, '\\') | (Some('"'), '\\') => script.extend(chars.next()),
+            (None, '\'') | (None, '"') => quote = Some(c),
+            (Some(open), _) if open == c => quote = None,
+            _ => {}
+        }
+    }
+    script
+}
+
+fn wait((mut child, path): (Child, &PathBuf)) {
+    let status = child.wait().expect("Impossible to wait for command");
+    if !status.success() {
+        eprintln!("Command failed for {} : {}", path.display(), status);
+    }
+}
+
+// The config file given on the command line, else the default one if it exists
+fn load_config(matches: &ArgMatches) -> Config {
+    match matches.value_of("config") {
+        Some(config_file) => Config::load(Path::new(config_file)),
+        None => match Config::default_path() {
+            Some(path) if path.is_file() => Config::load(&path),
+            _ => Config::default()
+        }
+    }
+}
+
+fn list(matches: &ArgMatches) {
+    let index = restore(matches.value_of("restore").unwrap(), matches, MemoryStore::default());
+    let output: Box<dyn Write> = match matches.value_of("output") {
+        Some(output_file) => Box::new(File::create(output_file).expect(format!("Impossible to create output file {}", output_file).as_str())),
+        None => Box::new(io::stdout().lock())
+    };
+    let exported = if Format::of(matches) == Format::Json {
+        let keys: Vec<&str> = index.keys().collect();
+        serde_json::to_writer(output, &keys).map_err(io::Error::from)
+    } else {
+        index.export_keys(output)
+    };
+    exported.expect("Impossible to write keys");
+}
+
+fn stats(matches: &ArgMatches) {
+    let index = restore(matches.value_of("restore").unwrap(), matches, MemoryStore::default());
+    let stats = index.stats();
+    if Format::of(matches) == Format::Json {
+        print_json(&stats);
+    } else {
+        println!("{} documents : {} fresh, {} modified, {} missing, {} unknown", stats.documents, stats.fresh, stats.modified, stats.missing, stats.unknown);
+        println!("{} tokens, {} distinct tokens summed over the documents", stats.tokens, stats.unique_tokens);
+    }
+}
+
+fn diff(matches: &ArgMatches) {
+    let old = FsIndex::restore(matches.value_of("OLD").unwrap());
+    let new = FsIndex::restore(matches.value_of("NEW").unwrap());
+    let changes = old.diff(&new);
+    if Format::of(matches) == Format::Json {
+        print_json(&changes);
+    } else {
+        let painter = Painter::new(matches);
+        for key in &changes.added {
+            println!("{} {}", painter.paint("A", GREEN), key);
+        }
+        for key in &changes.removed {
+            println!("{} {}", painter.paint("D", RED), key);
+        }
+        for key in &changes.modified {
+            println!("{} {}", painter.paint("M", YELLOW), key);
+        }
+    }
+}
+
+fn snapshots(matches: &ArgMatches) {
+    let generations = Generations::of(matches.value_of("restore").unwrap());
+    if Format::of(matches) == Format::Json {
+        if matches.is_present("save") {
+            print_json(&json!({ "saved": generations.save(matches.value_of("label")) }));
+        } else if let Some(name) = matches.value_of("checkout") {
+            print_json(&json!({ "restored": generations.checkout(name) }));
+        } else if let Some(name) = matches.value_of("delete") {
+            print_json(&json!({ "deleted": generations.delete(name) }));
+        } else {
+            print_json(&generations.list());
+        }
+    } else if matches.is_present("save") {
+        let generation = generations.save(matches.value_of("label"));
+        println!("Saved {}", generation.path.display());
+    } else if let Some(name) = matches.value_of("checkout") {
+        let generation = generations.checkout(name);
+        println!("Restored {} from {}", generation.name(), utc_time(generation.timestamp));
+    } else if let Some(name) = matches.value_of("delete") {
+        let generation = generations.delete(name);
+        println!("Deleted {} from {}", generation.name(), utc_time(generation.timestamp));
+    } else {
+        for generation in generations.list() {
+            println!("{}  {}  {}", utc_time(generation.timestamp), generation.label.as_deref().unwrap_or("-"), generation.path.display());
+        }
+    }
+}
+
+// Date and time of a Unix timestamp, as YYYY-MM-DD HH:MM:SS UTC
+fn utc_time(timestamp: u64) -> String {
+    let (days, seconds) = (timestamp / 86400, timestamp % 86400);
+    // Civil date from the number of days since 1970-01-01, counting eras of 400 years from 0000-03-01
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", year, month, day, seconds / 3600, seconds % 3600 / 60, seconds % 60)
+}
+
+// Restore the dump with --only and --map-prefix applied
+// The --dump file, which cannot be the restored dump when --only or --map-prefix restore part of it or under other keys
+fn dump_file<'a>(matches: &'a ArgMatches) -> Option<&'a str> {
+    let dump_file = matches.value_of("dump")?;
+    let partial = matches.is_present("only") || matches.is_present("map-prefix");
+    if partial && matches.value_of("restore").map_or(false, |restore_file| same_file(restore_file, dump_file)) {
+        panic!("Refusing to overwrite {} with a partially restored index, dump it to another file or leave out --only and --map-prefix", dump_file);
+    }
+    Some(dump_file)
+}
+
+fn same_file(first: &str, second: &str) -> bool {
+    match (fs::canonicalize(first), fs::canonicalize(second)) {
+        (Ok(first), Ok(second)) => first == second,
+        _ => Path::new(first) == Path::new(second)
+    }
+}
+
+fn restore<S: IndexStore>(restore_file: &str, matches: &ArgMatches, store: S) -> FsIndex<S> {
+    let mut index = match matches.value_of("only") {
+        Some(only) => FsIndex::restore_filtered_with_store(restore_file, only.trim_end_matches('*'), store),
+        None => FsIndex::restore_with_store(restore_file, store)
+    };
+    for mapping in matches.values_of("map-prefix").into_iter().flatten() {
+        let (from, to) = mapping.split_once('=').unwrap_or_else(|| panic!("Invalid prefix mapping {}, expected /old/root=/new/root", mapping));
+        index.map_prefix(from, to).unwrap_or_else(|collision| panic!("Unable to map {} : {}", mapping, collision));
+    }
+    index
+}
+
+#[cfg(feature = "tui")]
+fn tui(matches: &ArgMatches) {
+    let index = FsIndex::restore(matches.value_of("restore").unwrap());
+    cli_bloom::browse(&index).expect("Terminal error");
+}
+
+#[cfg(feature = "kafka")]
+fn consume(matches: &ArgMatches) {
+    let mut index = match matches.value_of("restore") {
+        Some(restore_file) => FsIndex::restore(restore_file),
+        None => FsIndex::new(matches.value_of("error-rate").unwrap().parse().expect("The error rate must be a number"))
+    };
+    let mut consumer = cli_bloom::TopicConsumer::connect(matches.value_of("brokers").unwrap(), matches.value_of("topic").unwrap(), matches.value_of("group").unwrap());
+    if let Some(header) = matches.value_of("key-header") {
+        consumer = consumer.key_header(header);
+    }
+    let every = Duration::from_secs(matches.value_of("dump-every").unwrap().parse().expect("The number of seconds between dumps must be a positive integer"));
+    // SIGINT and SIGTERM end the current batch, which is dumped and committed before exiting
+    let cancel = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    for signal in &[signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+        signal_hook::flag::register(*signal, cancel.clone()).expect("Unable to handle the termination signals");
+    }
+    index.set_cancel_flag(cancel.clone());
+    loop {
+        let report = index.consume(&consumer, every);
+        for issue in &report.failed {
+            eprintln!("Failed {} : {}", issue.path.display(), issue.reason);
+        }
+        index.dump(matches.value_of("dump").unwrap());
+        // Only once the messages are in the dump, so that a crash consumes them again
+        consumer.commit();
+        eprintln!("{} messages indexed, {} skipped, {} failed ({} bytes)", report.indexed, report.skipped.len(), report.failed.len(), report.bytes);
+        if cancel.load(std::sync::atomic::Ordering::Relaxed) {
+            break;
+        }
+    }
+}
+
+#[cfg(feature = "imap")]
+fn mail(matches: &ArgMatches) {
+    let mut index = match matches.value_of("restore") {
+        Some(restore_file) => FsIndex::restore(restore_file),
+        None => FsIndex::new(matches.value_of("error-rate").unwrap().parse().expect("The error rate must be a number"))
+    };
+    let password = env::var("IMAP_PASSWORD").expect("The password must be in the IMAP_PASSWORD environment variable");
+    let account = cli_bloom::ImapAccount::new(matches.value_of("host").unwrap(), matches.value_of("user").unwrap(), &password)
+        .port(matches.value_of("port").unwrap().parse().expect("The port must be a number"));
+    let folders: Vec<&str> = matches.values_of("folder").unwrap().collect();
+    let report = index.sync_mailbox(&account, &folders);
+    if Format::of(matches) == Format::Json {
+        print_json(&report);
+    } else {
+        for issue in &report.failed {
+            eprintln!("Failed {} : {}", issue.path.display(), issue.reason);
+        }
+        eprintln!("{} messages indexed, {} unchanged, {} failed ({} bytes)", report.indexed, report.unchanged, report.failed.len(), report.bytes);
+    }
+    index.dump(matches.value_of("dump").unwrap());
+}
+
+#[cfg(feature = "crawl")]
+fn crawl(matches: &ArgMatches) {
+    let mut index = match matches.value_of("restore") {
+        Some(restore_file) => FsIndex::restore(restore_file),
+        None => FsIndex::new(matches.value_of("error-rate").unwrap().parse().expect("The error rate must be a number"))
+    };
+    let depth = matches.value_of("depth").unwrap().parse().expect("The depth must be a positive integer");
+    let concurrency = matches.value_of("concurrency").unwrap().parse().expect("The concurrency must be a positive integer");
+    let rate = matches.value_of("rate").unwrap().parse().expect("The rate must be a number");
+    let politeness = cli_bloom::Politeness::new(concurrency, rate);
+    index.set_politeness(if matches.is_present("ignore-robots") { politeness.ignoring_robots() } else { politeness });
+    let report = index.crawl(matches.value_of("url").unwrap(), depth);
+    if Format::of(matches) == Format::Json {
+        print_json(&report);
+    } else {
+        for issue in &report.failed {
+            eprintln!("Failed {} : {}", issue.path.display(), issue.reason);
+        }
+        eprintln!("{} pages indexed, {} skipped, {} failed ({} bytes)", report.indexed, report.skipped.len(), report.failed.len(), report.bytes);
+    }
+    index.dump(matches.value_of("dump").unwrap());
+}
+
+#[cfg(feature = "parquet")]
+fn parquet(matches: &ArgMatches) {
+    let index = FsIndex::restore(matches.value_of("restore").unwrap());
+    index.export_parquet(matches.value_of("output").unwrap());
+}
+
+// Output of the commands, according to --format
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Plain,
+    Json
+}
+
+impl Format {
+    fn of(matches: &ArgMatches) -> Self {
+        match matches.value_of("format") {
+            Some("json") => Format::Json,
+            _ => Format::Plain
+        }
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    println!("{}", serde_json::to_string(value).expect("Impossible to serialize output"));
+}
+
+// One JSON line per ingestion event, for --events jsonl, flushed as soon as written for live consumers
+struct EventStream {
+    output: Mutex<Box<dyn Write + Send>>
+}
+
+impl EventStream {
+    // On the standard error with --format json, which keeps the standard output for the report
+    fn open(path: Option<&str>, format: Format) -> Self {
+        let output: Box<dyn Write + Send> = match path {
+            Some(path) => Box::new(File::create(path).unwrap_or_else(|error| panic!("Impossible to create events file {} : {}", path, error))),
+            None if format == Format::Json => Box::new(io::stderr()),
+            None => Box::new(io::stdout())
+        };
+        EventStream { output: Mutex::new(output) }
+    }
+
+    fn write(&self, event: serde_json::Value) {
+        let mut output = self.output.lock().unwrap();
+        writeln!(output, "{}", event).and_then(|_| output.flush()).expect("Impossible to write event");
+    }
+}
+
+impl ProgressObserver for EventStream {
+    fn on_file_start(&self, path: &Path) {
+        self.write(json!({ "event": "start", "path": path }));
+    }
+
+    fn on_file_done(&self, path: &Path, outcome: Outcome, bytes: u64) {
+        self.write(json!({ "event": "done", "path": path, "outcome": outcome, "bytes": bytes }));
+    }
+
+    fn on_skip(&self, path: &Path, reason: &str) {
+        self.write(json!({ "event": "skip", "path": path, "reason": reason }));
+    }
+
+    fn on_error(&self, path: &Path, reason: &str) {
+        self.write(json!({ "event": "error", "path": path, "reason": reason }));
+    }
+
+    fn on_finish(&self, report: &IngestReport) {
+        self.write(json!({ "event": "finish", "report": report }));
+    }
+}
+
+// A new empty directory of filters spilled by --max-memory, removed with its content when dropped, even after a panic
+struct SpillDirectory {
+    path: PathBuf
+}
+
+impl SpillDirectory {
+    // Never an existing directory, whose files would be taken for filters
+    fn create() -> io::Result<Self> {
+        for attempt in 0.. {
+            let path = env::temp_dir().join(format!("cli-bloom-spill-{}-{}", process::id(), attempt));
+            match fs::create_dir(&path) {
+                Ok(()) => return Ok(SpillDirectory { path }),
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(error) => return Err(error)
+            }
+        }
+        unreachable!()
+    }
+}
+
+impl Drop for SpillDirectory {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+const RED: &str = "31";
+const GREEN: &str = "32";
+const YELLOW: &str = "33";
+const BOLD_RED: &str = "1;31";
+
+// ANSI colors of the standard output, according to --color and NO_COLOR
+struct Painter {
+    enabled: bool
+}
+
+impl Painter {
+    fn new(matches: &ArgMatches) -> Self {
+        let no_color = env::var_os("NO_COLOR").map(|value| !value.is_empty()).unwrap_or(false);
+        let enabled = match matches.value_of("color") {
+            Some("always") => true,
+            Some("never") => false,
+            _ => !no_color && io::stdout().is_terminal()
+        };
+        Painter { enabled }
+    }
+
+    fn paint(&self, text: &str, color: &str) -> String {
+        if self.enabled {
+            format!("\x1b[{}m{}\x1b[0m", color, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    // Highlight the keywords found in `text`, ignoring case
+    fn highlight(&self, text: &str, keywords: &[&str]) -> String {
+        if !self.enabled {
+            return text.to_string();
+        }
+        let lowercase = text.to_ascii_lowercase();
+        let keywords: Vec<String> = keywords.iter().map(|keyword| keyword.to_ascii_lowercase()).filter(|keyword| !keyword.is_empty()).collect();
+        let mut highlighted = String::new();
+        let mut position = 0;
+        while position < text.len() {
+            match keywords.iter().find(|keyword| lowercase[position..].starts_with(keyword.as_str())) {
+                Some(keyword) => {
+                    highlighted.push_str(&self.paint(&text[position..position + keyword.len()], BOLD_RED));
+                    position += keyword.len();
+                },
+                None => {
+                    let character = text[position..].chars().next().unwrap();
+                    highlighted.push(character);
+                    position += character.len_utf8();
+                }
+            }
+        }
+        highlighted
+    }
+}
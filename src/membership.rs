@@ -0,0 +1,267 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use serde_json::value::RawValue;
+use siphasher::sip::SipHasher24;
+use twox_hash::XxHash64;
+use crate::errors::Error;
+use crate::filter::{Filter, single_filter_json};
+
+// Answers whether the tokens of a document may contain keywords, the part of a filter which depends on its backend
+pub(crate) trait Membership: Send + Sync {
+    // True if every token of `keywords` may be in the document, with false positives
+    fn contains(&self, keywords: &str) -> Result<bool, Error> {
+        let tokens: Vec<String> = tokens(keywords).collect();
+        if tokens.is_empty() {
+            return Ok(false);
+        }
+        self.contains_tokens(&tokens)
+    }
+
+    // Same as `contains` with the normalized tokens of the keywords, never empty
+    fn contains_tokens(&self, tokens: &[String]) -> Result<bool, Error>;
+
+    fn bits(&self) -> usize;
+
+    fn hash_count(&self) -> usize;
+
+    // Proportion of the structure holding tokens
+    fn fill_ratio(&self) -> f64;
+
+    fn false_positive_rate(&self) -> f64 {
+        self.fill_ratio().powi(self.hash_count() as i32)
+    }
+
+    fn backend(&self) -> Backend;
+
+    // Representation of the structure inside the "bloom_filters" map of a dump
+    fn to_raw(&self) -> Box<RawValue>;
+
+    // Representation of the structure alone, e.g. in a file of a directory store
+    fn to_json(&self) -> String {
+        single_filter_json(self.false_positive_rate() as f32, &self.to_raw())
+    }
+
+    // A copy of the structure without the tokens of `content`, if the backend can remove tokens
+    fn without(&self, _content: &str) -> Option<Box<dyn Membership>> {
+        None
+    }
+
+    // Filters stacked in the structure, oldest first, if it is made of several
+    fn layers(&self) -> Option<&[Filter]> {
+        None
+    }
+
+    // Bits of the structure, if it is a plain bit array
+    fn bitfield(&self) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// The structure holding the tokens of each document.
+///
+/// Bloom filters are always available, the other backends are enabled by the Cargo feature of the same name, see [`Backend::is_available`].
+/// Each filter records its backend in the dump, so an index can mix filters of several backends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Backend {
+    /// A bloom filter, with the layout of the `index-bloom` crate.
+    Bloom,
+    /// An xor filter, smaller than a bloom filter with the same error rate, for documents which never change.
+    Xor,
+    /// A cuckoo filter, close to an xor filter in size and able to remove tokens.
+    Cuckoo,
+    /// A bloom filter with a counter instead of each bit, 8 times larger and able to remove tokens.
+    Counting
+}
+
+impl Backend {
+    pub(crate) fn is_bloom(&self) -> bool {
+        *self == Backend::Bloom
+    }
+
+    /// Return whether filters of this backend can be built, i.e. whether the crate was built with its Cargo feature.
+    pub fn is_available(&self) -> bool {
+        match self {
+            Backend::Bloom => true,
+            Backend::Xor => cfg!(feature = "xor"),
+            Backend::Cuckoo => cfg!(feature = "cuckoo"),
+            Backend::Counting => cfg!(feature = "counting")
+        }
+    }
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Backend::Bloom
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Backend::Bloom => "bloom",
+            Backend::Xor => "xor",
+            Backend::Cuckoo => "cuckoo",
+            Backend::Counting => "counting"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Parse the name of a backend, as displayed, refusing the backends which are not available.
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let backend = match name {
+            "bloom" => Backend::Bloom,
+            "xor" => Backend::Xor,
+            "cuckoo" => Backend::Cuckoo,
+            "counting" => Backend::Counting,
+            _ => return Err(format!("Unknown filter backend {}", name))
+        };
+        if backend.is_available() {
+            Ok(backend)
+        } else {
+            Err(format!("Filter backend {} needs the {} feature", name, name))
+        }
+    }
+}
+
+// Tokens of `text`, normalized like the index-bloom ones: lowercase and without punctuation
+pub(crate) fn tokens(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split_whitespace()
+        .map(|word| word.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect::<String>())
+        .filter(|token| !token.is_empty())
+}
+
+/// The hash function of the tokens in the filters.
+///
+/// Each filter records its hash function in the dump, so that it is restored with the same one.
+/// Bloom filters restored from dumps written before version 3 keep the hash function of the `index-bloom` crate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashFunction {
+    /// FNV-1a, the fastest on short tokens, but easy to flood with crafted colliding tokens.
+    Fnv,
+    /// xxHash64, the fastest on long tokens.
+    XxHash,
+    /// SipHash-2-4 with a 128 bits key, resistant to colliding tokens as long as the key is secret.
+    SipHash {
+        key: [u64; 2]
+    }
+}
+
+impl HashFunction {
+    /// SipHash-2-4 with a random key.
+    pub fn random_sip_hash() -> Self {
+        let random = RandomState::new();
+        HashFunction::SipHash {
+            key: [random.build_hasher().finish(), random.build_hasher().finish() ^ 0x9e3779b97f4a7c15]
+        }
+    }
+
+    pub(crate) fn is_fnv(&self) -> bool {
+        *self == HashFunction::Fnv
+    }
+
+    /// Return the 64 bits hash of a normalized token, mixed with `seed` by the SplitMix64 finalizer.
+    ///
+    /// The hash is stable across runs and platforms, as filters are dumped.
+    pub fn hash(&self, token: &str, seed: u64) -> u64 {
+        let hash = match self {
+            HashFunction::Fnv => {
+                let mut hash: u64 = 0xcbf29ce484222325;
+                for byte in token.bytes() {
+                    hash ^= byte as u64;
+                    hash = hash.wrapping_mul(0x100000001b3);
+                }
+                hash
+            },
+            HashFunction::XxHash => {
+                let mut hasher = XxHash64::with_seed(0);
+                hasher.write(token.as_bytes());
+                hasher.finish()
+            },
+            HashFunction::SipHash { key } => {
+                let mut hasher = SipHasher24::new_with_keys(key[0], key[1]);
+                hasher.write(token.as_bytes());
+                hasher.finish()
+            }
+        };
+        mix(hash.wrapping_add(seed))
+    }
+}
+
+impl Default for HashFunction {
+    fn default() -> Self {
+        HashFunction::Fnv
+    }
+}
+
+/// Parse the name of a hash function: `fnv`, `xxhash` or `siphash`, the latter with a random key.
+impl FromStr for HashFunction {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "fnv" => Ok(HashFunction::Fnv),
+            "xxhash" => Ok(HashFunction::XxHash),
+            "siphash" => Ok(HashFunction::random_sip_hash()),
+            _ => Err(format!("Unknown hash function {}", name))
+        }
+    }
+}
+
+// Positions of the token hashed to `key` among `size` ones, `count` of them chosen by double hashing
+pub(crate) fn double_hashing(key: u64, count: usize, size: usize) -> impl Iterator<Item = usize> {
+    let step = mix(key) | 1;
+    (0..count as u64).map(move |index| (key.wrapping_add(index.wrapping_mul(step)) % size as u64) as usize)
+}
+
+pub(crate) fn mix(value: u64) -> u64 {
+    let mut value = value.wrapping_add(0x9e3779b97f4a7c15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d049bb133111eb);
+    value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalized_tokens() {
+        assert_eq!(vec!["word1", "word2", "été"], tokens("Word1, word2!\n ... Été").collect::<Vec<String>>());
+    }
+
+    #[test]
+    fn hash_functions() {
+        let sip_hash = HashFunction::SipHash { key: [1, 2] };
+        assert_eq!(sip_hash.hash("word1", 0), sip_hash.hash("word1", 0));
+        assert_ne!(sip_hash.hash("word1", 0), HashFunction::SipHash { key: [1, 3] }.hash("word1", 0));
+        assert_ne!(HashFunction::Fnv.hash("word1", 0), HashFunction::XxHash.hash("word1", 0));
+        assert_ne!(HashFunction::Fnv.hash("word1", 0), HashFunction::Fnv.hash("word1", 1));
+        assert!(matches!("siphash".parse(), Ok(HashFunction::SipHash { .. })));
+        assert_eq!("{\"siphash\":{\"key\":[1,2]}}", serde_json::to_string(&sip_hash).unwrap());
+    }
+
+    #[test]
+    fn backend_names() {
+        assert_eq!(Ok(Backend::Bloom), "bloom".parse());
+        assert_eq!("bloom", Backend::Bloom.to_string());
+        assert!("unknown".parse::<Backend>().is_err());
+    }
+
+    #[test]
+    #[cfg(not(feature = "xor"))]
+    fn unavailable_backend() {
+        assert!(!Backend::Xor.is_available());
+        assert_eq!(Err("Filter backend xor needs the xor feature".to_string()), "xor".parse::<Backend>());
+        assert_eq!("xor", Backend::Xor.to_string());
+    }
+}
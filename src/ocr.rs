@@ -0,0 +1,92 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const IMAGE_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "tif", "tiff", "bmp", "gif"];
+
+// Distinguishes the working directories of concurrent recognitions
+static WORK_DIRECTORIES: AtomicUsize = AtomicUsize::new(0);
+
+// Whether the text of files with the lowercase `extension` must be recognized
+pub(crate) fn is_scan_extension(extension: &str) -> bool {
+    extension == "pdf" || IMAGE_EXTENSIONS.contains(&extension)
+}
+
+// Text of an image or a PDF held in memory, recognized from a temporary copy
+pub(crate) fn recognize_bytes(bytes: &[u8]) -> io::Result<String> {
+    let directory = std::env::temp_dir().join(format!("cli-bloom-ocr-{}-{}", std::process::id(), WORK_DIRECTORIES.fetch_add(1, Ordering::Relaxed)));
+    fs::create_dir_all(&directory)?;
+    // tesseract recognizes the format of images from their content, PDFs are told by their signature
+    let name = if bytes.starts_with(b"%PDF") { "scan.pdf" } else { "scan.png" };
+    let scan = directory.join(name);
+    let text = fs::write(&scan, bytes).and_then(|_| recognize(&scan));
+    let _ = fs::remove_dir_all(&directory);
+    text
+}
+
+// Text of an image, or of a PDF falling back to the recognition of its rendered pages when it has no text layer
+pub(crate) fn recognize(path: &Path) -> io::Result<String> {
+    if extension(path).as_deref() == Some("pdf") {
+        let text = run(Command::new("pdftotext").arg(path).arg("-"))?;
+        if !text.trim().is_empty() {
+            return Ok(text);
+        }
+        return recognize_pages(path);
+    }
+    tesseract(path)
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension().and_then(|extension| extension.to_str()).map(|extension| extension.to_ascii_lowercase())
+}
+
+fn recognize_pages(path: &Path) -> io::Result<String> {
+    let directory = std::env::temp_dir().join(format!("cli-bloom-ocr-{}-{}", std::process::id(), WORK_DIRECTORIES.fetch_add(1, Ordering::Relaxed)));
+    fs::create_dir_all(&directory)?;
+    let text = render_and_recognize(path, &directory);
+    let _ = fs::remove_dir_all(&directory);
+    text
+}
+
+fn render_and_recognize(path: &Path, directory: &Path) -> io::Result<String> {
+    run(Command::new("pdftoppm").args(["-r", "300", "-png"]).arg(path).arg(directory.join("page")))?;
+    // Page numbers are zero padded, so names sort in page order
+    let mut pages = fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()?;
+    pages.sort();
+    let mut text = String::new();
+    for page in pages {
+        text.push_str(&tesseract(&page)?);
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+fn tesseract(image: &Path) -> io::Result<String> {
+    run(Command::new("tesseract").arg(image).arg("stdout"))
+}
+
+fn run(command: &mut Command) -> io::Result<String> {
+    let output = command.output()?;
+    if !output.status.success() {
+        let message = format!("{:?} failed: {}", command, String::from_utf8_lossy(&output.stderr).trim());
+        return Err(io::Error::new(io::ErrorKind::Other, message));
+    }
+    String::from_utf8(output.stdout).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_extensions() {
+        assert!(is_scan_extension("png"));
+        assert!(is_scan_extension("pdf"));
+        assert!(!is_scan_extension("txt"));
+        assert!(!is_scan_extension(""));
+    }
+}
@@ -0,0 +1,85 @@
+use std::fs::File;
+use std::sync::Arc;
+use arrow::array::{ArrayRef, Float64Array, StringArray, UInt64Array};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use crate::fs_loader::FsIndex;
+use crate::store::IndexStore;
+
+impl<S: IndexStore> FsIndex<S> {
+    /// Write the metadata of every document in a Parquet file.
+    ///
+    /// Each row holds a document key, its source path, the size and fill ratio of its filter, the modification time and the content hash,
+    /// so that the health of a large index can be analyzed with DuckDB, pandas or any Parquet reader.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a filter cannot be read or if it is not possible to write the file at `path`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use cli_bloom::FsIndex;
+    /// let fs_index = FsIndex::restore("/foo/dump.json");
+    /// fs_index.export_parquet("/foo/documents.parquet");
+    /// ```
+    pub fn export_parquet(&self, path: &str) {
+        let mut keys = Vec::new();
+        let mut paths = Vec::new();
+        let mut bits = Vec::new();
+        let mut fill_ratios = Vec::new();
+        let mut modified = Vec::new();
+        let mut hashes = Vec::new();
+        for (key, document) in self.documents() {
+            let filter = self.filter(key).expect("Unable to read filter from index store");
+            keys.push(key.as_str());
+            paths.push(document.path.as_ref().and_then(|path| path.to_str()));
+            bits.push(filter.as_ref().map(|filter| filter.bits() as u64));
+            fill_ratios.push(filter.as_ref().map(|filter| filter.fill_ratio()));
+            modified.push(document.modified);
+            hashes.push(document.hash.as_deref());
+        }
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("key", DataType::Utf8, false),
+            Field::new("path", DataType::Utf8, true),
+            Field::new("filter_bits", DataType::UInt64, true),
+            Field::new("fill_ratio", DataType::Float64, true),
+            Field::new("modified", DataType::UInt64, true),
+            Field::new("hash", DataType::Utf8, true)
+        ]));
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from(keys)),
+            Arc::new(StringArray::from(paths)),
+            Arc::new(UInt64Array::from(bits)),
+            Arc::new(Float64Array::from(fill_ratios)),
+            Arc::new(UInt64Array::from(modified)),
+            Arc::new(StringArray::from(hashes))
+        ];
+        let batch = RecordBatch::try_new(schema.clone(), columns).expect("Impossible to build documents table");
+        let output_file = File::create(path).expect(format!("Impossible to create parquet file {}", &path).as_str());
+        let mut writer = ArrowWriter::try_new(output_file, schema, None).expect("Impossible to write parquet file");
+        writer.write(&batch).expect("Impossible to write parquet file");
+        writer.close().expect("Impossible to write parquet file");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use crate::FsIndex;
+
+    #[test]
+    fn export_documents_metadata() {
+        let mut index = FsIndex::new(0.01);
+        index.ingest("./test/data/simple_directory");
+        let mut dest_file = std::env::temp_dir();
+        dest_file.push("bloom_documents.parquet");
+        index.export_parquet(dest_file.to_str().unwrap());
+        let reader = SerializedFileReader::new(fs::File::open(&dest_file).unwrap()).unwrap();
+        assert_eq!(2, reader.metadata().file_metadata().num_rows());
+        assert_eq!(6, reader.metadata().file_metadata().schema_descr().num_columns());
+        fs::remove_file(dest_file).unwrap();
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use serde::{Serialize, Deserialize};
+use crate::document::content_hash;
+use crate::filter::check_error_rate;
+use crate::fs_loader::FsIndex;
+
+const MANIFEST_FILE: &str = "partitions.json";
+
+/// A set of `FsIndex`, one per top-level directory of a source root.
+///
+/// Each directory directly under the root is a partition holding its whole directory tree, named after the directory.
+/// Files directly under the root belong to the partition named `""`.
+/// A partition can be searched or updated on its own, and a search on the whole index fans out across all partitions.
+///
+/// # Example
+///
+/// ```
+/// # use cli_bloom::PartitionedIndex;
+/// # fn search_index()  {
+/// let mut partitioned = PartitionedIndex::new("/foo", 0.00001);
+/// partitioned.ingest();
+/// let hits = partitioned.search_partition("src", "content");
+/// partitioned.dump("/foo/dumps");
+/// # }
+/// ```
+pub struct PartitionedIndex {
+    root: PathBuf,
+    error_rate: f32,
+    partitions: BTreeMap<String, FsIndex>
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    root: PathBuf,
+    error_rate: f32,
+    partitions: BTreeMap<String, String>
+}
+
+impl PartitionedIndex {
+    /// Constructs a new, empty `PartitionedIndex` of the directory `root`.
+    ///
+    /// The `error_rate` is the one of every partition (see [`FsIndex::new`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `error_rate` is invalid.
+    pub fn new(root: &str, error_rate: f32) -> Self {
+        check_error_rate(error_rate);
+        PartitionedIndex {
+            root: PathBuf::from(root),
+            error_rate,
+            partitions: BTreeMap::new()
+        }
+    }
+
+    /// Ingest all partitions of the root directory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the root is not a directory or if a content cannot be read.
+    pub fn ingest(&mut self) {
+        let entries = fs::read_dir(&self.root).expect(format!("Unable to read directory {}", self.root.display()).as_str());
+        let mut names = vec![String::new()];
+        for entry in entries {
+            let path = entry.expect("Unable to read directory entry").path();
+            if path.is_dir() {
+                if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        for name in names {
+            self.ingest_partition(&name);
+        }
+    }
+
+    /// Bring a single partition up to date with the file system.
+    ///
+    /// Documents of deleted files are removed and new or changed files are ingested, leaving the other partitions untouched.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the partition directory cannot be read.
+    pub fn ingest_partition(&mut self, name: &str) {
+        let error_rate = self.error_rate;
+        let index = self.partitions.entry(name.to_string()).or_insert_with(|| FsIndex::new(error_rate));
+        index.prune();
+        if name.is_empty() {
+            index.ingest(self.root.to_str().unwrap());
+        } else {
+            index.ingest_recursive(self.root.join(name).to_str().unwrap());
+        }
+    }
+
+    /// Return the names of the partitions, in ascending order.
+    pub fn partitions(&self) -> impl Iterator<Item = &str> {
+        self.partitions.keys().map(|name| name.as_str())
+    }
+
+    /// Return the index of the partition `name`, if any.
+    pub fn partition(&self, name: &str) -> Option<&FsIndex> {
+        self.partitions.get(name)
+    }
+
+    /// Search keywords in every partition.
+    ///
+    /// Hits are grouped by partition, in ascending partition name order.
+    /// Return `None` if nothing match.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `keywords` cannot be processed.
+    pub fn search(&self, keywords: &str) -> Option<Vec<&String>> {
+        let hits: Vec<&String> = self.partitions.values()
+            .filter_map(|index| index.search(keywords))
+            .flatten()
+            .collect();
+        if hits.is_empty() {
+            None
+        } else {
+            Some(hits)
+        }
+    }
+
+    /// Search keywords in the partition `name` only.
+    ///
+    /// Return `None` if nothing match or if the partition does not exist.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `keywords` cannot be processed.
+    pub fn search_partition(&self, name: &str, keywords: &str) -> Option<Vec<&String>> {
+        self.partitions.get(name).and_then(|index| index.search(keywords))
+    }
+
+    /// Dump every partition in the `directory`, with a manifest describing them.
+    ///
+    /// A partition is dumped in a file named after the hash of its name, so it keeps its file when partitions are added or removed.
+    /// The files of the partitions of a previous dump in the `directory` which no longer exist are removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if it is not possible to create the directory or to write the dumps.
+    pub fn dump(&self, directory: &str) {
+        let directory = Path::new(directory);
+        fs::create_dir_all(directory).expect(format!("Impossible to create dump directory {}", directory.display()).as_str());
+        let mut manifest = Manifest {
+            root: self.root.clone(),
+            error_rate: self.error_rate,
+            partitions: BTreeMap::new()
+        };
+        for (name, index) in &self.partitions {
+            let file_name = format!("partition-{}.json", content_hash(name.as_bytes()));
+            index.dump(directory.join(&file_name).to_str().unwrap());
+            manifest.partitions.insert(name.clone(), file_name);
+        }
+        let previous = fs::read_to_string(directory.join(MANIFEST_FILE)).ok()
+            .and_then(|serialized| serde_json::from_str::<Manifest>(&serialized).ok());
+        let serialized = serde_json::to_string(&manifest).expect("Impossible to serialize partitions");
+        fs::write(directory.join(MANIFEST_FILE), serialized).expect("Impossible to write partitions manifest");
+        if let Some(previous) = previous {
+            for file_name in previous.partitions.values().filter(|file_name| !manifest.partitions.values().any(|kept| kept == *file_name)) {
+                let _ = fs::remove_file(directory.join(file_name));
+            }
+        }
+    }
+
+    /// Restore a `PartitionedIndex` from a directory written by [`PartitionedIndex::dump`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the directory does not contain a valid dump.
+    pub fn restore(directory: &str) -> Self {
+        let directory = Path::new(directory);
+        let manifest_path = directory.join(MANIFEST_FILE);
+        let serialized = fs::read_to_string(&manifest_path).expect(format!("Unable to read partitions manifest {}", manifest_path.display()).as_str());
+        let manifest: Manifest = serde_json::from_str(&serialized).expect(format!("Invalid partitions manifest {}", manifest_path.display()).as_str());
+        let partitions = manifest.partitions.into_iter()
+            .map(|(name, file_name)| (name, FsIndex::restore(directory.join(file_name).to_str().unwrap())))
+            .collect();
+        PartitionedIndex {
+            root: manifest.root,
+            error_rate: manifest.error_rate,
+            partitions
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_across_partitions() {
+        let mut index = PartitionedIndex::new("./test/data", 0.01);
+        index.ingest();
+        let expected = vec!["./test/data/random_directory/sub_directory/file2.txt", "./test/data/simple_directory/file2.txt"];
+        assert_eq!(expected, index.search("word5").unwrap());
+        assert_eq!(vec!["./test/data/simple_directory/file2.txt"], index.search_partition("simple_directory", "word5").unwrap());
+        assert_eq!(vec!["./test/data/simple_content.txt"], index.search_partition("", "word4").unwrap());
+        assert_eq!(None, index.search_partition("unknown", "word5"));
+    }
+
+    #[test]
+    fn dump_and_restore_partitions() {
+        let mut index = PartitionedIndex::new("./test/data", 0.01);
+        index.ingest();
+        let mut directory = std::env::temp_dir();
+        directory.push("bloom_partitions");
+        index.dump(directory.to_str().unwrap());
+        let restored = PartitionedIndex::restore(directory.to_str().unwrap());
+        assert_eq!(index.partitions().collect::<Vec<&str>>(), restored.partitions().collect::<Vec<&str>>());
+        assert_eq!(index.search("word5"), restored.search("word5"));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn dump_again_without_removed_partitions() {
+        let mut index = PartitionedIndex::new("./test/data", 0.01);
+        index.ingest();
+        let directory = std::env::temp_dir().join("bloom_partitions_again");
+        let _ = fs::remove_dir_all(&directory);
+        index.dump(directory.to_str().unwrap());
+        let removed = index.partitions.keys().next().unwrap().clone();
+        index.partitions.retain(|name, _| *name != removed);
+        index.dump(directory.to_str().unwrap());
+        let files = fs::read_dir(&directory).unwrap().count();
+        assert_eq!(index.partitions().count() + 1, files);
+        let restored = PartitionedIndex::restore(directory.to_str().unwrap());
+        assert_eq!(index.partitions().collect::<Vec<&str>>(), restored.partitions().collect::<Vec<&str>>());
+        assert_eq!(index.search("word5"), restored.search("word5"));
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+/// How hard remote sources are fetched, e.g. by [`FsIndex::crawl`](crate::FsIndex::crawl).
+///
+/// Requests are made at most `concurrency` at a time and spaced to stay under `requests_per_second` for each origin.
+/// Paths disallowed to `cli-bloom` by the robots.txt of the origin are not fetched, and its crawl delay is honored, unless robots.txt is ignored.
+///
+/// # Example
+///
+/// ```
+/// # use cli_bloom::{FsIndex, Politeness};
+/// let mut fs_index = FsIndex::new(0.00001);
+/// fs_index.set_politeness(Politeness::new(2, 1.0));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Politeness {
+    concurrency: usize,
+    requests_per_second: f64,
+    robots: bool
+}
+
+impl Politeness {
+    /// Constructs a policy making at most `concurrency` requests at a time and `requests_per_second` requests per second, respecting robots.txt.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `concurrency` is 0 or `requests_per_second` is not positive.
+    pub fn new(concurrency: usize, requests_per_second: f64) -> Self {
+        if concurrency == 0 {
+            panic!("The concurrency must be at least 1");
+        }
+        if requests_per_second.is_nan() || requests_per_second <= 0.0 {
+            panic!("The requests per second must be positive");
+        }
+        Politeness { concurrency, requests_per_second, robots: true }
+    }
+
+    /// The same policy, also fetching the paths disallowed by robots.txt.
+    pub fn ignoring_robots(self) -> Self {
+        Politeness { robots: false, ..self }
+    }
+
+    /// Return the maximum number of requests at a time.
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// Return the maximum number of requests per second to an origin.
+    pub fn requests_per_second(&self) -> f64 {
+        self.requests_per_second
+    }
+
+    /// Return whether robots.txt is respected.
+    pub fn respects_robots(&self) -> bool {
+        self.robots
+    }
+
+    /// Return the minimum delay between two requests to an origin.
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.requests_per_second)
+    }
+}
+
+/// 4 requests at a time, 5 per second, respecting robots.txt.
+impl Default for Politeness {
+    fn default() -> Self {
+        Politeness::new(4, 5.0)
+    }
+}
@@ -0,0 +1,96 @@
+use std::io;
+use std::io::Write;
+use serde::Serialize;
+use serde_json::ser::Formatter;
+
+// Objects nested up to this depth have one entry per line, deeper values stay on the line of their entry
+const INDENTED_DEPTH: usize = 2;
+
+// A dump with one line per document and per filter, so that diffs between dumps show the changed documents
+pub(crate) fn to_string_pretty<T: Serialize>(value: &T) -> serde_json::Result<String> {
+    let mut serialized = Vec::new();
+    let mut serializer = serde_json::Serializer::with_formatter(&mut serialized, BlockFormatter::default());
+    value.serialize(&mut serializer)?;
+    Ok(String::from_utf8(serialized).expect("JSON is valid UTF-8"))
+}
+
+#[derive(Default)]
+struct BlockFormatter {
+    depth: usize,
+    has_value: bool
+}
+
+impl BlockFormatter {
+    fn indented(&self) -> bool {
+        self.depth <= INDENTED_DEPTH
+    }
+}
+
+fn indent<W: ?Sized + Write>(writer: &mut W, depth: usize) -> io::Result<()> {
+    writer.write_all(b"\n")?;
+    for _ in 0..depth {
+        writer.write_all(b"  ")?;
+    }
+    Ok(())
+}
+
+impl Formatter for BlockFormatter {
+    fn begin_array<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.depth += 1;
+        writer.write_all(b"[")
+    }
+
+    fn end_array<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.depth -= 1;
+        writer.write_all(b"]")
+    }
+
+    fn begin_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.depth += 1;
+        self.has_value = false;
+        writer.write_all(b"{")
+    }
+
+    fn end_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        let indented = self.indented();
+        self.depth -= 1;
+        if indented && self.has_value {
+            indent(writer, self.depth)?;
+        }
+        writer.write_all(b"}")
+    }
+
+    fn begin_object_key<W: ?Sized + Write>(&mut self, writer: &mut W, first: bool) -> io::Result<()> {
+        if !first {
+            writer.write_all(b",")?;
+        }
+        if self.indented() {
+            indent(writer, self.depth)?;
+        }
+        Ok(())
+    }
+
+    fn begin_object_value<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(if self.indented() { b": " } else { b":" })
+    }
+
+    fn end_object_value<W: ?Sized + Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        self.has_value = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn one_line_per_entry() {
+        let value = json!({ "version": 2, "documents": { "a": { "hash": "1", "aliases": ["b"] }, "c": {} }, "empty": {} });
+        let expected = "{\n  \"documents\": {\n    \"a\": {\"aliases\":[\"b\"],\"hash\":\"1\"},\n    \"c\": {}\n  },\n  \"empty\": {},\n  \"version\": 2\n}";
+        let pretty = to_string_pretty(&value).unwrap();
+        assert_eq!(expected, pretty);
+        assert_eq!(value, serde_json::from_str::<serde_json::Value>(&pretty).unwrap());
+    }
+}
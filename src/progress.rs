@@ -0,0 +1,46 @@
+use std::path::Path;
+use crate::audit::Outcome;
+use crate::stats::IngestReport;
+
+/// Receives the progress of the ingestions of a `FsIndex`, e.g. to render a progress bar.
+///
+/// Register it with [`FsIndex::set_progress_observer`](crate::FsIndex::set_progress_observer). Every method does nothing by default, implement those of interest.
+/// Methods are called on the thread running the ingestion.
+///
+/// # Example
+///
+/// ```
+/// # use std::path::Path;
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use cli_bloom::{FsIndex, Outcome, ProgressObserver};
+/// #[derive(Default)]
+/// struct Counter {
+///     done: AtomicUsize
+/// }
+///
+/// impl ProgressObserver for Counter {
+///     fn on_file_done(&self, path: &Path, _outcome: Outcome, _bytes: u64) {
+///         let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+///         eprintln!("{} files done, last {}", done, path.display());
+///     }
+/// }
+///
+/// let mut fs_index = FsIndex::new(0.00001);
+/// fs_index.set_progress_observer(Counter::default());
+/// ```
+pub trait ProgressObserver: Send + Sync {
+    /// Called before reading the file at `path`.
+    fn on_file_start(&self, _path: &Path) {}
+
+    /// Called when the file at `path` is indexed or found unchanged, `bytes` being its size.
+    fn on_file_done(&self, _path: &Path, _outcome: Outcome, _bytes: u64) {}
+
+    /// Called when the file at `path` is left out, e.g. because it is not a text file.
+    fn on_skip(&self, _path: &Path, _reason: &str) {}
+
+    /// Called when the file at `path` cannot be ingested.
+    fn on_error(&self, _path: &Path, _reason: &str) {}
+
+    /// Called at the end of an ingestion with its report, even if it was cancelled.
+    fn on_finish(&self, _report: &IngestReport) {}
+}
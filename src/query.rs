@@ -0,0 +1,397 @@
+use std::error::Error as StdError;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+use std::str::FromStr;
+use crate::analyzer::Analyzer;
+use crate::errors::Error;
+use crate::filter::Filter;
+
+/// A structured query, combining keywords with boolean operators.
+///
+/// Evaluated against every document by [`FsIndex::query`](crate::FsIndex::query).
+/// As bloom filters may report false positives, a `Not` query may leave out documents which do not contain the negated keywords.
+///
+/// # Example
+///
+/// ```
+/// # use cli_bloom::Query;
+/// // rust AND (async OR tokio) AND NOT unsafe
+/// let query = Query::term("rust")
+///     .and(Query::term("async").or(Query::term("tokio")))
+///     .not(Query::term("unsafe"));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum Query {
+    /// Documents containing all the keywords of the term.
+    Term(String),
+    /// Documents matching both queries.
+    And(Box<Query>, Box<Query>),
+    /// Documents matching either query.
+    Or(Box<Query>, Box<Query>),
+    /// Documents not matching the query.
+    Not(Box<Query>)
+}
+
+impl Query {
+    /// Documents containing all the `keywords`, as searched by [`FsIndex::search`](crate::FsIndex::search).
+    pub fn term(keywords: &str) -> Self {
+        Query::Term(keywords.to_string())
+    }
+
+    /// Documents matching this query and `other`.
+    pub fn and(self, other: Query) -> Self {
+        Query::And(Box::new(self), Box::new(other))
+    }
+
+    /// Documents matching this query or `other`.
+    pub fn or(self, other: Query) -> Self {
+        Query::Or(Box::new(self), Box::new(other))
+    }
+
+    /// Documents matching this query but not `other`.
+    pub fn not(self, other: Query) -> Self {
+        self.and(Query::Not(Box::new(other)))
+    }
+
+    /// Parse a query string.
+    ///
+    /// Keywords are matched by documents containing all of them, double quotes group keywords into a single term.
+    /// Terms are combined with the `AND`, `OR` and `NOT` operators (in capital letters) and parentheses.
+    /// `NOT` binds tighter than `AND`, which binds tighter than `OR`, and terms separated by parentheses or `NOT` without operator are combined with `AND`.
+    /// The [`Display`](fmt::Display) representation of a `Query` is parsed back to the same query.
+    ///
+    /// # Errors
+    ///
+    /// Return an error if the query is empty, has unbalanced parentheses or quotes, or misplaced operators.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::Query;
+    /// let query = Query::parse("rust (async OR tokio) NOT \"unsafe code\"").unwrap();
+    /// let expected = Query::term("rust")
+    ///     .and(Query::term("async").or(Query::term("tokio")))
+    ///     .not(Query::term("unsafe code"));
+    /// assert_eq!(expected, query);
+    /// ```
+    pub fn parse(query: &str) -> Result<Query, ParseError> {
+        let tokens = tokenize(query)?;
+        if tokens.is_empty() {
+            return Err(ParseError::new("empty query", 0));
+        }
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            end: query.len()
+        };
+        let parsed = parser.parse_or()?;
+        match parser.tokens.get(parser.position) {
+            Some((position, _)) => Err(ParseError::new("unexpected closing parenthesis", *position)),
+            None => Ok(parsed)
+        }
+    }
+
+    /// Return the keywords a matching document may contain, i.e. the keywords of the terms not under a `Not`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::Query;
+    /// let query = Query::parse("rust async NOT unsafe").unwrap();
+    /// assert_eq!(vec!["rust", "async"], query.keywords());
+    /// ```
+    pub fn keywords(&self) -> Vec<&str> {
+        match self {
+            Query::Term(keywords) => keywords.split_whitespace().collect(),
+            Query::And(left, right) | Query::Or(left, right) => {
+                let mut keywords = left.keywords();
+                keywords.extend(right.keywords());
+                keywords
+            },
+            Query::Not(_) => Vec::new()
+        }
+    }
+
+    pub(crate) fn evaluate(&self, filter: &Filter, analyzer: &Analyzer) -> Result<bool, Error> {
+        match self {
+            Query::Term(keywords) => filter.contains(&analyzer.analyze(keywords)),
+            Query::And(left, right) => Ok(left.evaluate(filter, analyzer)? && right.evaluate(filter, analyzer)?),
+            Query::Or(left, right) => Ok(left.evaluate(filter, analyzer)? || right.evaluate(filter, analyzer)?),
+            Query::Not(query) => Ok(!query.evaluate(filter, analyzer)?)
+        }
+    }
+
+    // Number of distinct keywords of the query found in `filter`, higher for the documents matching more alternatives
+    pub(crate) fn score(&self, filter: &Filter, analyzer: &Analyzer) -> Result<usize, Error> {
+        let keywords: BTreeSet<String> = self.keywords().into_iter().map(|keyword| analyzer.analyze(keyword)).collect();
+        let mut score = 0;
+        for keyword in keywords.iter().filter(|keyword| !keyword.is_empty()) {
+            if filter.contains(keyword)? {
+                score += 1;
+            }
+        }
+        Ok(score)
+    }
+}
+
+/// Order of search hits, see [`FsIndex::sort_hits`](crate::FsIndex::sort_hits).
+///
+/// Without sorting, hits come in ascending order of their keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sort {
+    /// Ascending order of the source file paths, documents without a source file last.
+    Path,
+    /// Most recently modified sources first, documents without a modification time last.
+    Modified,
+    /// Documents containing the most keywords of the query first, e.g. those matching both sides of an `OR`.
+    Score
+}
+
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Sort::Path => "path",
+            Sort::Modified => "modified",
+            Sort::Score => "score"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Parse the name of a sort order, as displayed.
+impl FromStr for Sort {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "path" => Ok(Sort::Path),
+            "modified" => Ok(Sort::Modified),
+            "score" => Ok(Sort::Score),
+            _ => Err(format!("Unknown sort order {}", name))
+        }
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Query::Term(keywords) => write!(f, "\"{}\"", keywords),
+            Query::And(left, right) => write!(f, "({} AND {})", left, right),
+            Query::Or(left, right) => write!(f, "({} OR {})", left, right),
+            Query::Not(query) => write!(f, "NOT {}", query)
+        }
+    }
+}
+
+/// Error returned by [`Query::parse`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    message: String,
+    position: usize
+}
+
+impl ParseError {
+    fn new(message: &str, position: usize) -> Self {
+        ParseError {
+            message: message.to_string(),
+            position
+        }
+    }
+
+    /// Return the byte offset of the error in the query string.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl StdError for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Open,
+    Close,
+    And,
+    Or,
+    Not
+}
+
+// Tokens with their byte offset in the query
+fn tokenize(query: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = query.char_indices().peekable();
+    while let Some((position, character)) = chars.next() {
+        let token = match character {
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '"' => {
+                let quoted: String = chars.by_ref().map(|(_, character)| character).take_while(|character| *character != '"').collect();
+                if !query[position + 1..].contains('"') {
+                    return Err(ParseError::new("unterminated quote", position));
+                }
+                Token::Quoted(quoted)
+            },
+            character if character.is_whitespace() => continue,
+            character => {
+                let mut word = character.to_string();
+                word.push_str(&take_word(&mut chars));
+                match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Word(word)
+                }
+            }
+        };
+        tokens.push((position, token));
+    }
+    Ok(tokens)
+}
+
+fn take_word(chars: &mut Peekable<CharIndices>) -> String {
+    let mut word = String::new();
+    while let Some((_, character)) = chars.peek() {
+        if character.is_whitespace() || *character == '(' || *character == ')' || *character == '"' {
+            break;
+        }
+        word.push(*character);
+        chars.next();
+    }
+    word
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    // Offset reported for errors at the end of the query
+    end: usize
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens.get(self.position).map(|(offset, _)| *offset).unwrap_or(self.end)
+    }
+
+    fn parse_or(&mut self) -> Result<Query, ParseError> {
+        let mut query = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            query = query.or(self.parse_and()?);
+        }
+        Ok(query)
+    }
+
+    fn parse_and(&mut self) -> Result<Query, ParseError> {
+        let mut query = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => self.position += 1,
+                Some(Token::Word(_)) | Some(Token::Quoted(_)) | Some(Token::Open) | Some(Token::Not) => (),
+                _ => return Ok(query)
+            }
+            query = query.and(self.parse_unary()?);
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Query, ParseError> {
+        let offset = self.offset();
+        match self.tokens.get(self.position).map(|(_, token)| token) {
+            Some(Token::Not) => {
+                self.position += 1;
+                Ok(Query::Not(Box::new(self.parse_unary()?)))
+            },
+            Some(Token::Open) => {
+                self.position += 1;
+                let query = self.parse_or()?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err(ParseError::new("missing closing parenthesis", self.offset()));
+                }
+                self.position += 1;
+                Ok(query)
+            },
+            Some(Token::Quoted(keywords)) => {
+                let query = Query::term(keywords);
+                self.position += 1;
+                Ok(query)
+            },
+            Some(Token::Word(_)) => {
+                // Consecutive keywords make a single term
+                let mut keywords = Vec::new();
+                while let Some(Token::Word(word)) = self.peek() {
+                    keywords.push(word.clone());
+                    self.position += 1;
+                }
+                Ok(Query::term(&keywords.join(" ")))
+            },
+            _ => Err(ParseError::new("expected keywords", offset))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluate_query() {
+        let filter = Filter::new(0.01, "word1 word2 word3").unwrap();
+        assert!(Query::term("word1 word2").evaluate(&filter, &Analyzer::default()).unwrap());
+        assert!(!Query::term("word1").and(Query::term("word4")).evaluate(&filter, &Analyzer::default()).unwrap());
+        assert!(Query::term("word4").or(Query::term("word3")).evaluate(&filter, &Analyzer::default()).unwrap());
+        assert!(!Query::term("word1").not(Query::term("word2")).evaluate(&filter, &Analyzer::default()).unwrap());
+        assert!(Query::term("word1").not(Query::term("word4")).evaluate(&filter, &Analyzer::default()).unwrap());
+    }
+
+    #[test]
+    fn score_query() {
+        let filter = Filter::new(0.01, "word1 word2 word3").unwrap();
+        assert_eq!(2, Query::parse("word1 OR word2 OR word4").unwrap().score(&filter, &Analyzer::default()).unwrap());
+        assert_eq!(1, Query::parse("word1 NOT word2").unwrap().score(&filter, &Analyzer::default()).unwrap());
+        assert_eq!(Ok(Sort::Modified), "modified".parse());
+        assert!("size".parse::<Sort>().is_err());
+    }
+
+    #[test]
+    fn display_query() {
+        let query = Query::term("rust").and(Query::term("async").or(Query::term("tokio"))).not(Query::term("unsafe code"));
+        assert_eq!("((\"rust\" AND (\"async\" OR \"tokio\")) AND NOT \"unsafe code\")", query.to_string());
+    }
+
+    #[test]
+    fn parse_query() {
+        assert_eq!(Query::term("word1 word2"), Query::parse("word1 word2").unwrap());
+        assert_eq!(Query::term("word1").or(Query::term("word2 word3")), Query::parse("word1 OR word2 word3").unwrap());
+        assert_eq!(Query::term("word1").and(Query::term("word2").or(Query::term("word3"))), Query::parse("word1 AND (word2 OR word3)").unwrap());
+        assert_eq!(Query::term("word1 or").not(Query::term("word2")), Query::parse("word1 or NOT word2").unwrap());
+        assert_eq!(Query::Not(Box::new(Query::term("word1 word2"))), Query::parse("NOT \"word1 word2\"").unwrap());
+    }
+
+    #[test]
+    fn parse_displayed_query() {
+        let query = Query::term("rust").and(Query::term("async").or(Query::term("tokio"))).not(Query::term("unsafe code"));
+        assert_eq!(query, Query::parse(&query.to_string()).unwrap());
+    }
+
+    #[test]
+    fn invalid_query() {
+        assert_eq!("empty query at position 0", Query::parse("  ").unwrap_err().to_string());
+        assert_eq!("missing closing parenthesis at position 15", Query::parse("(word1 OR word2").unwrap_err().to_string());
+        assert_eq!("unexpected closing parenthesis at position 5", Query::parse("word1) word2").unwrap_err().to_string());
+        assert_eq!("expected keywords at position 9", Query::parse("word1 OR ").unwrap_err().to_string());
+        assert_eq!("unterminated quote at position 6", Query::parse("word1 \"word2").unwrap_err().to_string());
+        assert_eq!(10, Query::parse("word1 AND OR word2").unwrap_err().position());
+    }
+}
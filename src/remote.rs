@@ -0,0 +1,166 @@
+use std::env;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use futures::TryStreamExt;
+use object_store::{ObjectMeta, ObjectStore};
+use object_store::path::Path as ObjectPath;
+use tokio::runtime::Runtime;
+use url::Url;
+use crate::document::content_hash;
+use crate::fs_loader::FsIndex;
+use crate::limiter::Limiter;
+use crate::stats::{IngestReport, Issue};
+use crate::store::IndexStore;
+
+const SCHEMES: [&str; 10] = ["s3", "s3a", "gs", "az", "adl", "azure", "abfs", "abfss", "file", "https"];
+
+// Whether `location` is the URL of an object store rather than a local path
+pub(crate) fn is_remote(location: &str) -> bool {
+    match Url::parse(location) {
+        Ok(url) => SCHEMES.contains(&url.scheme()),
+        Err(_) => false
+    }
+}
+
+// An object, or the objects under a prefix, of S3, GCS, Azure Blob or the local file system.
+// Credentials and options are read from the environment, e.g. AWS_ACCESS_KEY_ID or GOOGLE_SERVICE_ACCOUNT.
+pub(crate) struct Remote {
+    url: Url,
+    store: Box<dyn ObjectStore>,
+    path: ObjectPath,
+    runtime: Runtime
+}
+
+impl Remote {
+    pub(crate) fn open(location: &str) -> io::Result<Self> {
+        let url = Url::parse(location).map_err(other)?;
+        let options = env::vars().map(|(key, value)| (key.to_ascii_lowercase(), value));
+        let (store, path) = object_store::parse_url_opts(&url, options).map_err(other)?;
+        let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+        Ok(Remote { url, store, path, runtime })
+    }
+
+    pub(crate) fn read_string(&self) -> io::Result<String> {
+        String::from_utf8(self.read(&self.path)?).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    pub(crate) fn write(&self, content: Vec<u8>) -> io::Result<()> {
+        self.runtime.block_on(self.store.put(&self.path, content.into())).map_err(other)?;
+        Ok(())
+    }
+
+    // The objects under the prefix, or the object itself if there are none
+    fn list(&self) -> io::Result<Vec<ObjectMeta>> {
+        let objects: Vec<ObjectMeta> = self.runtime.block_on(self.store.list(Some(&self.path)).try_collect()).map_err(other)?;
+        if !objects.is_empty() {
+            return Ok(objects);
+        }
+        Ok(vec![self.runtime.block_on(self.store.head(&self.path)).map_err(other)?])
+    }
+
+    fn read(&self, path: &ObjectPath) -> io::Result<Vec<u8>> {
+        self.runtime.block_on(async { self.store.get(path).await?.bytes().await })
+            .map(|bytes| bytes.to_vec())
+            .map_err(other)
+    }
+
+    // URL of the object at `path`, keying its document
+    fn url_of(&self, path: &ObjectPath) -> String {
+        format!("{}://{}/{}", self.url.scheme(), self.url.host_str().unwrap_or_default(), path)
+    }
+}
+
+fn other<E: std::error::Error + Send + Sync + 'static>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, error)
+}
+
+impl<S: IndexStore> FsIndex<S> {
+    // Ingest the objects at the URL `location`, fetched according to the politeness of the index.
+    // Objects whose content did not change since they were indexed are counted as unchanged, those which are not UTF-8 text skipped.
+    pub(crate) fn ingest_remote(&mut self, location: &str, report: &mut IngestReport) -> io::Result<()> {
+        let remote = Remote::open(location)?;
+        let objects = remote.list()?;
+        let politeness = self.politeness();
+        let limiter = Limiter::new(politeness.interval());
+        let fetched: Vec<Mutex<Option<io::Result<Vec<u8>>>>> = objects.iter().map(|_| Mutex::new(None)).collect();
+        let position = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0..politeness.concurrency().min(objects.len()) {
+                scope.spawn(|| loop {
+                    let object = position.fetch_add(1, Ordering::SeqCst);
+                    if object >= objects.len() {
+                        break;
+                    }
+                    limiter.wait();
+                    *fetched[object].lock().unwrap() = Some(remote.read(&objects[object].location));
+                });
+            }
+        });
+        for (object, content) in objects.iter().zip(fetched) {
+            if self.cancelled(report) {
+                break;
+            }
+            let key = remote.url_of(&object.location);
+            let content = match content.into_inner().unwrap().expect("Object not fetched") {
+                Ok(content) => content,
+                Err(error) => {
+                    report.failed.push(Issue { path: PathBuf::from(key), reason: error.to_string() });
+                    continue;
+                }
+            };
+            let hash = content_hash(&content);
+            if self.document(&key).and_then(|document| document.hash.as_ref()) == Some(&hash) {
+                report.unchanged += 1;
+                continue;
+            }
+            match String::from_utf8(content) {
+                Ok(text) if !text.contains('\0') => {
+                    self.insert_text(&key, &text);
+                    report.indexed += 1;
+                    report.bytes += object.size as u64;
+                },
+                _ => report.skipped.push(Issue { path: PathBuf::from(key), reason: "not UTF-8 text".to_string() })
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn remote_locations() {
+        assert!(is_remote("s3://bucket/docs/"));
+        assert!(is_remote("gs://bucket/dump.json"));
+        assert!(is_remote("file:///tmp/dump.json"));
+        assert!(!is_remote("/tmp/dump.json"));
+        assert!(!is_remote("C:\\dump.json"));
+        assert!(!is_remote("./test/data"));
+    }
+
+    #[test]
+    fn ingest_and_dump_local_objects() {
+        let directory = env::temp_dir().join(format!("cli-bloom-remote-{}", std::process::id()));
+        fs::create_dir_all(directory.join("docs")).unwrap();
+        fs::write(directory.join("docs/a.txt"), "word1 word2").unwrap();
+        fs::write(directory.join("docs/b.bin"), b"\x00\xff").unwrap();
+        let source = format!("file://{}/docs", directory.display());
+        let mut fs_index = FsIndex::new(0.00001);
+        let report = fs_index.ingest(&source);
+        assert_eq!(1, report.indexed);
+        assert_eq!(1, report.skipped.len());
+        let dump = format!("file://{}/dump.json", directory.display());
+        fs_index.dump(&dump);
+        let mut restored = FsIndex::restore(&dump);
+        let key = format!("file://{}/docs/a.txt", directory.display());
+        assert_eq!(vec![&key], restored.search("word2").unwrap());
+        assert_eq!(1, restored.ingest(&source).unchanged);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
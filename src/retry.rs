@@ -0,0 +1,90 @@
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+/// How many times to try again reading a file after a transient error.
+///
+/// Network filesystems and FUSE mounts may fail a read with an interrupted, would block or timed out error which does not happen again a moment later.
+/// Such reads are attempted again after a delay doubling on every retry, other errors fail the file immediately.
+///
+/// # Example
+///
+/// ```
+/// # use std::time::Duration;
+/// # use cli_bloom::{FsIndex, RetryPolicy};
+/// let mut fs_index = FsIndex::new(0.00001);
+/// fs_index.set_retry_policy(RetryPolicy::new(3, Duration::from_millis(100)));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+    retries: u32,
+    backoff: Duration
+}
+
+impl RetryPolicy {
+    /// Constructs a policy trying again at most `retries` times, first after `backoff`.
+    pub fn new(retries: u32, backoff: Duration) -> Self {
+        RetryPolicy { retries, backoff }
+    }
+
+    /// A policy failing on the first error.
+    pub fn none() -> Self {
+        RetryPolicy::new(0, Duration::from_millis(0))
+    }
+
+    pub(crate) fn run<T, F: FnMut() -> io::Result<T>>(&self, mut operation: F) -> io::Result<T> {
+        let mut backoff = self.backoff;
+        let mut retries = 0;
+        loop {
+            match operation() {
+                Err(error) if retries < self.retries && is_transient(&error) => {
+                    thread::sleep(backoff);
+                    backoff *= 2;
+                    retries += 1;
+                },
+                result => return result
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::none()
+    }
+}
+
+fn is_transient(error: &io::Error) -> bool {
+    matches!(error.kind(), io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failing(failures: u32, kind: io::ErrorKind) -> impl FnMut() -> io::Result<u32> {
+        let mut calls = 0;
+        move || {
+            calls += 1;
+            if calls <= failures {
+                Err(io::Error::new(kind, "failure"))
+            } else {
+                Ok(calls)
+            }
+        }
+    }
+
+    #[test]
+    fn retry_transient_errors() {
+        let policy = RetryPolicy::new(2, Duration::from_millis(1));
+        assert_eq!(3, policy.run(failing(2, io::ErrorKind::Interrupted)).unwrap());
+        assert_eq!(io::ErrorKind::TimedOut, policy.run(failing(3, io::ErrorKind::TimedOut)).unwrap_err().kind());
+    }
+
+    #[test]
+    fn fail_on_other_errors() {
+        let policy = RetryPolicy::new(2, Duration::from_millis(1));
+        assert_eq!(io::ErrorKind::NotFound, policy.run(failing(1, io::ErrorKind::NotFound)).unwrap_err().kind());
+        assert_eq!(io::ErrorKind::Interrupted, RetryPolicy::none().run(failing(1, io::ErrorKind::Interrupted)).unwrap_err().kind());
+    }
+}
@@ -0,0 +1,90 @@
+use std::borrow::Cow;
+use std::io;
+use std::path::Path;
+use rocksdb::{DB, IteratorMode};
+use crate::filter::Filter;
+use crate::store::IndexStore;
+
+/// An `IndexStore` keeping filters in a RocksDB database.
+///
+/// Filters are read from the database only when a search evaluates them, so the index does not have to fit in memory.
+/// Reopening a database gives back its documents, but not their metadata (hash, modification time, path).
+///
+/// # Example
+///
+/// ```no_run
+/// # use cli_bloom::{FsIndex, RocksDbStore};
+/// let store = RocksDbStore::open("/foo/index.db").unwrap();
+/// let mut fs_index = FsIndex::with_store(0.00001, store);
+/// fs_index.ingest("/foo/bar");
+/// ```
+pub struct RocksDbStore {
+    db: DB
+}
+
+impl RocksDbStore {
+    /// Open the database at `path`, creating it if missing.
+    ///
+    /// # Errors
+    ///
+    /// Return an error if the database cannot be opened.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let db = DB::open_default(path).map_err(to_io_error)?;
+        Ok(RocksDbStore {
+            db
+        })
+    }
+}
+
+impl IndexStore for RocksDbStore {
+    fn get(&self, key: &str) -> io::Result<Option<Cow<'_, Filter>>> {
+        match self.db.get(key.as_bytes()).map_err(to_io_error)? {
+            Some(serialized) => {
+                let serialized = String::from_utf8(serialized).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+                Ok(Some(Cow::Owned(Filter::from_json(&serialized))))
+            },
+            None => Ok(None)
+        }
+    }
+
+    fn insert(&mut self, key: &str, filter: Filter) -> io::Result<()> {
+        self.db.put(key.as_bytes(), filter.to_json().as_bytes()).map_err(to_io_error)
+    }
+
+    fn remove(&mut self, key: &str) -> io::Result<()> {
+        self.db.delete(key.as_bytes()).map_err(to_io_error)
+    }
+
+    fn keys(&self) -> io::Result<Vec<String>> {
+        let mut keys = Vec::new();
+        for entry in self.db.iterator(IteratorMode::Start) {
+            let (key, _) = entry.map_err(to_io_error)?;
+            keys.push(String::from_utf8(key.into_vec()).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?);
+        }
+        Ok(keys)
+    }
+}
+
+fn to_io_error(error: rocksdb::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FsIndex;
+
+    #[test]
+    fn reopen_rocksdb_index() {
+        let mut path = std::env::temp_dir();
+        path.push("bloom_rocksdb_store");
+        {
+            let mut index = FsIndex::with_store(0.01, RocksDbStore::open(&path).unwrap());
+            index.ingest("./test/data/simple_directory");
+        }
+        let index = FsIndex::with_store(0.01, RocksDbStore::open(&path).unwrap());
+        assert_eq!(vec!["./test/data/simple_directory/file2.txt"], index.search("word4").unwrap());
+        drop(index);
+        DB::destroy(&rocksdb::Options::default(), &path).unwrap();
+    }
+}
@@ -0,0 +1,91 @@
+use std::path::Path;
+use jsonschema::JSONSchema;
+use serde_json::Value;
+use crate::lock::read_locked;
+
+/// The JSON Schema of a dump written by [`FsIndex::dump`](crate::FsIndex::dump), for tools generating or reading dumps.
+///
+/// Dumps in sections are not described.
+pub const DUMP_SCHEMA: &str = include_str!("../schema/dump.schema.json");
+
+/// Validate a JSON dump against [`DUMP_SCHEMA`], returning a description of each violation prefixed by its location in the dump.
+///
+/// An empty result means the dump conforms to the schema.
+///
+/// # Example
+///
+/// ```
+/// # use cli_bloom::validate_dump;
+/// let violations = validate_dump("{\"error_rate\":0.1,\"bloom_filters\":{}}");
+/// assert!(violations.is_empty());
+/// ```
+pub fn validate_dump(serialized: &str) -> Vec<String> {
+    let dump: Value = match serde_json::from_str(serialized) {
+        Ok(dump) => dump,
+        Err(error) => return vec![format!("/ : invalid JSON, {}", error)]
+    };
+    let schema: Value = serde_json::from_str(DUMP_SCHEMA).expect("Invalid dump schema");
+    let schema = JSONSchema::compile(&schema).expect("Invalid dump schema");
+    let violations = match schema.validate(&dump) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors.map(|error| {
+            let location = error.instance_path.to_string();
+            format!("{} : {}", if location.is_empty() { "/" } else { &location }, error)
+        }).collect()
+    };
+    violations
+}
+
+/// Validate the dump file at `path` against [`DUMP_SCHEMA`], see [`validate_dump`].
+///
+/// The file is read once no other process is writing it, as when restoring an index.
+///
+/// # Panics
+///
+/// Panics if the dump file cannot be read.
+///
+/// # Example
+///
+/// ```
+/// # use cli_bloom::validate_dump_file;
+/// # fn search_index()  {
+/// for violation in validate_dump_file("/foo/dump.json") {
+///     println!("{}", violation);
+/// }
+/// # }
+/// ```
+pub fn validate_dump_file(path: &str) -> Vec<String> {
+    let serialized = read_locked(Path::new(path)).expect(format!("Unable to read dump file {}", &path).as_str());
+    validate_dump(&serialized)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::*;
+    use crate::FsIndex;
+
+    #[test]
+    fn dumps_conform_to_schema() {
+        let mut dump = std::env::temp_dir();
+        dump.push("bloom_dump_schema.json");
+        let mut index = FsIndex::new(0.1);
+        index.ingest("./test/data/simple_directory");
+        index.append("log", "word1");
+        index.append("log", "word2");
+        index.dump(dump.to_str().unwrap());
+        assert_eq!(Vec::<String>::new(), validate_dump(&fs::read_to_string(&dump).unwrap()));
+        assert_eq!(Vec::<String>::new(), validate_dump_file(dump.to_str().unwrap()));
+        assert_eq!(Vec::<String>::new(), validate_dump(&fs::read_to_string("./test/data/simple_dump.json").unwrap()));
+        fs::remove_file(&dump).unwrap();
+    }
+
+    #[test]
+    fn report_violations() {
+        let violations = validate_dump("{\"error_rate\":1.5,\"bloom_filters\":{\"a\":{\"key_size\":-1}}}");
+        assert_eq!(2, violations.len());
+        assert!(violations.iter().any(|violation| violation.starts_with("/error_rate : ")));
+        assert!(violations.iter().any(|violation| violation.starts_with("/bloom_filters/a : ")));
+        assert!(validate_dump("{")[0].starts_with("/ : invalid JSON"));
+    }
+}
@@ -0,0 +1,48 @@
+use std::io;
+use std::io::Write;
+use serde::de::Error as _;
+
+// First line of a dump made of sections, which cannot be the start of a JSON dump
+pub(crate) const SECTIONS_MAGIC: &str = "cli-bloom sections\n";
+
+// Each section is its byte length on a line, followed by its content and a line feed
+pub(crate) fn write_sections<W: Write>(writer: &mut W, sections: &[String]) -> io::Result<()> {
+    writer.write_all(SECTIONS_MAGIC.as_bytes())?;
+    for section in sections {
+        write!(writer, "{}\n{}\n", section.len(), section)?;
+    }
+    writer.flush()
+}
+
+pub(crate) fn read_sections(content: &str) -> Result<Vec<&str>, serde_json::Error> {
+    let mut rest = content.strip_prefix(SECTIONS_MAGIC).ok_or_else(|| serde_json::Error::custom("missing sections header"))?;
+    let mut sections = Vec::new();
+    while !rest.is_empty() {
+        let line_end = rest.find('\n').ok_or_else(|| serde_json::Error::custom("missing section length"))?;
+        let length: usize = rest[..line_end].parse().map_err(serde_json::Error::custom)?;
+        let start = line_end + 1;
+        let section = rest.get(start..start + length).ok_or_else(|| serde_json::Error::custom("truncated section"))?;
+        sections.push(section);
+        rest = rest.get(start + length + 1..).unwrap_or("");
+    }
+    Ok(sections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_round_trip() {
+        let sections = vec!["{\"a\":1}".to_string(), "{}".to_string(), "{\"é\":\"\\n\"}".to_string()];
+        let mut content = Vec::new();
+        write_sections(&mut content, &sections).unwrap();
+        let content = String::from_utf8(content).unwrap();
+        assert_eq!(sections, read_sections(&content).unwrap());
+    }
+
+    #[test]
+    fn truncated_section() {
+        assert!(read_sections("cli-bloom sections\n10\n{}\n").is_err());
+    }
+}
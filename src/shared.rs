@@ -0,0 +1,117 @@
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use crate::fs_loader::FsIndex;
+use crate::stats::IngestReport;
+use crate::store::{IndexStore, MemoryStore};
+
+/// A `FsIndex` shared between threads, searched while being updated.
+///
+/// Updates are applied to a copy of the current version of the index, which is then published for the next searches.
+/// Searches never wait for an update to complete, they run on the version published when they started.
+/// Concurrent updates are applied one after the other.
+///
+/// # Example
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use std::thread;
+/// # use cli_bloom::{FsIndex, SharedFsIndex};
+/// # fn search_index()  {
+/// let shared = Arc::new(SharedFsIndex::new(FsIndex::new(0.00001)));
+/// let ingest = {
+///     let shared = Arc::clone(&shared);
+///     thread::spawn(move || shared.ingest("/foo/bar"))
+/// };
+/// let results = shared.search("word1 word2");
+/// ingest.join().unwrap();
+/// # }
+/// ```
+pub struct SharedFsIndex<S = MemoryStore> {
+    current: RwLock<Arc<FsIndex<S>>>,
+    update: Mutex<()>
+}
+
+impl<S: IndexStore + Clone> SharedFsIndex<S> {
+    /// Constructs a `SharedFsIndex` starting from `index`.
+    pub fn new(index: FsIndex<S>) -> Self {
+        SharedFsIndex {
+            current: RwLock::new(Arc::new(index)),
+            update: Mutex::new(())
+        }
+    }
+
+    /// Return the current version of the index, unaffected by later updates.
+    pub fn current(&self) -> Arc<FsIndex<S>> {
+        Arc::clone(&self.current.read().expect("Shared index lock poisoned"))
+    }
+
+    /// Search keywords in the current version of the index, see [`FsIndex::search`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if an error occurs during the search.
+    pub fn search(&self, keywords: &str) -> Option<Vec<String>> {
+        self.current().search_owned(keywords)
+    }
+
+    /// Ingest `source` and publish the updated index, see [`FsIndex::ingest`].
+    ///
+    /// # Panics
+    ///
+    /// Panics like [`FsIndex::ingest`], leaving the current version unchanged.
+    pub fn ingest(&self, source: &str) -> IngestReport {
+        self.update(|index| index.ingest(source))
+    }
+
+    /// Apply `update` to a copy of the current index and publish it.
+    ///
+    /// Return the result of `update`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `update` panics, leaving the current version unchanged. Later updates still apply.
+    pub fn update<R, F: FnOnce(&mut FsIndex<S>) -> R>(&self, update: F) -> R {
+        // A panicking update never published its copy, so the current version is still consistent
+        let _guard = self.update.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut index = FsIndex::clone(&self.current());
+        let result = update(&mut index);
+        *self.current.write().expect("Shared index lock poisoned") = Arc::new(index);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn search_during_ingestion() {
+        let shared = Arc::new(SharedFsIndex::new(FsIndex::new(0.01)));
+        shared.ingest("./test/data/simple_content.txt");
+        let before = shared.current();
+        let ingest = {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || shared.ingest("./test/data/simple_directory"))
+        };
+        assert!(shared.search("word1").is_some());
+        assert_eq!(2, ingest.join().unwrap().indexed);
+        assert_eq!(Some(vec!["./test/data/simple_content.txt".to_string()]), before.search_owned("word1"));
+        assert_eq!(2, shared.search("word1").unwrap().len());
+    }
+
+    #[test]
+    fn update_after_panicking_update() {
+        let shared = Arc::new(SharedFsIndex::new(FsIndex::new(0.01)));
+        let failed = {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || shared.update(|index| {
+                index.ingest_reader("doc", "word1".as_bytes());
+                panic!("Update failed");
+            }))
+        };
+        assert!(failed.join().is_err());
+        assert_eq!(None, shared.search("word1"));
+        shared.ingest("./test/data/simple_content.txt");
+        assert_eq!(Some(vec!["./test/data/simple_content.txt".to_string()]), shared.search("word1"));
+    }
+}
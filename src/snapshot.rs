@@ -0,0 +1,56 @@
+use std::sync::Arc;
+use crate::analyzer::Analyzer;
+use crate::filter::Filter;
+
+/// An immutable view of a [`FsIndex`](crate::FsIndex), for searches only.
+///
+/// Cloning a `Snapshot` is cheap, the documents are shared between the clones.
+///
+/// See [`FsIndex::snapshot`](crate::FsIndex::snapshot).
+#[derive(Clone)]
+pub struct Snapshot {
+    // Sorted by key
+    filters: Arc<[(String, Filter)]>,
+    analyzer: Arc<Analyzer>
+}
+
+impl Snapshot {
+    pub(crate) fn new(filters: Vec<(String, Filter)>, analyzer: Analyzer) -> Self {
+        Snapshot {
+            filters: filters.into(),
+            analyzer: Arc::new(analyzer)
+        }
+    }
+
+    /// Search keywords in every document of the snapshot, see [`FsIndex::search`](crate::FsIndex::search).
+    ///
+    /// # Panics
+    ///
+    /// Panics if an error occurs during the search.
+    pub fn search(&self, keywords: &str) -> Option<Vec<&String>> {
+        let analyzed = self.analyzer.analyze(keywords);
+        let mut hits = Vec::new();
+        for (key, filter) in self.filters.iter() {
+            match filter.contains(&analyzed) {
+                Ok(true) => hits.push(key),
+                Ok(false) => (),
+                Err(error) => panic!("Error while searching for {} : {}", keywords, error)
+            }
+        }
+        if hits.is_empty() {
+            None
+        } else {
+            Some(hits)
+        }
+    }
+
+    /// Return the number of documents in the snapshot.
+    pub fn len(&self) -> usize {
+        self.filters.len()
+    }
+
+    /// Return `true` if the snapshot has no document.
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+}
@@ -0,0 +1,298 @@
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+#[cfg(feature = "archive")]
+use crate::archive;
+#[cfg(feature = "compressed")]
+use crate::compression::decompressing;
+use crate::audit::Outcome;
+use crate::file_system::{FileSystem, RealFileSystem};
+use crate::fs_loader::FsIndex;
+use crate::stats::{IngestReport, Issue};
+use crate::store::IndexStore;
+
+/// Items to ingest with [`FsIndex::ingest_from`], each a document with a key and a content read as a stream.
+///
+/// Implement this trait to ingest from a new kind of source. The sources of cli-bloom are [`DirectorySource`], [`ReaderSource`], `ArchiveSource` with the `archive` feature and `UrlSource` with the `crawl` feature.
+///
+/// # Example
+///
+/// ```
+/// # use std::io;
+/// # use std::io::Read;
+/// # use cli_bloom::{DataSource, FsIndex};
+/// // The lines of a text, each a document keyed by its number
+/// struct Lines(&'static str);
+///
+/// impl DataSource for Lines {
+///     fn visit(&mut self, visit: &mut dyn FnMut(&str, io::Result<&mut dyn Read>)) -> io::Result<()> {
+///         for (number, line) in self.0.lines().enumerate() {
+///             visit(&format!("line{}", number + 1), Ok(&mut line.as_bytes() as &mut dyn Read));
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let mut fs_index = FsIndex::new(0.00001);
+/// fs_index.ingest_from(Lines("word1\nword2"));
+/// assert_eq!(vec!["line2"], fs_index.search("word2").unwrap());
+/// ```
+pub trait DataSource {
+    /// Enumerate the items, calling `visit` with the key of each one and a reader of its content, or the error opening it.
+    ///
+    /// Return an error if the items cannot be enumerated.
+    fn visit(&mut self, visit: &mut dyn FnMut(&str, io::Result<&mut dyn Read>)) -> io::Result<()>;
+}
+
+impl<D: DataSource + ?Sized> DataSource for &mut D {
+    fn visit(&mut self, visit: &mut dyn FnMut(&str, io::Result<&mut dyn Read>)) -> io::Result<()> {
+        (**self).visit(visit)
+    }
+}
+
+/// The files of a directory, keyed by their path, optionally with the files of its subdirectories.
+pub struct DirectorySource {
+    path: PathBuf,
+    recursive: bool,
+    file_system: Arc<dyn FileSystem>
+}
+
+impl DirectorySource {
+    /// Constructs the source of the files at the first level of the directory at `path`, on the real file system.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        DirectorySource { path: path.as_ref().to_path_buf(), recursive: false, file_system: Arc::new(RealFileSystem) }
+    }
+
+    /// The same source, with the files of the subdirectories.
+    pub fn recursive(self) -> Self {
+        DirectorySource { recursive: true, ..self }
+    }
+
+    /// The same source, reading the files from `file_system`.
+    pub fn file_system<F: FileSystem + 'static>(self, file_system: F) -> Self {
+        DirectorySource { file_system: Arc::new(file_system), ..self }
+    }
+}
+
+impl DataSource for DirectorySource {
+    fn visit(&mut self, visit: &mut dyn FnMut(&str, io::Result<&mut dyn Read>)) -> io::Result<()> {
+        let mut directories = vec![self.path.clone()];
+        while let Some(directory) = directories.pop() {
+            let mut entries = self.file_system.read_dir(&directory)?;
+            entries.sort();
+            for entry in entries {
+                let key = entry.to_string_lossy();
+                match self.file_system.metadata(&entry) {
+                    Ok(metadata) if metadata.is_file => match self.file_system.open(&entry) {
+                        Ok(mut reader) => visit(&key, Ok(&mut reader as &mut dyn Read)),
+                        Err(error) => visit(&key, Err(error))
+                    },
+                    Ok(metadata) if metadata.is_dir && self.recursive => directories.push(entry.clone()),
+                    Ok(_) => (),
+                    Err(error) => visit(&key, Err(error))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A single document read from a reader, e.g. the standard input.
+///
+/// With the `compressed` feature (enabled by default), gzip, zstd and xz content is decompressed.
+pub struct ReaderSource<R> {
+    key: String,
+    reader: Option<R>
+}
+
+impl<R: Read> ReaderSource<R> {
+    /// Constructs the source of the document `key` read from `reader`.
+    pub fn new(key: &str, reader: R) -> Self {
+        ReaderSource { key: key.to_string(), reader: Some(reader) }
+    }
+}
+
+impl ReaderSource<io::Stdin> {
+    /// Constructs the source of the document `key` read from the standard input.
+    pub fn stdin(key: &str) -> Self {
+        ReaderSource::new(key, io::stdin())
+    }
+}
+
+impl<R: Read> DataSource for ReaderSource<R> {
+    fn visit(&mut self, visit: &mut dyn FnMut(&str, io::Result<&mut dyn Read>)) -> io::Result<()> {
+        // A reader is consumed by its first visit
+        if let Some(reader) = self.reader.take() {
+            #[cfg(feature = "compressed")]
+            let reader = decompressing(reader);
+            #[cfg(not(feature = "compressed"))]
+            let reader: io::Result<R> = Ok(reader);
+            match reader {
+                Ok(mut reader) => visit(&self.key, Ok(&mut reader as &mut dyn Read)),
+                Err(error) => visit(&self.key, Err(error))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The entries of a tar archive, optionally compressed with gzip or zstd, keyed `archive!entry`.
+///
+/// Entries are streamed through the decompressor, so the archive is never held in memory.
+#[cfg(feature = "archive")]
+pub struct ArchiveSource {
+    path: PathBuf,
+    file_system: Arc<dyn FileSystem>
+}
+
+#[cfg(feature = "archive")]
+impl ArchiveSource {
+    /// Constructs the source of the entries of the archive at `path`, on the real file system.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        ArchiveSource { path: path.as_ref().to_path_buf(), file_system: Arc::new(RealFileSystem) }
+    }
+
+    /// The same source, reading the archive from `file_system`.
+    pub fn file_system<F: FileSystem + 'static>(self, file_system: F) -> Self {
+        ArchiveSource { file_system: Arc::new(file_system), ..self }
+    }
+}
+
+#[cfg(feature = "archive")]
+impl DataSource for ArchiveSource {
+    fn visit(&mut self, visit: &mut dyn FnMut(&str, io::Result<&mut dyn Read>)) -> io::Result<()> {
+        let reader = archive::decompressed(&self.path, self.file_system.open(&self.path)?)?;
+        let mut tar_archive = tar::Archive::new(reader);
+        for entry in tar_archive.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let key = format!("{}!{}", self.path.display(), entry.path()?.to_string_lossy());
+            visit(&key, Ok(&mut entry as &mut dyn Read));
+        }
+        Ok(())
+    }
+}
+
+/// The resource at a URL, keyed by the URL.
+#[cfg(feature = "crawl")]
+pub struct UrlSource {
+    url: String
+}
+
+#[cfg(feature = "crawl")]
+impl UrlSource {
+    /// Constructs the source of the resource at `url`.
+    pub fn new(url: &str) -> Self {
+        UrlSource { url: url.to_string() }
+    }
+}
+
+#[cfg(feature = "crawl")]
+impl DataSource for UrlSource {
+    fn visit(&mut self, visit: &mut dyn FnMut(&str, io::Result<&mut dyn Read>)) -> io::Result<()> {
+        match ureq::get(&self.url).call() {
+            Ok(response) => visit(&self.url, Ok(&mut response.into_reader() as &mut dyn Read)),
+            Err(error) => visit(&self.url, Err(io::Error::new(io::ErrorKind::Other, error.to_string())))
+        }
+        Ok(())
+    }
+}
+
+impl<S: IndexStore> FsIndex<S> {
+    /// Ingest the items of `source`, each a document replacing any document with the same key.
+    ///
+    /// The text of the items is extracted according to the extension of their key, see [`FsIndex::register_extractor`].
+    /// Items which are not text are listed as skipped in the report, items which cannot be read as failures, with their key as path.
+    /// The documents have no source file, so they are never pruned nor refreshed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the items of `source` cannot be enumerated or if an item cannot be inserted in the index.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use cli_bloom::{FsIndex, MemoryFileSystem, DirectorySource};
+    /// let files = MemoryFileSystem::default();
+    /// files.insert("/foo/bar/baz.txt", "word1 word2");
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// fs_index.ingest_from(DirectorySource::new("/foo").recursive().file_system(files));
+    /// assert_eq!(vec!["/foo/bar/baz.txt"], fs_index.search("word2").unwrap());
+    /// ```
+    pub fn ingest_from<D: DataSource>(&mut self, mut source: D) -> IngestReport {
+        let mut report = IngestReport::default();
+        let enumerated = source.visit(&mut |key, reader| {
+            if self.cancelled(&mut report) {
+                return;
+            }
+            let path = Path::new(key);
+            self.observe(|observer| observer.on_file_start(path));
+            let mut content = Vec::new();
+            if let Err(error) = reader.and_then(|reader| reader.read_to_end(&mut content)) {
+                self.observe(|observer| observer.on_error(path, &error.to_string()));
+                report.failed.push(Issue { path: PathBuf::from(key), reason: error.to_string() });
+                return;
+            }
+            let size = content.len() as u64;
+            let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+            let (skipped, reason) = match self.extractors().extract(None, extension, content) {
+                Ok(text) if !text.contains('\0') => {
+                    self.insert_text(key, &text);
+                    report.indexed += 1;
+                    report.bytes += size;
+                    self.observe(|observer| observer.on_file_done(path, Outcome::Ingested, size));
+                    return;
+                },
+                Ok(_) => (true, "binary content".to_string()),
+                Err(error) if error.kind() == io::ErrorKind::InvalidData => (true, error.to_string()),
+                Err(error) => (false, error.to_string())
+            };
+            if skipped {
+                self.observe(|observer| observer.on_skip(path, &reason));
+                report.skipped.push(Issue { path: PathBuf::from(key), reason });
+            } else {
+                self.observe(|observer| observer.on_error(path, &reason));
+                report.failed.push(Issue { path: PathBuf::from(key), reason });
+            }
+        });
+        if let Err(error) = enumerated {
+            panic!("Unable to enumerate the items of the source : {}", error);
+        }
+        self.observe(|observer| observer.on_finish(&report));
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::MemoryFileSystem;
+
+    #[test]
+    fn ingest_from_directory_source() {
+        let files = MemoryFileSystem::default();
+        files.insert("/foo/a.txt", "word1");
+        files.insert("/foo/b.bin", b"\x00\xff".to_vec());
+        files.insert("/foo/bar/c.txt", "word2");
+        let mut fs_index = FsIndex::new(0.00001);
+        let report = fs_index.ingest_from(DirectorySource::new("/foo").file_system(files.clone()));
+        assert_eq!(1, report.indexed);
+        assert_eq!(1, report.skipped.len());
+        assert_eq!(None, fs_index.search("word2"));
+        fs_index.ingest_from(DirectorySource::new("/foo").recursive().file_system(files));
+        assert_eq!(vec!["/foo/bar/c.txt"], fs_index.search("word2").unwrap());
+    }
+
+    #[test]
+    fn ingest_from_reader_source() {
+        let mut fs_index = FsIndex::new(0.00001);
+        let mut source = ReaderSource::new("doc", &b"word1 word2"[..]);
+        assert_eq!(1, fs_index.ingest_from(&mut source).indexed);
+        // The reader is consumed
+        assert_eq!(0, fs_index.ingest_from(&mut source).indexed);
+        assert_eq!(vec!["doc"], fs_index.search("word2").unwrap());
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+use postgres::{Client, NoTls, SimpleQueryMessage};
+use crate::fs_loader::FsIndex;
+use crate::stats::{IngestReport, Issue};
+use crate::store::IndexStore;
+
+impl<S: IndexStore> FsIndex<S> {
+    /// Ingest the rows returned by `query` on the PostgreSQL database at `url`, e.g. `postgres://user@localhost/blog`.
+    ///
+    /// Each row is a document keyed by its first column, with the text of the other columns as content, NULL values left out.
+    /// Rows whose first column is NULL are listed as skipped in the report, with the row number as path.
+    /// The documents have no source file, so they are never pruned nor refreshed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the database cannot be reached, if the query fails or if a row cannot be inserted in the index.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use cli_bloom::FsIndex;
+    /// let mut fs_index = FsIndex::new(0.00001);
+    /// let report = fs_index.ingest_query("postgres://localhost/blog", "SELECT id, title, body FROM articles");
+    /// println!("{} rows indexed", report.indexed);
+    /// ```
+    pub fn ingest_query(&mut self, url: &str, query: &str) -> IngestReport {
+        let mut client = Client::connect(url, NoTls).unwrap_or_else(|error| panic!("Unable to connect to {} : {}", url, error));
+        let messages = client.simple_query(query).unwrap_or_else(|error| panic!("Query failed : {}", error));
+        let mut report = IngestReport::default();
+        let mut documents = Vec::new();
+        let rows = messages.iter().filter_map(|message| match message {
+            SimpleQueryMessage::Row(row) => Some(row),
+            _ => None
+        });
+        for (number, row) in rows.enumerate() {
+            let values: Vec<Option<&str>> = (0..row.len()).map(|column| row.get(column)).collect();
+            match row_document(&values) {
+                Some((key, content)) => {
+                    report.indexed += 1;
+                    report.bytes += content.len() as u64;
+                    documents.push((key, content));
+                },
+                None => report.skipped.push(Issue { path: PathBuf::from(format!("row {}", number + 1)), reason: "NULL key".to_string() })
+            }
+        }
+        self.ingest_map(documents);
+        report
+    }
+}
+
+// Key and content of a row, or `None` if its first column is NULL
+fn row_document(values: &[Option<&str>]) -> Option<(String, String)> {
+    let (key, columns) = values.split_first()?;
+    let content: Vec<&str> = columns.iter().flatten().copied().collect();
+    Some(((*key)?.to_string(), content.join(" ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn document_of_row() {
+        assert_eq!(Some(("42".to_string(), "title word1 word2".to_string())), row_document(&[Some("42"), Some("title"), None, Some("word1 word2")]));
+        assert_eq!(Some(("42".to_string(), String::new())), row_document(&[Some("42")]));
+        assert_eq!(None, row_document(&[None, Some("title")]));
+        assert_eq!(None, row_document(&[]));
+    }
+}
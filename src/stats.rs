@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+use serde::Serialize;
+
+/// Summary of the documents held by a `FsIndex`.
+///
+/// See [`FsIndex::stats`](crate::FsIndex::stats).
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Stats {
+    /// Number of documents in the index.
+    pub documents: usize,
+    /// Documents whose source content is unchanged.
+    pub fresh: usize,
+    /// Documents whose source content changed since ingestion.
+    pub modified: usize,
+    /// Documents whose source does not exist anymore.
+    pub missing: usize,
+    /// Documents whose freshness cannot be determined.
+    pub unknown: usize,
+    /// Tokens of the documents counted at ingestion, see [`Document::tokens`](crate::Document::tokens).
+    pub tokens: usize,
+    /// Distinct tokens of each document counted at ingestion, summed over the documents.
+    pub unique_tokens: usize
+}
+
+/// Outcome of [`FsIndex::refresh`](crate::FsIndex::refresh).
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Refresh {
+    /// Keys of the documents removed because their source does not exist anymore.
+    pub pruned: Vec<String>,
+    /// Keys of the documents ingested again because their source changed.
+    pub updated: Vec<String>
+}
+
+/// Documents changed from an index to another, see [`FsIndex::diff`](crate::FsIndex::diff).
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Changes {
+    /// Keys of the documents only in the other index.
+    pub added: Vec<String>,
+    /// Keys of the documents only in the compared index.
+    pub removed: Vec<String>,
+    /// Keys of the documents of both indexes whose content hash differs.
+    pub modified: Vec<String>
+}
+
+/// Outcome of [`FsIndex::compact`](crate::FsIndex::compact).
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Compaction {
+    /// Keys of the documents whose filter was rebuilt.
+    pub rebuilt: Vec<String>,
+    /// Keys of the documents whose filter is oversized or saturated, but whose content cannot be read again.
+    pub skipped: Vec<String>,
+    /// Number of bits of the rebuilt filters before compaction.
+    pub bits_before: usize,
+    /// Number of bits of the rebuilt filters after compaction.
+    pub bits_after: usize
+}
+
+/// Outcome of an ingestion, see [`FsIndex::ingest`](crate::FsIndex::ingest).
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct IngestReport {
+    /// Number of files whose content was indexed.
+    pub indexed: usize,
+    /// Number of files already indexed with the same content.
+    pub unchanged: usize,
+    /// Number of bytes read from indexed and unchanged files.
+    pub bytes: u64,
+    /// Files left out, e.g. because they are not text files.
+    pub skipped: Vec<Issue>,
+    /// Files which could not be ingested.
+    pub failed: Vec<Issue>,
+    /// Whether the ingestion was cancelled before processing every file, see [`FsIndex::set_cancel_flag`](crate::FsIndex::set_cancel_flag).
+    pub cancelled: bool
+}
+
+impl IngestReport {
+    /// Add the counts and issues of `other` to this report.
+    pub fn merge(&mut self, other: IngestReport) {
+        self.indexed += other.indexed;
+        self.unchanged += other.unchanged;
+        self.bytes += other.bytes;
+        self.skipped.extend(other.skipped);
+        self.failed.extend(other.failed);
+        self.cancelled |= other.cancelled;
+    }
+}
+
+/// Outcome of a search which may have been stopped early, see [`FsIndex::query_within`](crate::FsIndex::query_within).
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Hits<'a> {
+    /// Keys of the matching documents found, in ascending order.
+    pub keys: Vec<&'a String>,
+    /// Whether the search was stopped before evaluating every document, so matching documents may be missing.
+    pub truncated: bool
+}
+
+/// A file which was not ingested, and why.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Issue {
+    /// Path of the file.
+    pub path: PathBuf,
+    /// Description of the problem.
+    pub reason: String
+}
@@ -0,0 +1,165 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::path::Path;
+use std::path::PathBuf;
+use crate::document::content_hash;
+use crate::filter::Filter;
+
+// Bookkeeping cost of an in memory filter on top of its bits
+pub(crate) const ENTRY_OVERHEAD: usize = 128;
+
+/// Storage of the per-document filters of a `FsIndex`.
+///
+/// A `FsIndex` keeps the document keys and metadata, and delegates the filters to its store.
+/// Implement this trait to persist filters somewhere else than in memory.
+///
+/// # Example
+///
+/// ```
+/// # use cli_bloom::{FsIndex, MemoryStore};
+/// let mut fs_index = FsIndex::with_store(0.00001, MemoryStore::default());
+/// ```
+pub trait IndexStore {
+    /// Return the filter of the document `key`, if any.
+    fn get(&self, key: &str) -> io::Result<Option<Cow<'_, Filter>>>;
+
+    /// Insert the filter of the document `key`, replacing the previous one.
+    fn insert(&mut self, key: &str, filter: Filter) -> io::Result<()>;
+
+    /// Remove the filter of the document `key`.
+    fn remove(&mut self, key: &str) -> io::Result<()>;
+
+    /// Return the keys of all stored filters.
+    fn keys(&self) -> io::Result<Vec<String>>;
+
+    /// Return an estimate of the memory used by the filters, in bytes.
+    ///
+    /// Stores keeping filters outside of memory only count their caches, the default implementation returns 0.
+    fn memory_usage(&self) -> usize {
+        0
+    }
+}
+
+/// An `IndexStore` keeping all filters in memory.
+///
+/// This is the store of a `FsIndex` created with [`FsIndex::new`](crate::FsIndex::new).
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+    filters: HashMap<String, Filter>
+}
+
+impl IndexStore for MemoryStore {
+    fn get(&self, key: &str) -> io::Result<Option<Cow<'_, Filter>>> {
+        Ok(self.filters.get(key).map(Cow::Borrowed))
+    }
+
+    fn insert(&mut self, key: &str, filter: Filter) -> io::Result<()> {
+        self.filters.insert(key.to_string(), filter);
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &str) -> io::Result<()> {
+        self.filters.remove(key);
+        Ok(())
+    }
+
+    fn keys(&self) -> io::Result<Vec<String>> {
+        Ok(self.filters.keys().cloned().collect())
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.filters.iter().map(|(key, filter)| filter.bits() / 8 + key.len() + ENTRY_OVERHEAD).sum()
+    }
+}
+
+/// An `IndexStore` keeping each filter in its own file of a directory.
+///
+/// Filters are read from disk only when a search evaluates them.
+///
+/// # Example
+///
+/// ```no_run
+/// # use cli_bloom::{FsIndex, DirectoryStore};
+/// let store = DirectoryStore::open("/foo/filters").unwrap();
+/// let mut fs_index = FsIndex::with_store(0.00001, store);
+/// ```
+pub struct DirectoryStore {
+    path: PathBuf
+}
+
+impl DirectoryStore {
+    /// Use the directory at `path`, creating it if missing.
+    ///
+    /// # Errors
+    ///
+    /// Return an error if the directory cannot be created.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::create_dir_all(&path)?;
+        Ok(DirectoryStore {
+            path: path.as_ref().to_path_buf()
+        })
+    }
+
+    // A filter file holds the key as a JSON string on the first line, then the filter
+    fn filter_path(&self, key: &str) -> PathBuf {
+        self.path.join(content_hash(key.as_bytes()))
+    }
+}
+
+impl IndexStore for DirectoryStore {
+    fn get(&self, key: &str) -> io::Result<Option<Cow<'_, Filter>>> {
+        let content = match fs::read_to_string(self.filter_path(key)) {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error)
+        };
+        match content.splitn(2, '\n').nth(1) {
+            Some(serialized) => Ok(Some(Cow::Owned(Filter::from_json(serialized)))),
+            None => Err(io::Error::new(io::ErrorKind::InvalidData, format!("Invalid filter file for {}", key)))
+        }
+    }
+
+    fn insert(&mut self, key: &str, filter: Filter) -> io::Result<()> {
+        let serialized_key = serde_json::to_string(key)?;
+        fs::write(self.filter_path(key), format!("{}\n{}", serialized_key, filter.to_json()))
+    }
+
+    fn remove(&mut self, key: &str) -> io::Result<()> {
+        match fs::remove_file(self.filter_path(key)) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(())
+        }
+    }
+
+    fn keys(&self) -> io::Result<Vec<String>> {
+        let mut keys = Vec::new();
+        for entry in fs::read_dir(&self.path)? {
+            let mut line = String::new();
+            BufReader::new(File::open(entry?.path())?).read_line(&mut line)?;
+            keys.push(serde_json::from_str(&line)?);
+        }
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FsIndex;
+
+    #[test]
+    fn reopen_directory_index() {
+        let mut path = std::env::temp_dir();
+        path.push("bloom_directory_store");
+        let mut index = FsIndex::with_store(0.01, DirectoryStore::open(&path).unwrap());
+        index.ingest("./test/data/simple_directory");
+        let index = FsIndex::with_store(0.01, DirectoryStore::open(&path).unwrap());
+        assert_eq!(vec!["./test/data/simple_directory/file2.txt"], index.search("word4").unwrap());
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
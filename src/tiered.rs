@@ -0,0 +1,159 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::io;
+use std::sync::Mutex;
+use crate::filter::Filter;
+use crate::store::{IndexStore, ENTRY_OVERHEAD};
+
+/// An `IndexStore` keeping recently used filters in memory in front of another store.
+///
+/// Every filter is written to the `cold` store. Filters recently ingested or evaluated by a search also stay in memory,
+/// and the least recently used ones are dropped from memory once their total size exceeds the memory budget.
+///
+/// # Example
+///
+/// ```no_run
+/// # use cli_bloom::{FsIndex, DirectoryStore, TieredStore};
+/// let cold = DirectoryStore::open("/foo/filters").unwrap();
+/// let mut fs_index = FsIndex::with_store(0.00001, TieredStore::new(cold, 64 * 1024 * 1024));
+/// ```
+pub struct TieredStore<C> {
+    cold: C,
+    hot: Mutex<Hot>,
+    budget: usize
+}
+
+#[derive(Default)]
+struct Hot {
+    filters: HashMap<String, HotFilter>,
+    recency: BTreeMap<u64, String>,
+    clock: u64,
+    used: usize
+}
+
+struct HotFilter {
+    filter: Filter,
+    size: usize,
+    last_use: u64
+}
+
+impl<C: IndexStore> TieredStore<C> {
+    /// Constructs a `TieredStore` in front of `cold`, keeping at most `budget` bytes of filters in memory.
+    pub fn new(cold: C, budget: usize) -> Self {
+        TieredStore {
+            cold,
+            hot: Mutex::new(Hot::default()),
+            budget
+        }
+    }
+
+    /// Return the number of filters kept in memory.
+    pub fn hot_len(&self) -> usize {
+        self.hot.lock().unwrap().filters.len()
+    }
+}
+
+impl Hot {
+    fn touch(&mut self, key: &str) -> Option<Filter> {
+        self.clock += 1;
+        let clock = self.clock;
+        let entry = self.filters.get_mut(key)?;
+        self.recency.remove(&entry.last_use);
+        entry.last_use = clock;
+        self.recency.insert(clock, key.to_string());
+        Some(entry.filter.clone())
+    }
+
+    fn insert(&mut self, key: &str, filter: Filter, budget: usize) {
+        self.remove(key);
+        let size = filter.bits() / 8 + key.len() + ENTRY_OVERHEAD;
+        self.clock += 1;
+        self.recency.insert(self.clock, key.to_string());
+        self.filters.insert(key.to_string(), HotFilter {
+            filter,
+            size,
+            last_use: self.clock
+        });
+        self.used += size;
+        while self.used > budget {
+            let oldest = match self.recency.keys().next() {
+                Some(last_use) => *last_use,
+                None => break
+            };
+            if let Some(key) = self.recency.remove(&oldest) {
+                self.remove(&key);
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.filters.remove(key) {
+            self.recency.remove(&entry.last_use);
+            self.used -= entry.size;
+        }
+    }
+}
+
+impl<C: IndexStore> IndexStore for TieredStore<C> {
+    fn get(&self, key: &str) -> io::Result<Option<Cow<'_, Filter>>> {
+        if let Some(filter) = self.hot.lock().unwrap().touch(key) {
+            return Ok(Some(Cow::Owned(filter)));
+        }
+        match self.cold.get(key)? {
+            Some(filter) => {
+                let filter = filter.into_owned();
+                self.hot.lock().unwrap().insert(key, filter.clone(), self.budget);
+                Ok(Some(Cow::Owned(filter)))
+            },
+            None => Ok(None)
+        }
+    }
+
+    fn insert(&mut self, key: &str, filter: Filter) -> io::Result<()> {
+        self.cold.insert(key, filter.clone())?;
+        self.hot.lock().unwrap().insert(key, filter, self.budget);
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &str) -> io::Result<()> {
+        self.hot.lock().unwrap().remove(key);
+        self.cold.remove(key)
+    }
+
+    fn keys(&self) -> io::Result<Vec<String>> {
+        self.cold.keys()
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.hot.lock().unwrap().used + self.cold.memory_usage()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FsIndex, MemoryStore};
+
+    #[test]
+    fn keep_filters_within_budget() {
+        let store = TieredStore::new(MemoryStore::default(), ENTRY_OVERHEAD + 100);
+        let mut index = FsIndex::with_store(0.01, store);
+        index.ingest("./test/data/simple_directory");
+        assert_eq!(vec!["./test/data/simple_directory/file1.txt"], index.search("word1").unwrap());
+        assert_eq!(vec!["./test/data/simple_directory/file2.txt"], index.search("word4").unwrap());
+    }
+
+    #[test]
+    fn evict_least_recently_used() {
+        let mut store = TieredStore::new(MemoryStore::default(), 2 * (ENTRY_OVERHEAD + 10));
+        store.insert("a", Filter::new(0.1, "word1").unwrap()).unwrap();
+        store.insert("b", Filter::new(0.1, "word2").unwrap()).unwrap();
+        store.get("a").unwrap();
+        store.insert("c", Filter::new(0.1, "word3").unwrap()).unwrap();
+        let hot = store.hot.lock().unwrap();
+        assert!(hot.filters.contains_key("a"));
+        assert!(!hot.filters.contains_key("b"));
+        assert!(hot.filters.contains_key("c"));
+    }
+}
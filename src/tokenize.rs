@@ -0,0 +1,114 @@
+use crate::membership::HashFunction;
+
+const ONES: u64 = 0x0101010101010101;
+const HIGH_BITS: u64 = 0x8080808080808080;
+
+// Call `f` with each normalized token of `text`, like `membership::tokens` but without allocating for tokens which are already normalized.
+// Words are scanned 8 bytes at a time, as long as they contain no byte which may be whitespace.
+pub(crate) fn for_each_token<F: FnMut(&str)>(text: &str, mut f: F) {
+    let bytes = text.as_bytes();
+    let mut buffer = String::new();
+    let mut position = 0;
+    loop {
+        position = skip_whitespace(text, position);
+        if position >= bytes.len() {
+            break;
+        }
+        let start = position;
+        position = word_end(text, position);
+        let word = &text[start..position];
+        if word.bytes().all(|byte| byte.is_ascii_lowercase() || byte.is_ascii_digit()) {
+            f(word);
+        } else {
+            buffer.clear();
+            buffer.extend(word.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase));
+            if !buffer.is_empty() {
+                f(&buffer);
+            }
+        }
+    }
+}
+
+/// Return the sorted hashes of the distinct normalized tokens of `text`, as inserted in the filters of the xor, cuckoo and counting backends.
+#[doc(hidden)]
+pub fn distinct_token_hashes(hash: HashFunction, text: &str) -> Vec<u64> {
+    let mut hashes = Vec::new();
+    for_each_token(text, |token| hashes.push(hash.hash(token, 0)));
+    hashes.sort_unstable();
+    hashes.dedup();
+    hashes
+}
+
+fn skip_whitespace(text: &str, mut position: usize) -> usize {
+    let bytes = text.as_bytes();
+    while position < bytes.len() {
+        let byte = bytes[position];
+        if byte.is_ascii() {
+            if !byte.is_ascii_whitespace() && byte != 0x0b {
+                break;
+            }
+            position += 1;
+        } else {
+            let c = text[position..].chars().next().unwrap();
+            if !c.is_whitespace() {
+                break;
+            }
+            position += c.len_utf8();
+        }
+    }
+    position
+}
+
+fn word_end(text: &str, mut position: usize) -> usize {
+    let bytes = text.as_bytes();
+    loop {
+        // Whitespace is either below 0x21 or not ASCII, words of plain ASCII are skipped a word at a time
+        while position + 8 <= bytes.len() {
+            let mut chunk = [0; 8];
+            chunk.copy_from_slice(&bytes[position..position + 8]);
+            let chunk = u64::from_le_bytes(chunk);
+            let below_space = chunk.wrapping_sub(ONES * 0x21) & !chunk & HIGH_BITS;
+            if below_space | (chunk & HIGH_BITS) != 0 {
+                break;
+            }
+            position += 8;
+        }
+        if position >= bytes.len() {
+            return bytes.len();
+        }
+        let byte = bytes[position];
+        if byte.is_ascii() {
+            if byte.is_ascii_whitespace() || byte == 0x0b {
+                return position;
+            }
+            position += 1;
+        } else {
+            let c = text[position..].chars().next().unwrap();
+            if c.is_whitespace() {
+                return position;
+            }
+            position += c.len_utf8();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::membership::tokens;
+
+    #[test]
+    fn same_tokens_as_split_whitespace() {
+        let text = "Word1, word2!\n ... Été\u{a0}long-lived_token\twordswithoutanyspace \u{b}x\u{2003}ÉTÉ  ";
+        let mut scanned = Vec::new();
+        for_each_token(text, |token| scanned.push(token.to_string()));
+        assert_eq!(tokens(text).collect::<Vec<String>>(), scanned);
+    }
+
+    #[test]
+    fn distinct_hashes() {
+        let hashes = distinct_token_hashes(HashFunction::Fnv, "word1 Word1 word2, word1");
+        assert_eq!(2, hashes.len());
+        assert!(hashes.contains(&HashFunction::Fnv.hash("word2", 0)));
+    }
+}
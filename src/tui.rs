@@ -0,0 +1,159 @@
+use std::fs;
+use std::io;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::{Frame, Terminal};
+use crate::fs_loader::FsIndex;
+use crate::query::Query;
+use crate::store::IndexStore;
+
+// Maximum number of matching lines in the preview
+const PREVIEW_LINES: usize = 200;
+
+struct Browser<'a, S> {
+    index: &'a FsIndex<S>,
+    input: String,
+    results: Vec<&'a String>,
+    keywords: Vec<String>,
+    error: Option<String>,
+    selected: ListState
+}
+
+impl<'a, S: IndexStore> Browser<'a, S> {
+    // Run the query being typed, keeping the previous results while it is invalid
+    fn update_results(&mut self) {
+        if self.input.trim().is_empty() {
+            self.results.clear();
+            self.keywords.clear();
+            self.error = None;
+        } else {
+            match Query::parse(&self.input) {
+                Ok(query) => {
+                    self.results = self.index.query(&query).unwrap_or_default();
+                    self.keywords = query.keywords().iter().map(|keyword| keyword.to_lowercase()).collect();
+                    self.error = None;
+                },
+                Err(error) => self.error = Some(error.to_string())
+            }
+        }
+        self.selected.select(if self.results.is_empty() { None } else { Some(0) });
+    }
+
+    fn select(&mut self, offset: isize) {
+        if let Some(selected) = self.selected.selected() {
+            let last = self.results.len() as isize - 1;
+            self.selected.select(Some((selected as isize + offset).clamp(0, last) as usize));
+        }
+    }
+
+    // Numbered lines of the selected file containing one of the keywords
+    fn preview(&self) -> Vec<Line<'static>> {
+        let path = self.selected.selected()
+            .and_then(|selected| self.index.document(self.results[selected]))
+            .and_then(|document| document.path.as_ref());
+        let content = match path.map(fs::read_to_string) {
+            Some(Ok(content)) => content,
+            Some(Err(error)) => return vec![Line::from(error.to_string())],
+            None => return Vec::new()
+        };
+        content.lines()
+            .enumerate()
+            .filter(|(_, line)| {
+                let line = line.to_lowercase();
+                self.keywords.iter().any(|keyword| line.contains(keyword.as_str()))
+            })
+            .take(PREVIEW_LINES)
+            .map(|(number, line)| Line::from(vec![
+                Span::styled(format!("{:>5} ", number + 1), Style::default().fg(Color::DarkGray)),
+                Span::raw(line.to_string())
+            ]))
+            .collect()
+    }
+}
+
+/// Browse the documents of `index` in the terminal.
+///
+/// Results are updated as the query is typed (see [`Query::parse`]), the arrow keys select a result,
+/// and the lines of the selected file containing the keywords are shown next to the results. Escape quits.
+///
+/// # Errors
+///
+/// Return an error if the terminal cannot be set up or read.
+pub fn browse<S: IndexStore>(index: &FsIndex<S>) -> io::Result<()> {
+    let mut browser = Browser {
+        index,
+        input: String::new(),
+        results: Vec::new(),
+        keywords: Vec::new(),
+        error: None,
+        selected: ListState::default()
+    };
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    let result = run(&mut terminal, &mut browser);
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
+
+fn run<S: IndexStore>(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, browser: &mut Browser<S>) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| draw(frame, browser))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Esc => return Ok(()),
+                KeyCode::Char(character) => {
+                    browser.input.push(character);
+                    browser.update_results();
+                },
+                KeyCode::Backspace => {
+                    browser.input.pop();
+                    browser.update_results();
+                },
+                KeyCode::Down => browser.select(1),
+                KeyCode::Up => browser.select(-1),
+                KeyCode::PageDown => browser.select(10),
+                KeyCode::PageUp => browser.select(-10),
+                _ => ()
+            }
+        }
+    }
+}
+
+fn draw<S: IndexStore>(frame: &mut Frame, browser: &mut Browser<S>) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(frame.size());
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(rows[1]);
+    let title = match &browser.error {
+        Some(error) => format!("Query: {}", error),
+        None => "Query (Esc to quit)".to_string()
+    };
+    let input = Paragraph::new(browser.input.as_str())
+        .block(Block::default().borders(Borders::ALL).title(title));
+    frame.render_widget(input, rows[0]);
+    let preview = Paragraph::new(browser.preview())
+        .block(Block::default().borders(Borders::ALL).title("Preview"));
+    frame.render_widget(preview, columns[1]);
+    let items: Vec<ListItem> = browser.results.iter().map(|key| ListItem::new(key.as_str())).collect();
+    let results = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(format!("{} results", browser.results.len())))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(results, columns[0], &mut browser.selected);
+    frame.set_cursor(rows[0].x + 1 + browser.input.chars().count() as u16, rows[0].y + 1);
+}
@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use crate::filter::{MIN_ERROR_RATE, bits_per_token};
+use crate::store::ENTRY_OVERHEAD;
+
+// Above this error rate, a search returns nearly every document
+const MAX_ERROR_RATE: f32 = 0.5;
+
+/// Pick the smallest error rate whose filters for `sources` fit in `memory_target` bytes.
+///
+/// The sources are scanned like [`FsIndex::ingest_many`](crate::FsIndex::ingest_many) would ingest them, to count the distinct tokens of every file.
+/// Files which cannot be read as UTF-8 text are not counted.
+/// The estimate ignores documents split from a file (e.g. notebook cells), which take slightly more memory.
+///
+/// # Panics
+///
+/// Panics if a source is not a file or a directory, or if `memory_target` is too small for the filters to be of any use.
+///
+/// # Example
+///
+/// ```
+/// # use cli_bloom::{FsIndex, auto_error_rate};
+/// # fn search_index()  {
+/// let error_rate = auto_error_rate(&["/foo/bar"], 64 * 1024 * 1024);
+/// let mut fs_index = FsIndex::new(error_rate);
+/// fs_index.ingest("/foo/bar");
+/// # }
+/// ```
+pub fn auto_error_rate<T: AsRef<str>>(sources: &[T], memory_target: usize) -> f32 {
+    let mut tokens = 0;
+    let mut overhead = 0;
+    for source in sources {
+        for (key, count) in scan_source(Path::new(source.as_ref())) {
+            tokens += count;
+            overhead += key.len() + ENTRY_OVERHEAD;
+        }
+    }
+    if tokens == 0 {
+        // Any error rate fits, keep the usual one
+        return 0.00001;
+    }
+    let bits = memory_target.saturating_sub(overhead) as f64 * 8.0;
+    // From bits per token = -ln(error rate) / ln(2)², the error rate spending all the bits
+    let error_rate = (-(bits / tokens as f64) * 2f64.ln() * 2f64.ln()).exp() as f32;
+    if error_rate > MAX_ERROR_RATE || bits == 0.0 {
+        panic!("Memory target of {} bytes is too small for {} tokens, at least {} bytes are needed", memory_target, tokens, overhead + (tokens as f64 * bits_per_token(MAX_ERROR_RATE) / 8.0).ceil() as usize);
+    }
+    error_rate.max(MIN_ERROR_RATE)
+}
+
+// Number of distinct tokens of each file of the source, keyed by path
+fn scan_source(source: &Path) -> Vec<(String, usize)> {
+    let paths = if source.is_file() {
+        vec![source.to_path_buf()]
+    } else if source.is_dir() {
+        let entries = fs::read_dir(source).expect(format!("Unable to read directory {}", source.display()).as_str());
+        entries.filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect()
+    } else {
+        panic!("source type must be file or directory");
+    };
+    paths.into_iter()
+        .filter_map(|path| {
+            let content = fs::read_to_string(&path).ok()?;
+            Some((path.to_string_lossy().into_owned(), distinct_tokens(&content)))
+        })
+        .collect()
+}
+
+// Tokens as normalized by the index: lowercase words without punctuation
+fn distinct_tokens(content: &str) -> usize {
+    content.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_lowercase())
+        .collect::<HashSet<String>>()
+        .len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn larger_target_gives_smaller_error_rate() {
+        // 5 tokens in 2 files, each file taking 166 bytes of overhead
+        let small = auto_error_rate(&["./test/data/simple_directory"], 336);
+        let large = auto_error_rate(&["./test/data/simple_directory"], 340);
+        assert!(large < small);
+        assert!(small <= MAX_ERROR_RATE && large >= MIN_ERROR_RATE);
+    }
+
+    #[test]
+    #[should_panic(expected="Memory target of 10 bytes is too small")]
+    fn target_too_small() {
+        auto_error_rate(&["./test/data/simple_directory"], 10);
+    }
+
+    #[test]
+    fn count_distinct_tokens() {
+        assert_eq!(3, distinct_tokens("Word1, word1 word2!\nword3"));
+    }
+}
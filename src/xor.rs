@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+use serde_json::value::RawValue;
+use crate::errors::Error;
+use crate::membership::{Backend, Membership, mix, HashFunction};
+use crate::tokenize::distinct_token_hashes;
+
+// Attempts with different seeds before giving up, each one succeeds with a probability close to 1
+const MAX_SEEDS: u64 = 1000;
+
+// An xor filter with fingerprints of up to 16 bits, built once from all the tokens of a document.
+// See Graf and Lemire, "Xor Filters: Faster and Smaller Than Bloom and Cuckoo Filters".
+#[derive(Serialize, Deserialize)]
+pub(crate) struct XorFilter {
+    #[serde(default, skip_serializing_if = "HashFunction::is_fnv")]
+    hash: HashFunction,
+    seed: u64,
+    fingerprint_bits: u32,
+    tokens: usize,
+    fingerprints: Vec<u16>
+}
+
+impl XorFilter {
+    pub(crate) fn new(hash: HashFunction, error_rate: f32, content: &str) -> Self {
+        // A token which is not in the filter matches when its fingerprint does, with a probability of 2^-bits
+        let fingerprint_bits = (-(error_rate as f64).log2()).ceil().max(1.0).min(16.0) as u32;
+        let keys = distinct_token_hashes(hash, content);
+        let capacity = (32 + (1.23 * keys.len() as f64).ceil() as usize) / 3 * 3;
+        for seed in 0..MAX_SEEDS {
+            if let Some(order) = peel(&keys, seed, capacity) {
+                let mut filter = XorFilter {
+                    hash,
+                    seed,
+                    fingerprint_bits,
+                    tokens: keys.len(),
+                    fingerprints: vec![0; capacity]
+                };
+                // Each key is assigned the last free slot among its three slots, in reverse peeling order
+                for (key, slot) in order.into_iter().rev() {
+                    let hash = mix(key ^ seed);
+                    let [h0, h1, h2] = filter.slots(hash);
+                    filter.fingerprints[slot] = filter.fingerprint(hash) ^ filter.fingerprints[h0] ^ filter.fingerprints[h1] ^ filter.fingerprints[h2];
+                }
+                return filter;
+            }
+        }
+        panic!("Impossible to build a xor filter of {} tokens", keys.len());
+    }
+
+    pub(crate) fn from_raw(raw: &RawValue) -> Self {
+        let mut tagged: BTreeMap<String, XorFilter> = serde_json::from_str(raw.get()).expect("Invalid xor filter");
+        tagged.remove("xor").expect("Invalid xor filter")
+    }
+
+    fn contains_token(&self, token: &str) -> bool {
+        let hash = mix(self.hash.hash(token, 0) ^ self.seed);
+        let [h0, h1, h2] = self.slots(hash);
+        self.fingerprint(hash) == self.fingerprints[h0] ^ self.fingerprints[h1] ^ self.fingerprints[h2]
+    }
+
+    // One slot in each third of the fingerprints
+    fn slots(&self, hash: u64) -> [usize; 3] {
+        slots(hash, self.fingerprints.len() / 3)
+    }
+
+    fn fingerprint(&self, hash: u64) -> u16 {
+        let mask = ((1u32 << self.fingerprint_bits) - 1) as u16;
+        (hash ^ (hash >> 32)) as u16 & mask
+    }
+}
+
+fn slots(hash: u64, segment: usize) -> [usize; 3] {
+    let reduce = |value: u64| ((value as u32 as u64 * segment as u64) >> 32) as usize;
+    [reduce(hash), segment + reduce(hash.rotate_left(21)), 2 * segment + reduce(hash.rotate_left(42))]
+}
+
+// Order in which keys can be removed from slots they are alone in, with these slots, if all of them can be
+fn peel(keys: &[u64], seed: u64, capacity: usize) -> Option<Vec<(u64, usize)>> {
+    let segment = capacity / 3;
+    let mut counts = vec![0u32; capacity];
+    let mut xors = vec![0u64; capacity];
+    for &key in keys {
+        for slot in slots(mix(key ^ seed), segment) {
+            counts[slot] += 1;
+            xors[slot] ^= key;
+        }
+    }
+    let mut alone: Vec<usize> = (0..capacity).filter(|&slot| counts[slot] == 1).collect();
+    let mut order = Vec::with_capacity(keys.len());
+    while let Some(slot) = alone.pop() {
+        if counts[slot] != 1 {
+            continue;
+        }
+        let key = xors[slot];
+        order.push((key, slot));
+        for other in slots(mix(key ^ seed), segment) {
+            counts[other] -= 1;
+            xors[other] ^= key;
+            if counts[other] == 1 {
+                alone.push(other);
+            }
+        }
+    }
+    if order.len() == keys.len() {
+        Some(order)
+    } else {
+        None
+    }
+}
+
+impl Membership for XorFilter {
+    fn contains_tokens(&self, tokens: &[String]) -> Result<bool, Error> {
+        Ok(tokens.iter().all(|token| self.contains_token(token)))
+    }
+
+    fn bits(&self) -> usize {
+        self.fingerprints.len() * self.fingerprint_bits as usize
+    }
+
+    fn hash_count(&self) -> usize {
+        3
+    }
+
+    fn fill_ratio(&self) -> f64 {
+        if self.fingerprints.is_empty() {
+            return 0.0;
+        }
+        self.tokens as f64 / self.fingerprints.len() as f64
+    }
+
+    fn false_positive_rate(&self) -> f64 {
+        0.5f64.powi(self.fingerprint_bits as i32)
+    }
+
+    fn backend(&self) -> Backend {
+        Backend::Xor
+    }
+
+    fn to_raw(&self) -> Box<RawValue> {
+        let mut tagged = BTreeMap::new();
+        tagged.insert("xor", self);
+        RawValue::from_string(serde_json::to_string(&tagged).expect("Impossible to serialize filter")).expect("Impossible to serialize filter")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xor_filter_contains_tokens() {
+        let content: Vec<String> = (0..1000).map(|position| format!("word{}", position)).collect();
+        let filter = XorFilter::new(HashFunction::Fnv, 0.001, &content.join(" "));
+        assert!(filter.contains("word1 Word999, word500").unwrap());
+        let false_positives = (1000..11000).filter(|position| filter.contains(&format!("word{}", position)).unwrap()).count();
+        assert!(false_positives < 50);
+        assert_eq!(10, filter.fingerprint_bits);
+    }
+
+    #[test]
+    fn xor_filter_raw_round_trip() {
+        let filter = XorFilter::new(HashFunction::Fnv, 0.01, "word1 word2");
+        let restored = XorFilter::from_raw(&filter.to_raw());
+        assert!(restored.contains("word2").unwrap());
+        assert!(!restored.contains("").unwrap());
+    }
+}